@@ -41,26 +41,29 @@ async fn main(){
     }
     
     tokio::spawn(async{
+        // Responses are one line of JSON each and may span many reads, bytes past a newline belong
+        // to the next response
+        let mut pending = Vec::new();
+
         unsafe{
             loop{
                 if let Some(stream_ref) = &mut STREAM{
                     let mut stream_ref = stream_ref.lock().await;
                     let mut buf = [0_u8; 4096];
-                     if let Ok(bytes) = stream_ref.read(&mut buf).await{
-                        if bytes == 0{
+                    match stream_ref.read(&mut buf).await{
+                        Ok(0) | Err(_) =>{
                             STREAM = None;
+                            continue;
                         }
+                        Ok(bytes) => pending.extend_from_slice(&buf[..bytes]),
                     }
-                    else{
-                        STREAM = None;
-                    }
-
-                    let package = serde_json::from_slice::<Package>(&buf[..buf.iter()
-                        .position(|x| *x == b'\n')
-                        .unwrap()
-                    ]).unwrap();
 
-                    PACKAGES.lock().unwrap().1.insert(package.id, package);
+                    while let Some(package_end) = pending.iter().position(|x| *x == b'\n'){
+                        let line = pending.drain(..=package_end).collect::<Vec<u8>>();
+                        if let Ok(package) = serde_json::from_slice::<Package>(&line[..package_end]){
+                            PACKAGES.lock().unwrap().1.insert(package.id, package);
+                        }
+                    }
                 }
             }
         }
@@ -0,0 +1,3 @@
+ALTER TABLE kanji DROP COLUMN components;
+DROP TABLE kanji_components;
+DROP TABLE radicals
@@ -0,0 +1,15 @@
+CREATE TABLE radicals (
+  id SERIAL PRIMARY KEY,
+  symbol TEXT NOT NULL UNIQUE,
+  stroke_count INT NOT NULL
+);
+CREATE TABLE kanji_components (
+  id SERIAL PRIMARY KEY,
+  kanji_symbol TEXT NOT NULL,
+  radical_id INT NOT NULL,
+  CONSTRAINT fk_radical
+    FOREIGN KEY(radical_id)
+     REFERENCES "radicals"(id)
+);
+CREATE INDEX kanji_components_symbol ON kanji_components(kanji_symbol);
+ALTER TABLE kanji ADD COLUMN components TEXT[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,5 @@
+CREATE TABLE kanjidic (
+  id SERIAL PRIMARY KEY,
+  symbol TEXT NOT NULL UNIQUE,
+  stroke_count INT NOT NULL
+);
@@ -0,0 +1,2 @@
+DROP TABLE vocab_readings;
+ALTER TABLE vocab DROP COLUMN exception
@@ -0,0 +1,14 @@
+ALTER TABLE vocab ADD COLUMN exception BOOLEAN NOT NULL DEFAULT FALSE;
+CREATE TABLE vocab_readings (
+  id SERIAL PRIMARY KEY,
+  reading TEXT NOT NULL,
+  position INT NOT NULL,
+  kanji_symbol TEXT NOT NULL,
+  kind TEXT NOT NULL,
+  kanji_reading TEXT NOT NULL,
+  vocab_id INT NOT NULL,
+  CONSTRAINT fk_vocab
+    FOREIGN KEY(vocab_id)
+     REFERENCES "vocab"(id)
+     ON DELETE CASCADE
+);
@@ -0,0 +1,9 @@
+ALTER TABLE kanji
+  DROP COLUMN frequency_rank,
+  DROP COLUMN grade,
+  DROP COLUMN jlpt_level,
+  DROP COLUMN stroke_count;
+ALTER TABLE kanjidic
+  DROP COLUMN frequency_rank,
+  DROP COLUMN grade,
+  DROP COLUMN old_jlpt_level
@@ -0,0 +1,10 @@
+-- KANJIDIC2's jlpt is the pre-2010 four level scale, not N5 to N1
+ALTER TABLE kanjidic
+  ADD COLUMN old_jlpt_level INT,
+  ADD COLUMN grade INT,
+  ADD COLUMN frequency_rank INT;
+ALTER TABLE kanji
+  ADD COLUMN stroke_count INT,
+  ADD COLUMN jlpt_level INT,
+  ADD COLUMN grade INT,
+  ADD COLUMN frequency_rank INT;
@@ -0,0 +1,2 @@
+DROP TABLE vocab_examples;
+DROP TABLE examples
@@ -0,0 +1,23 @@
+CREATE TABLE examples (
+  id SERIAL PRIMARY KEY,
+  sentence TEXT NOT NULL,
+  translation TEXT NOT NULL,
+  reading TEXT,
+  user_id INT NOT NULL,
+  CONSTRAINT fk_user
+    FOREIGN KEY(user_id)
+     REFERENCES "users"(id)
+);
+CREATE TABLE vocab_examples (
+  id SERIAL PRIMARY KEY,
+  vocab_id INT NOT NULL,
+  CONSTRAINT fk_vocab
+    FOREIGN KEY(vocab_id)
+     REFERENCES "vocab"(id)
+     ON DELETE CASCADE,
+  example_id INT NOT NULL,
+  CONSTRAINT fk_example
+    FOREIGN KEY(example_id)
+     REFERENCES "examples"(id)
+     ON DELETE CASCADE
+);
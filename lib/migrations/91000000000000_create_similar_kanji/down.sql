@@ -0,0 +1,2 @@
+DROP TABLE similar_kanji;
+DROP TABLE kanji_similarities
@@ -0,0 +1,19 @@
+CREATE TABLE kanji_similarities (
+  id SERIAL PRIMARY KEY,
+  kanji_symbol TEXT NOT NULL,
+  similar_symbol TEXT NOT NULL
+);
+CREATE INDEX kanji_similarities_symbol ON kanji_similarities(kanji_symbol);
+CREATE TABLE similar_kanji (
+  id SERIAL PRIMARY KEY,
+  kanji_id INT NOT NULL,
+  CONSTRAINT fk_kanji
+    FOREIGN KEY(kanji_id)
+     REFERENCES "kanji"(id)
+     ON DELETE CASCADE,
+  similar_id INT NOT NULL,
+  CONSTRAINT fk_similar
+    FOREIGN KEY(similar_id)
+     REFERENCES "kanji"(id)
+     ON DELETE CASCADE
+);
@@ -0,0 +1 @@
+DROP TABLE vocab_relations
@@ -0,0 +1,14 @@
+CREATE TABLE vocab_relations (
+  id SERIAL PRIMARY KEY,
+  kind TEXT NOT NULL,
+  vocab_id INT NOT NULL,
+  CONSTRAINT fk_vocab
+    FOREIGN KEY(vocab_id)
+     REFERENCES "vocab"(id)
+     ON DELETE CASCADE,
+  related_id INT NOT NULL,
+  CONSTRAINT fk_related
+    FOREIGN KEY(related_id)
+     REFERENCES "vocab"(id)
+     ON DELETE CASCADE
+);
@@ -0,0 +1,29 @@
+CREATE TABLE reviews (
+  id SERIAL PRIMARY KEY,
+  ease DOUBLE PRECISION NOT NULL,
+  interval_days INT NOT NULL,
+  due TIMESTAMPTZ NOT NULL,
+  repetitions INT NOT NULL,
+  lapses INT NOT NULL,
+  user_id INT NOT NULL,
+  CONSTRAINT fk_user
+    FOREIGN KEY(user_id)
+     REFERENCES "users"(id),
+  kanji_id INT,
+  CONSTRAINT fk_kanji
+    FOREIGN KEY(kanji_id)
+     REFERENCES "kanji"(id)
+     ON DELETE CASCADE,
+  vocab_id INT,
+  CONSTRAINT fk_vocab
+    FOREIGN KEY(vocab_id)
+     REFERENCES "vocab"(id)
+     ON DELETE CASCADE,
+  CONSTRAINT one_item
+    CHECK ((kanji_id IS NULL) <> (vocab_id IS NULL))
+);
+CREATE INDEX reviews_due ON reviews(user_id, due);
+INSERT INTO reviews (ease, interval_days, due, repetitions, lapses, user_id, kanji_id)
+  SELECT 2.5, 0, NOW(), 0, 0, user_id, id FROM kanji;
+INSERT INTO reviews (ease, interval_days, due, repetitions, lapses, user_id, vocab_id)
+  SELECT 2.5, 0, NOW(), 0, 0, user_id, id FROM vocab;
@@ -0,0 +1,9 @@
+DROP TABLE review_logs;
+ALTER TABLE reviews
+  DROP COLUMN last_review,
+  DROP COLUMN difficulty,
+  DROP COLUMN stability;
+ALTER TABLE users
+  DROP COLUMN fsrs_weights,
+  DROP COLUMN desired_retention,
+  DROP COLUMN algorithm
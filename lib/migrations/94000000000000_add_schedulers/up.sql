@@ -0,0 +1,24 @@
+ALTER TABLE users
+  ADD COLUMN algorithm TEXT NOT NULL DEFAULT 'sm2',
+  ADD COLUMN desired_retention DOUBLE PRECISION NOT NULL DEFAULT 0.9,
+  ADD COLUMN fsrs_weights DOUBLE PRECISION[];
+ALTER TABLE reviews
+  ADD COLUMN stability DOUBLE PRECISION,
+  ADD COLUMN difficulty DOUBLE PRECISION,
+  ADD COLUMN last_review TIMESTAMPTZ;
+CREATE TABLE review_logs (
+  id SERIAL PRIMARY KEY,
+  grade TEXT NOT NULL,
+  reviewed_at TIMESTAMPTZ NOT NULL,
+  elapsed_days DOUBLE PRECISION NOT NULL,
+  user_id INT NOT NULL,
+  CONSTRAINT fk_user
+    FOREIGN KEY(user_id)
+     REFERENCES "users"(id),
+  review_id INT NOT NULL,
+  CONSTRAINT fk_review
+    FOREIGN KEY(review_id)
+     REFERENCES "reviews"(id)
+     ON DELETE CASCADE
+);
+CREATE INDEX review_logs_review ON review_logs(review_id, reviewed_at);
@@ -0,0 +1,16 @@
+ALTER TABLE review_logs
+  DROP COLUMN session_id,
+  DROP COLUMN prior_last_review,
+  DROP COLUMN prior_difficulty,
+  DROP COLUMN prior_stability,
+  DROP COLUMN prior_lapses,
+  DROP COLUMN prior_repetitions,
+  DROP COLUMN prior_due,
+  DROP COLUMN prior_interval_days,
+  DROP COLUMN prior_ease,
+  DROP COLUMN undone,
+  DROP COLUMN response_ms,
+  DROP COLUMN card;
+ALTER TABLE reviews
+  DROP COLUMN card;
+DROP TABLE review_sessions
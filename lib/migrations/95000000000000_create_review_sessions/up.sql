@@ -0,0 +1,29 @@
+CREATE TABLE review_sessions (
+  id SERIAL PRIMARY KEY,
+  started_at TIMESTAMPTZ NOT NULL,
+  group_title TEXT,
+  user_id INT NOT NULL,
+  CONSTRAINT fk_user
+    FOREIGN KEY(user_id)
+     REFERENCES "users"(id)
+);
+ALTER TABLE reviews
+  ADD COLUMN card TEXT NOT NULL DEFAULT 'meaning';
+ALTER TABLE review_logs
+  ADD COLUMN card TEXT NOT NULL DEFAULT 'meaning',
+  ADD COLUMN response_ms INT,
+  ADD COLUMN undone BOOLEAN NOT NULL DEFAULT FALSE,
+  ADD COLUMN prior_ease DOUBLE PRECISION NOT NULL DEFAULT 2.5,
+  ADD COLUMN prior_interval_days INT NOT NULL DEFAULT 0,
+  ADD COLUMN prior_due TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  ADD COLUMN prior_repetitions INT NOT NULL DEFAULT 0,
+  ADD COLUMN prior_lapses INT NOT NULL DEFAULT 0,
+  ADD COLUMN prior_stability DOUBLE PRECISION,
+  ADD COLUMN prior_difficulty DOUBLE PRECISION,
+  ADD COLUMN prior_last_review TIMESTAMPTZ,
+  ADD COLUMN session_id INT,
+  ADD CONSTRAINT fk_session
+    FOREIGN KEY(session_id)
+     REFERENCES "review_sessions"(id)
+     ON DELETE SET NULL;
+CREATE INDEX review_logs_session ON review_logs(session_id, reviewed_at);
@@ -0,0 +1,5 @@
+DROP INDEX reviews_vocab_card;
+DROP INDEX reviews_kanji_card;
+DELETE FROM reviews WHERE card <> 'meaning';
+ALTER TABLE users
+  DROP COLUMN disabled_cards
@@ -0,0 +1,15 @@
+ALTER TABLE users
+  ADD COLUMN disabled_cards TEXT[] NOT NULL DEFAULT '{}';
+INSERT INTO reviews (ease, interval_days, due, repetitions, lapses, user_id, kanji_id, card)
+  SELECT 2.5, 0, NOW(), 0, 0, user_id, id, 'onyomi' FROM kanji
+    WHERE EXISTS (SELECT 1 FROM unnest(onyomi) AS reading WHERE btrim(reading) <> '');
+INSERT INTO reviews (ease, interval_days, due, repetitions, lapses, user_id, kanji_id, card)
+  SELECT 2.5, 0, NOW(), 0, 0, user_id, id, 'kunyomi' FROM kanji
+    WHERE EXISTS (SELECT 1 FROM unnest(kunyomi) AS reading WHERE btrim(reading) <> '');
+INSERT INTO reviews (ease, interval_days, due, repetitions, lapses, user_id, vocab_id, card)
+  SELECT 2.5, 0, NOW(), 0, 0, user_id, id, 'reading' FROM vocab
+    WHERE EXISTS (SELECT 1 FROM unnest(reading) AS reading WHERE btrim(reading) <> '');
+INSERT INTO reviews (ease, interval_days, due, repetitions, lapses, user_id, vocab_id, card)
+  SELECT 2.5, 0, NOW(), 0, 0, user_id, id, 'recall' FROM vocab;
+CREATE UNIQUE INDEX reviews_kanji_card ON reviews(kanji_id, card) WHERE kanji_id IS NOT NULL;
+CREATE UNIQUE INDEX reviews_vocab_card ON reviews(vocab_id, card) WHERE vocab_id IS NOT NULL;
@@ -0,0 +1,10 @@
+ALTER TABLE review_logs
+  DROP COLUMN suspended_reviews,
+  DROP COLUMN prior_suspended,
+  DROP COLUMN prior_leech;
+ALTER TABLE reviews
+  DROP COLUMN suspended,
+  DROP COLUMN leech;
+ALTER TABLE users
+  DROP COLUMN leech_action,
+  DROP COLUMN leech_threshold
@@ -0,0 +1,10 @@
+ALTER TABLE users
+  ADD COLUMN leech_threshold INT NOT NULL DEFAULT 8,
+  ADD COLUMN leech_action TEXT NOT NULL DEFAULT 'suspend';
+ALTER TABLE reviews
+  ADD COLUMN leech BOOLEAN NOT NULL DEFAULT FALSE,
+  ADD COLUMN suspended BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE review_logs
+  ADD COLUMN prior_leech BOOLEAN NOT NULL DEFAULT FALSE,
+  ADD COLUMN prior_suspended BOOLEAN NOT NULL DEFAULT FALSE,
+  ADD COLUMN suspended_reviews INT[] NOT NULL DEFAULT '{}';
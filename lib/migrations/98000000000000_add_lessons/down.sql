@@ -0,0 +1,6 @@
+ALTER TABLE vocab
+  DROP COLUMN learned_at;
+ALTER TABLE kanji
+  DROP COLUMN learned_at;
+ALTER TABLE users
+  DROP COLUMN lessons_per_day
@@ -0,0 +1,18 @@
+ALTER TABLE users
+  ADD COLUMN lessons_per_day INT NOT NULL DEFAULT 10;
+ALTER TABLE kanji
+  ADD COLUMN learned_at TIMESTAMPTZ;
+ALTER TABLE vocab
+  ADD COLUMN learned_at TIMESTAMPTZ;
+UPDATE kanji SET learned_at = (
+    SELECT COALESCE(MIN(review_logs.reviewed_at), MIN(reviews.last_review))
+    FROM reviews LEFT JOIN review_logs ON review_logs.review_id = reviews.id
+    WHERE reviews.kanji_id = kanji.id
+  )
+  WHERE EXISTS (SELECT 1 FROM reviews WHERE reviews.kanji_id = kanji.id AND reviews.last_review IS NOT NULL);
+UPDATE vocab SET learned_at = (
+    SELECT COALESCE(MIN(review_logs.reviewed_at), MIN(reviews.last_review))
+    FROM reviews LEFT JOIN review_logs ON review_logs.review_id = reviews.id
+    WHERE reviews.vocab_id = vocab.id
+  )
+  WHERE EXISTS (SELECT 1 FROM reviews WHERE reviews.vocab_id = vocab.id AND reviews.last_review IS NOT NULL);
@@ -0,0 +1,3 @@
+DROP TABLE review_actions;
+ALTER TABLE reviews
+  DROP COLUMN buried_until
@@ -0,0 +1,13 @@
+ALTER TABLE reviews
+  ADD COLUMN buried_until TIMESTAMPTZ;
+CREATE TABLE review_actions (
+  id SERIAL PRIMARY KEY,
+  action TEXT NOT NULL,
+  performed_at TIMESTAMPTZ NOT NULL,
+  snapshot TEXT NOT NULL,
+  undone BOOLEAN NOT NULL DEFAULT FALSE,
+  user_id INT NOT NULL,
+  CONSTRAINT fk_user
+    FOREIGN KEY(user_id)
+     REFERENCES "users"(id)
+);
@@ -0,0 +1,5 @@
+ALTER TABLE users
+  DROP COLUMN default_preset_id;
+ALTER TABLE groups
+  DROP COLUMN preset_id;
+DROP TABLE srs_presets
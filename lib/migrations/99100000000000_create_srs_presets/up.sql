@@ -0,0 +1,25 @@
+CREATE TABLE srs_presets (
+  id SERIAL PRIMARY KEY,
+  title TEXT NOT NULL,
+  new_per_day INT NOT NULL,
+  max_interval INT NOT NULL,
+  learning_steps INT[] NOT NULL,
+  ease_bonus DOUBLE PRECISION NOT NULL,
+  desired_retention DOUBLE PRECISION,
+  user_id INT NOT NULL,
+  CONSTRAINT fk_user
+    FOREIGN KEY(user_id)
+     REFERENCES "users"(id)
+);
+ALTER TABLE groups
+  ADD COLUMN preset_id INT,
+  ADD CONSTRAINT fk_preset
+    FOREIGN KEY(preset_id)
+     REFERENCES "srs_presets"(id)
+     ON DELETE SET NULL;
+ALTER TABLE users
+  ADD COLUMN default_preset_id INT,
+  ADD CONSTRAINT fk_default_preset
+    FOREIGN KEY(default_preset_id)
+     REFERENCES "srs_presets"(id)
+     ON DELETE SET NULL;
@@ -0,0 +1,4 @@
+ALTER TABLE kanjidic
+  DROP COLUMN kunyomi,
+  DROP COLUMN onyomi,
+  DROP COLUMN meanings;
@@ -0,0 +1,4 @@
+ALTER TABLE kanjidic
+  ADD COLUMN meanings TEXT[] NOT NULL DEFAULT '{}',
+  ADD COLUMN onyomi TEXT[] NOT NULL DEFAULT '{}',
+  ADD COLUMN kunyomi TEXT[] NOT NULL DEFAULT '{}';
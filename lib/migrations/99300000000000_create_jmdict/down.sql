@@ -0,0 +1,4 @@
+ALTER TABLE vocab DROP COLUMN parts_of_speech;
+DROP TABLE jmdict_senses;
+DROP TABLE jmdict_forms;
+DROP TABLE jmdict_entries
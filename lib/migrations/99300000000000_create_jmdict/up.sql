@@ -0,0 +1,29 @@
+CREATE TABLE jmdict_entries (
+  id INT PRIMARY KEY,
+  common BOOLEAN NOT NULL
+);
+CREATE TABLE jmdict_forms (
+  id SERIAL PRIMARY KEY,
+  text TEXT NOT NULL,
+  reading BOOLEAN NOT NULL,
+  common BOOLEAN NOT NULL,
+  restrictions TEXT[] NOT NULL,
+  entry_id INT NOT NULL,
+  CONSTRAINT fk_entry
+    FOREIGN KEY(entry_id)
+     REFERENCES "jmdict_entries"(id)
+     ON DELETE CASCADE
+);
+CREATE INDEX jmdict_forms_text ON jmdict_forms(text);
+CREATE TABLE jmdict_senses (
+  id SERIAL PRIMARY KEY,
+  position INT NOT NULL,
+  glosses TEXT[] NOT NULL,
+  parts_of_speech TEXT[] NOT NULL,
+  entry_id INT NOT NULL,
+  CONSTRAINT fk_entry
+    FOREIGN KEY(entry_id)
+     REFERENCES "jmdict_entries"(id)
+     ON DELETE CASCADE
+);
+ALTER TABLE vocab ADD COLUMN parts_of_speech TEXT[] NOT NULL DEFAULT '{}';
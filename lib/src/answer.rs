@@ -0,0 +1,218 @@
+use serde::{Serialize, Deserialize};
+use crate::kana::*;
+
+// Words dropped from the front of a meaning before comparing, so "to eat" matches "eat"
+const FILLER_PREFIXES: [&str; 4] = ["to ", "a ", "an ", "the "];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict{
+    Correct,
+    // A typo away from an accepted meaning
+    Close,
+    Wrong,
+    // A reading of the item, just not of the kind the card asks for
+    WrongKind,
+}
+
+// Reading in plain hiragana, with the okurigana dot of dictionary readings dropped. Their affix
+// dashes are trimmed by reading_forms beforehand, a dash typed in romaji is the long vowel mark
+pub fn normalize_reading(reading: &str)-> String{
+    to_hiragana(&romaji_to_hiragana(reading.trim()))
+        .chars()
+        .filter(|c| !matches!(c, '.' | ' ' | '　'))
+        .collect()
+}
+
+// Forms a dictionary reading may be typed in, a kun'yomi like た.べる is accepted as たべる or た
+fn reading_forms(reading: &str)-> Vec<String>{
+    let reading = reading.trim_matches('-');
+    let mut forms = vec![normalize_reading(reading)];
+
+    if let Some((stem, _)) = reading.split_once('.'){
+        forms.push(normalize_reading(stem));
+    }
+
+    forms.retain(|form| !form.is_empty());
+    forms
+}
+
+// Compares a typed reading, romaji or kana, against the readings the card asks for and the item's other readings
+pub fn check_reading(answer: &str, expected: &[String], other: &[String])-> Verdict{
+    let answer = normalize_reading(answer);
+    let matches = |readings: &[String]| readings.iter()
+        .any(|reading| reading_forms(reading).contains(&answer));
+
+    if answer.is_empty(){
+        Verdict::Wrong
+    }
+    else if matches(expected){
+        Verdict::Correct
+    }
+    else if matches(other){
+        Verdict::WrongKind
+    }
+    else{
+        Verdict::Wrong
+    }
+}
+
+// Lowercased meaning with punctuation, parenthesised notes and leading fillers removed
+pub fn normalize_meaning(meaning: &str)-> String{
+    let mut depth = 0;
+    let stripped = meaning.to_lowercase()
+        .chars()
+        .filter_map(|c|{
+            match c{
+                '(' => depth += 1,
+                ')' => depth = (depth - 1).max(0),
+                _ if depth > 0 => (),
+                c if c.is_alphanumeric() => return Some(c),
+                _ => return Some(' '),
+            }
+
+            None
+        })
+        .collect::<String>();
+
+    let mut normalized = stripped.split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ");
+    while let Some(prefix) = FILLER_PREFIXES.iter().find(|prefix| normalized.starts_with(**prefix)){
+        normalized = normalized[prefix.len()..].to_owned();
+    }
+
+    normalized
+}
+
+// Each of the meanings listed in a meaning field, e.g. "eat, consume; have a meal"
+pub fn accepted_meanings(meaning: &str)-> Vec<String>{
+    meaning.split([',', ';', '/'])
+        .map(normalize_meaning)
+        .filter(|meaning| !meaning.is_empty())
+        .collect()
+}
+
+pub fn edit_distance(a: &str, b: &str)-> usize{
+    let b = b.chars().collect::<Vec<char>>();
+    let mut previous = (0..=b.len()).collect::<Vec<usize>>();
+
+    for (i, a_char) in a.chars().enumerate(){
+        let mut current = vec![i + 1];
+
+        for (j, b_char) in b.iter().enumerate(){
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+// Typos forgiven in a meaning of the given length, none for short words where one letter changes the word
+fn typo_allowance(len: usize)-> usize{
+    match len{
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+pub fn check_meaning(answer: &str, accepted: &[String])-> Verdict{
+    let answer = normalize_meaning(answer);
+    if answer.is_empty(){
+        return Verdict::Wrong;
+    }
+
+    if accepted.contains(&answer){
+        return Verdict::Correct;
+    }
+
+    let close = accepted.iter().any(|meaning|{
+        edit_distance(&answer, meaning) <= typo_allowance(meaning.chars().count())
+    });
+
+    if close{ Verdict::Close } else{ Verdict::Wrong }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn accepted(meaning: &str)-> Vec<String>{
+        accepted_meanings(meaning)
+    }
+
+    #[test]
+    fn meanings_match_after_normalizing(){
+        let meanings = accepted("to eat, consume; have a meal");
+
+        assert_eq!(meanings, ["eat", "consume", "have a meal"]);
+        assert_eq!(check_meaning("eat", &meanings), Verdict::Correct);
+        assert_eq!(check_meaning("To Eat", &meanings), Verdict::Correct);
+        assert_eq!(check_meaning("  have   a meal! ", &meanings), Verdict::Correct);
+        assert_eq!(check_meaning("drink", &meanings), Verdict::Wrong);
+        assert_eq!(check_meaning("", &meanings), Verdict::Wrong);
+        assert_eq!(check_meaning("(to)", &meanings), Verdict::Wrong);
+    }
+
+    #[test]
+    fn meaning_notes_are_ignored(){
+        let meanings = accepted("bank (of a river)/shore");
+
+        assert_eq!(meanings, ["bank", "shore"]);
+        assert_eq!(check_meaning("bank", &meanings), Verdict::Correct);
+        assert_eq!(check_meaning("the shore (sea)", &meanings), Verdict::Correct);
+    }
+
+    #[test]
+    fn typos_allowed_by_length(){
+        let meanings = accepted("dog, house, university");
+
+        // Too short to forgive a letter
+        assert_eq!(check_meaning("dig", &meanings), Verdict::Wrong);
+        assert_eq!(check_meaning("houze", &meanings), Verdict::Close);
+        // A swap is two edits
+        assert_eq!(check_meaning("huose", &meanings), Verdict::Wrong);
+        assert_eq!(check_meaning("hose", &meanings), Verdict::Close);
+        assert_eq!(check_meaning("hse", &meanings), Verdict::Wrong);
+        assert_eq!(check_meaning("univercity", &meanings), Verdict::Close);
+        assert_eq!(check_meaning("unversety", &meanings), Verdict::Close);
+        assert_eq!(check_meaning("universe", &meanings), Verdict::Wrong);
+    }
+
+    #[test]
+    fn edit_distances(){
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("same", "same"), 0);
+    }
+
+    #[test]
+    fn readings_typed_in_romaji_or_kana(){
+        let kunyomi = ["た.べる".to_owned()];
+        let onyomi = ["ショク".to_owned(), "ジキ".to_owned()];
+
+        assert_eq!(check_reading("taberu", &kunyomi, &onyomi), Verdict::Correct);
+        assert_eq!(check_reading("ta", &kunyomi, &onyomi), Verdict::Correct);
+        assert_eq!(check_reading("たべる", &kunyomi, &onyomi), Verdict::Correct);
+        assert_eq!(check_reading("shoku", &onyomi, &kunyomi), Verdict::Correct);
+        assert_eq!(check_reading("ショク", &onyomi, &kunyomi), Verdict::Correct);
+        assert_eq!(check_reading("shoku", &kunyomi, &onyomi), Verdict::WrongKind);
+        assert_eq!(check_reading("nomu", &kunyomi, &onyomi), Verdict::Wrong);
+        assert_eq!(check_reading(" ", &kunyomi, &onyomi), Verdict::Wrong);
+    }
+
+    #[test]
+    fn reading_affixes_and_long_vowels(){
+        let prefix = ["お-".to_owned()];
+        let loanword = ["ラーメン".to_owned()];
+
+        assert_eq!(check_reading("o", &prefix, &[]), Verdict::Correct);
+        assert_eq!(check_reading("ra-men", &loanword, &[]), Verdict::Correct);
+        assert_eq!(normalize_reading("ra-men"), "らーめん");
+        assert_eq!(normalize_reading("ramen"), "らめん");
+    }
+}
@@ -0,0 +1,302 @@
+use std::collections::HashMap;
+use serde::{Serialize, Deserialize};
+use crate::kana::*;
+
+// Extra cost of a voiced or geminated reading over its dictionary form
+const SOUND_CHANGE_COST: u32 = 2;
+// Extra cost of a kun'yomi whose okurigana doesn't follow the kanji in the phrase
+const OKURIGANA_COST: u32 = 1;
+// Cost of giving a run of kanji a reading not built from their known readings, plus one per extra kanji
+const UNMATCHED_COST: u32 = 10;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReadingKind{
+    Onyomi,
+    Kunyomi,
+}
+
+impl ReadingKind{
+    pub fn as_str(&self)-> &'static str{
+        match self{
+            ReadingKind::Onyomi => "onyomi",
+            ReadingKind::Kunyomi => "kunyomi",
+        }
+    }
+}
+
+// The known kanji reading a segment was built from, before any sound change
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ReadingSource{
+    pub kind: ReadingKind,
+    pub reading: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RubySegment{
+    pub text: String,
+    pub ruby: Option<String>,
+    pub source: Option<ReadingSource>,
+}
+
+#[derive(Default, Clone)]
+pub struct KanjiReadings{
+    pub onyomi: Vec<String>,
+    pub kunyomi: Vec<String>,
+}
+
+struct Candidate{
+    kana: Vec<char>,
+    cost: u32,
+    source: ReadingSource,
+}
+
+type Alignment = Option<(u32, Vec<RubySegment>)>;
+
+// Kana which can't start a syllable, so a segment's ruby can't begin with them
+fn is_dependent(c: char)-> bool{
+    matches!(c, 'ぁ' | 'ぃ' | 'ぅ' | 'ぇ' | 'ぉ' | 'っ' | 'ゃ' | 'ゅ' | 'ょ' | 'ゎ' | 'ー')
+}
+
+fn hiragana(c: char)-> char{
+    to_hiragana(&c.to_string()).chars().next().unwrap_or(c)
+}
+
+// Readings a kanji may take at position idx of the phrase, including sound changes
+fn candidates(phrase: &[char], idx: usize, readings: &HashMap<char, KanjiReadings>)-> Vec<Candidate>{
+    let symbol = if phrase[idx] == '々' && idx > 0{
+        phrase[idx - 1]
+    }
+    else{
+        phrase[idx]
+    };
+
+    let kanji_readings = match readings.get(&symbol){
+        Some(kanji_readings) => kanji_readings,
+        None => return Vec::new(),
+    };
+
+    let following = phrase[idx + 1..].iter()
+        .take_while(|c| is_kana(**c))
+        .map(|c| hiragana(*c))
+        .collect::<String>();
+    let compound_start = idx > 0 && is_kanji(phrase[idx - 1]);
+    let compound_end = idx + 1 < phrase.len() && is_kanji(phrase[idx + 1]);
+
+    let mut bases = Vec::new();
+    for onyomi in &kanji_readings.onyomi{
+        bases.push((to_hiragana(onyomi.trim_matches('-')), 0, ReadingKind::Onyomi));
+    }
+    for kunyomi in &kanji_readings.kunyomi{
+        let kunyomi = to_hiragana(kunyomi.trim_matches('-'));
+        match kunyomi.split_once('.'){
+            Some((stem, okurigana)) =>{
+                let cost = if following.starts_with(okurigana){ 0 } else{ OKURIGANA_COST };
+                bases.push((stem.to_owned(), cost, ReadingKind::Kunyomi));
+            }
+            None => bases.push((kunyomi, 0, ReadingKind::Kunyomi)),
+        }
+    }
+
+    let mut result = Vec::new();
+    for (base, cost, kind) in bases.into_iter().filter(|(base, _, _)| !base.is_empty()){
+        let source = ReadingSource{ kind, reading: base.to_owned() };
+
+        if compound_start || phrase[idx] == '々'{
+            for voiced in rendaku(&base){
+                result.push(Candidate{
+                    kana: voiced.chars().collect(),
+                    cost: cost + SOUND_CHANGE_COST,
+                    source: source.clone(),
+                });
+            }
+        }
+        if compound_end{
+            if let Some(geminated) = gemination(&base){
+                result.push(Candidate{
+                    kana: geminated.chars().collect(),
+                    cost: cost + SOUND_CHANGE_COST,
+                    source: source.clone(),
+                });
+            }
+        }
+        result.push(Candidate{ kana: base.chars().collect(), cost, source });
+    }
+
+    result
+}
+
+fn search(
+    phrase: &[char],
+    reading: &[char],
+    pos: (usize, usize),
+    readings: &HashMap<char, KanjiReadings>,
+    memo: &mut HashMap<(usize, usize), Alignment>
+)-> Alignment{
+    let (i, j) = pos;
+    if i == phrase.len(){
+        return if j == reading.len(){ Some((0, Vec::new())) } else{ None };
+    }
+    if let Some(alignment) = memo.get(&pos){
+        return alignment.clone();
+    }
+
+    let mut options = Vec::<(u32, RubySegment, (usize, usize))>::new();
+
+    if !is_kanji(phrase[i]){
+        if j < reading.len() && hiragana(phrase[i]) == reading[j]{
+            options.push((0, RubySegment{ text: phrase[i].to_string(), ruby: None, source: None }, (i + 1, j + 1)));
+        }
+    }
+    else{
+        for candidate in candidates(phrase, i, readings){
+            if reading[j..].starts_with(&candidate.kana){
+                options.push((candidate.cost, RubySegment{
+                    text: phrase[i].to_string(),
+                    ruby: Some(candidate.kana.iter().collect()),
+                    source: Some(candidate.source),
+                }, (i + 1, j + candidate.kana.len())));
+            }
+        }
+
+        // Jukujikun and unknown kanji, give a run of kanji any reading as a single group
+        let run_end = (i..phrase.len())
+            .find(|k| !is_kanji(phrase[*k]))
+            .unwrap_or(phrase.len());
+        for m in i + 1..=run_end{
+            for l in 1..=reading.len() - j{
+                if matches!(reading.get(j + l), Some(c) if is_dependent(*c)){
+                    continue;
+                }
+
+                options.push((UNMATCHED_COST + (m - i - 1) as u32, RubySegment{
+                    text: phrase[i..m].iter().collect(),
+                    ruby: Some(reading[j..j + l].iter().collect()),
+                    source: None,
+                }, (m, j + l)));
+            }
+        }
+    }
+
+    let mut best: Alignment = None;
+    for (cost, segment, next) in options{
+        if let Some((rest_cost, rest)) = search(phrase, reading, next, readings, memo){
+            let total = cost + rest_cost;
+            if !matches!(best, Some((best_cost, _)) if best_cost <= total){
+                let mut segments = vec![segment];
+                segments.extend(rest);
+                best = Some((total, segments));
+            }
+        }
+    }
+
+    memo.insert(pos, best.clone());
+    best
+}
+
+// Splits a phrase into ruby segments over its reading, e.g. 下さい/ください -> [下:くだ][さい]
+pub fn align(phrase: &str, reading: &str, readings: &HashMap<char, KanjiReadings>)-> Option<Vec<RubySegment>>{
+    let phrase = phrase.chars().collect::<Vec<char>>();
+    let reading = to_hiragana(reading).chars().collect::<Vec<char>>();
+
+    let (_, segments) = search(&phrase, &reading, (0, 0), readings, &mut HashMap::new())?;
+
+    let mut merged = Vec::<RubySegment>::new();
+    for segment in segments{
+        match merged.last_mut(){
+            Some(last) if last.ruby.is_none() && segment.ruby.is_none() => last.text.push_str(&segment.text),
+            _ => merged.push(segment),
+        }
+    }
+
+    Some(merged)
+}
+
+// Whether an alignment is irregular (jukujikun, ateji), kanji without known readings can't be judged
+pub fn is_exception(segments: &Option<Vec<RubySegment>>, readings: &HashMap<char, KanjiReadings>)-> bool{
+    match segments{
+        Some(segments) => segments.iter().any(|segment|{
+            segment.ruby.is_some() && segment.source.is_none() && segment.text.chars()
+                .all(|c| c == '々' || readings.contains_key(&c))
+        }),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn readings()-> HashMap<char, KanjiReadings>{
+        let kanji = |onyomi: &[&str], kunyomi: &[&str]| KanjiReadings{
+            onyomi: onyomi.iter().map(|reading| reading.to_string()).collect(),
+            kunyomi: kunyomi.iter().map(|reading| reading.to_string()).collect(),
+        };
+
+        HashMap::from([
+            ('下', kanji(&["カ", "ゲ"], &["した", "しも", "もと", "さ.げる", "くだ.さる", "くだ.る", "お.りる"])),
+            ('今', kanji(&["コン", "キン"], &["いま"])),
+            ('日', kanji(&["ニチ", "ジツ"], &["ひ", "-び", "-か"])),
+            ('大', kanji(&["ダイ", "タイ"], &["おお-", "おお.きい", "おお.いに"])),
+            ('人', kanji(&["ジン", "ニン"], &["ひと", "-り", "-と"])),
+            ('学', kanji(&["ガク"], &["まな.ぶ"])),
+            ('校', kanji(&["コウ", "キョウ"], &[])),
+        ])
+    }
+
+    // Segments as text and ruby pairs, easier to compare than whole RubySegments
+    fn ruby(phrase: &str, reading: &str)-> Option<Vec<(String, Option<String>)>>{
+        align(phrase, reading, &readings()).map(|segments| segments.into_iter()
+            .map(|segment| (segment.text, segment.ruby))
+            .collect())
+    }
+
+    fn segment(text: &str, ruby: Option<&str>)-> (String, Option<String>){
+        (text.to_owned(), ruby.map(|ruby| ruby.to_owned()))
+    }
+
+    #[test]
+    fn okurigana_stays_outside_ruby(){
+        assert_eq!(ruby("下さい", "ください"), Some(vec![segment("下", Some("くだ")), segment("さい", None)]));
+
+        let segments = align("下さい", "ください", &readings()).unwrap();
+        assert_eq!(segments[0].source, Some(ReadingSource{ kind: ReadingKind::Kunyomi, reading: "くだ".to_owned() }));
+        assert!(!is_exception(&Some(segments), &readings()));
+    }
+
+    #[test]
+    fn jukujikun_reads_as_one_group(){
+        assert_eq!(ruby("今日", "きょう"), Some(vec![segment("今日", Some("きょう"))]));
+        assert_eq!(ruby("大人", "おとな"), Some(vec![segment("大人", Some("おとな"))]));
+
+        assert!(is_exception(&align("今日", "きょう", &readings()), &readings()));
+        assert!(is_exception(&align("大人", "おとな", &readings()), &readings()));
+    }
+
+    #[test]
+    fn compounds_split_per_kanji(){
+        assert_eq!(ruby("今日", "こんにち"), Some(vec![segment("今", Some("こん")), segment("日", Some("にち"))]));
+        assert_eq!(ruby("大人", "だいにん"), Some(vec![segment("大", Some("だい")), segment("人", Some("にん"))]));
+        assert!(!is_exception(&align("今日", "こんにち", &readings()), &readings()));
+    }
+
+    #[test]
+    fn sound_changes_keep_their_source(){
+        assert_eq!(ruby("学校", "がっこう"), Some(vec![segment("学", Some("がっ")), segment("校", Some("こう"))]));
+
+        let segments = align("人々", "ひとびと", &readings()).unwrap();
+        assert_eq!(segments[1].ruby.as_deref(), Some("びと"));
+        assert_eq!(segments[1].source.as_ref().map(|source| source.reading.as_str()), Some("ひと"));
+    }
+
+    #[test]
+    fn katakana_reading_aligns_as_hiragana(){
+        assert_eq!(ruby("下さい", "クダサイ"), Some(vec![segment("下", Some("くだ")), segment("さい", None)]));
+    }
+
+    #[test]
+    fn mismatched_kana_fails(){
+        assert_eq!(ruby("下さい", "くださる"), None);
+        assert!(is_exception(&None, &readings()));
+    }
+}
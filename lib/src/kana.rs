@@ -0,0 +1,240 @@
+// Offset between the katakana and hiragana unicode blocks
+const KATAKANA_OFFSET: u32 = 0x60;
+
+pub fn is_hiragana(c: char)-> bool{
+    ('\u{3041}'..='\u{309F}').contains(&c)
+}
+
+pub fn is_katakana(c: char)-> bool{
+    ('\u{30A0}'..='\u{30FF}').contains(&c)
+}
+
+pub fn is_kana(c: char)-> bool{
+    is_hiragana(c) || is_katakana(c)
+}
+
+// CJK ideographs plus the iteration and abbreviation marks which stand in for one
+pub fn is_kanji(c: char)-> bool{
+    ('\u{4E00}'..='\u{9FFF}').contains(&c)
+        || ('\u{3400}'..='\u{4DBF}').contains(&c)
+        || ('\u{F900}'..='\u{FAFF}').contains(&c)
+        || ('\u{20000}'..='\u{2FA1F}').contains(&c)
+        || c == '々'
+        || c == '〆'
+}
+
+// Katakana to hiragana, leaves the long vowel mark and anything else untouched
+pub fn to_hiragana(text: &str)-> String{
+    text.chars()
+        .map(|c|{
+            if ('\u{30A1}'..='\u{30F6}').contains(&c){
+                char::from_u32(c as u32 - KATAKANA_OFFSET).unwrap_or(c)
+            }
+            else{
+                c
+            }
+        })
+        .collect()
+}
+
+// Voiced forms a reading can take when it's not the first in a compound, e.g. かわ -> がわ
+pub fn rendaku(reading: &str)-> Vec<String>{
+    let mut chars = reading.chars();
+    let first = match chars.next(){
+        Some(first) => first,
+        None => return Vec::new(),
+    };
+    let rest = chars.as_str();
+
+    let voiced: &[char] = match first{
+        'か' => &['が'], 'き' => &['ぎ'], 'く' => &['ぐ'], 'け' => &['げ'], 'こ' => &['ご'],
+        'さ' => &['ざ'], 'し' => &['じ'], 'す' => &['ず'], 'せ' => &['ぜ'], 'そ' => &['ぞ'],
+        'た' => &['だ'], 'ち' => &['ぢ', 'じ'], 'つ' => &['づ', 'ず'], 'て' => &['で'], 'と' => &['ど'],
+        'は' => &['ば', 'ぱ'], 'ひ' => &['び', 'ぴ'], 'ふ' => &['ぶ', 'ぷ'], 'へ' => &['べ', 'ぺ'], 'ほ' => &['ぼ', 'ぽ'],
+        _ => &[],
+    };
+
+    voiced.iter()
+        .map(|c| format!("{c}{rest}"))
+        .collect()
+}
+
+// Geminated form of a reading before a following consonant, e.g. がく -> がっ
+pub fn gemination(reading: &str)-> Option<String>{
+    let mut chars = reading.chars();
+    let last = chars.next_back()?;
+
+    if chars.as_str().is_empty() || !matches!(last, 'つ' | 'く' | 'ち' | 'き'){
+        return None;
+    }
+
+    Some(format!("{}っ", chars.as_str()))
+}
+
+// Romaji syllables, Hepburn and Nihon-shiki spellings both, plus x/l prefixed small kana
+const ROMAJI: &[(&str, &str)] = &[
+    ("a", "あ"), ("i", "い"), ("u", "う"), ("e", "え"), ("o", "お"),
+    ("ka", "か"), ("ki", "き"), ("ku", "く"), ("ke", "け"), ("ko", "こ"),
+    ("kya", "きゃ"), ("kyu", "きゅ"), ("kyo", "きょ"),
+    ("ga", "が"), ("gi", "ぎ"), ("gu", "ぐ"), ("ge", "げ"), ("go", "ご"),
+    ("gya", "ぎゃ"), ("gyu", "ぎゅ"), ("gyo", "ぎょ"),
+    ("sa", "さ"), ("shi", "し"), ("si", "し"), ("su", "す"), ("se", "せ"), ("so", "そ"),
+    ("sha", "しゃ"), ("shu", "しゅ"), ("she", "しぇ"), ("sho", "しょ"),
+    ("sya", "しゃ"), ("syu", "しゅ"), ("syo", "しょ"),
+    ("za", "ざ"), ("ji", "じ"), ("zi", "じ"), ("zu", "ず"), ("ze", "ぜ"), ("zo", "ぞ"),
+    ("ja", "じゃ"), ("ju", "じゅ"), ("je", "じぇ"), ("jo", "じょ"),
+    ("jya", "じゃ"), ("jyu", "じゅ"), ("jyo", "じょ"), ("zya", "じゃ"), ("zyu", "じゅ"), ("zyo", "じょ"),
+    ("ta", "た"), ("chi", "ち"), ("ti", "ち"), ("tsu", "つ"), ("tu", "つ"), ("te", "て"), ("to", "と"),
+    ("cha", "ちゃ"), ("chu", "ちゅ"), ("che", "ちぇ"), ("cho", "ちょ"),
+    ("tya", "ちゃ"), ("tyu", "ちゅ"), ("tyo", "ちょ"),
+    ("da", "だ"), ("di", "ぢ"), ("du", "づ"), ("de", "で"), ("do", "ど"),
+    ("na", "な"), ("ni", "に"), ("nu", "ぬ"), ("ne", "ね"), ("no", "の"),
+    ("nya", "にゃ"), ("nyu", "にゅ"), ("nyo", "にょ"),
+    ("ha", "は"), ("hi", "ひ"), ("fu", "ふ"), ("hu", "ふ"), ("he", "へ"), ("ho", "ほ"),
+    ("hya", "ひゃ"), ("hyu", "ひゅ"), ("hyo", "ひょ"),
+    ("fa", "ふぁ"), ("fi", "ふぃ"), ("fe", "ふぇ"), ("fo", "ふぉ"),
+    ("ba", "ば"), ("bi", "び"), ("bu", "ぶ"), ("be", "べ"), ("bo", "ぼ"),
+    ("bya", "びゃ"), ("byu", "びゅ"), ("byo", "びょ"),
+    ("pa", "ぱ"), ("pi", "ぴ"), ("pu", "ぷ"), ("pe", "ぺ"), ("po", "ぽ"),
+    ("pya", "ぴゃ"), ("pyu", "ぴゅ"), ("pyo", "ぴょ"),
+    ("ma", "ま"), ("mi", "み"), ("mu", "む"), ("me", "め"), ("mo", "も"),
+    ("mya", "みゃ"), ("myu", "みゅ"), ("myo", "みょ"),
+    ("ya", "や"), ("yu", "ゆ"), ("yo", "よ"),
+    ("ra", "ら"), ("ri", "り"), ("ru", "る"), ("re", "れ"), ("ro", "ろ"),
+    ("rya", "りゃ"), ("ryu", "りゅ"), ("ryo", "りょ"),
+    ("wa", "わ"), ("wo", "を"), ("vu", "ゔ"),
+    ("xa", "ぁ"), ("xi", "ぃ"), ("xu", "ぅ"), ("xe", "ぇ"), ("xo", "ぉ"),
+    ("la", "ぁ"), ("li", "ぃ"), ("lu", "ぅ"), ("le", "ぇ"), ("lo", "ぉ"),
+    ("xya", "ゃ"), ("xyu", "ゅ"), ("xyo", "ょ"), ("lya", "ゃ"), ("lyu", "ゅ"), ("lyo", "ょ"),
+    ("xtu", "っ"), ("ltu", "っ"), ("xwa", "ゎ"), ("lwa", "ゎ"),
+    ("-", "ー"),
+];
+
+fn is_vowel(c: char)-> bool{
+    matches!(c, 'a' | 'i' | 'u' | 'e' | 'o')
+}
+
+// Romaji as typed on an IME to hiragana, e.g. konnichiha -> こんにちは, kana and anything
+// that isn't romaji is left as is
+pub fn romaji_to_hiragana(text: &str)-> String{
+    let chars = text.to_lowercase().chars().collect::<Vec<char>>();
+    let mut result = String::new();
+    let mut idx = 0;
+
+    while idx < chars.len(){
+        let c = chars[idx];
+        let next = chars.get(idx + 1).copied();
+
+        if c == 'n' && !matches!(next, Some(next) if is_vowel(next) || next == 'y'){
+            result.push('ん');
+            idx += match next{
+                // nn only stands for ん on its own when the second n can't start a syllable
+                Some('n') if !matches!(chars.get(idx + 2), Some(after) if is_vowel(*after) || *after == 'y') => 2,
+                Some('\'') => 2,
+                _ => 1,
+            };
+            continue;
+        }
+
+        // Doubled consonant, or t before ch, is a small tsu
+        if c.is_ascii_alphabetic() && !is_vowel(c) && c != 'n' && (next == Some(c) || (c == 't' && next == Some('c'))){
+            result.push('っ');
+            idx += 1;
+            continue;
+        }
+
+        let syllable = (1..=3).rev()
+            .filter(|len| idx + len <= chars.len())
+            .find_map(|len|{
+                let romaji = chars[idx..idx + len].iter().collect::<String>();
+                ROMAJI.iter()
+                    .find(|(known, _)| *known == romaji)
+                    .map(|(_, kana)| (len, *kana))
+            });
+
+        match syllable{
+            Some((len, kana)) =>{
+                result.push_str(kana);
+                idx += len;
+            }
+            None =>{
+                result.push(c);
+                idx += 1;
+            }
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn romaji_n_before_consonants_and_at_end(){
+        assert_eq!(romaji_to_hiragana("hon"), "ほん");
+        assert_eq!(romaji_to_hiragana("honn"), "ほん");
+        assert_eq!(romaji_to_hiragana("sensei"), "せんせい");
+        assert_eq!(romaji_to_hiragana("konnichiha"), "こんにちは");
+        assert_eq!(romaji_to_hiragana("kanna"), "かんな");
+        assert_eq!(romaji_to_hiragana("sannin"), "さんにん");
+    }
+
+    #[test]
+    fn romaji_n_before_vowels_needs_apostrophe(){
+        assert_eq!(romaji_to_hiragana("kinen"), "きねん");
+        assert_eq!(romaji_to_hiragana("kin'en"), "きんえん");
+        assert_eq!(romaji_to_hiragana("hon'ya"), "ほんや");
+        assert_eq!(romaji_to_hiragana("nyuu"), "にゅう");
+    }
+
+    #[test]
+    fn romaji_doubled_consonants_are_small_tsu(){
+        assert_eq!(romaji_to_hiragana("kka"), "っか");
+        assert_eq!(romaji_to_hiragana("gakkou"), "がっこう");
+        assert_eq!(romaji_to_hiragana("kippu"), "きっぷ");
+        assert_eq!(romaji_to_hiragana("matcha"), "まっちゃ");
+        assert_eq!(romaji_to_hiragana("zasshi"), "ざっし");
+    }
+
+    #[test]
+    fn romaji_dash_is_long_vowel_mark(){
+        assert_eq!(romaji_to_hiragana("-"), "ー");
+        assert_eq!(romaji_to_hiragana("ra-men"), "らーめん");
+    }
+
+    #[test]
+    fn romaji_leaves_kana_and_others_alone(){
+        assert_eq!(romaji_to_hiragana("TABERU"), "たべる");
+        assert_eq!(romaji_to_hiragana("たべる"), "たべる");
+        assert_eq!(romaji_to_hiragana("た.べる"), "た.べる");
+        assert_eq!(romaji_to_hiragana("q"), "q");
+    }
+
+    #[test]
+    fn katakana_to_hiragana(){
+        assert_eq!(to_hiragana("カタカナ"), "かたかな");
+        assert_eq!(to_hiragana("コーヒー"), "こーひー");
+        assert_eq!(to_hiragana("漢字"), "漢字");
+    }
+
+    #[test]
+    fn sound_changes(){
+        assert_eq!(rendaku("かわ"), ["がわ"]);
+        assert_eq!(rendaku("ひと"), ["びと", "ぴと"]);
+        assert!(rendaku("やま").is_empty());
+        assert_eq!(gemination("がく"), Some("がっ".to_owned()));
+        assert_eq!(gemination("く"), None);
+        assert_eq!(gemination("こう"), None);
+    }
+
+    #[test]
+    fn character_classes(){
+        assert!(is_kanji('漢') && is_kanji('々') && is_kanji('〆'));
+        assert!(!is_kanji('か') && !is_kanji('a'));
+        assert!(is_hiragana('か') && !is_hiragana('カ'));
+        assert!(is_katakana('カ') && is_katakana('ー'));
+        assert!(is_kana('か') && is_kana('カ') && !is_kana('漢'));
+    }
+}
@@ -2,6 +2,10 @@ use serde::{Serialize, Deserialize};
 
 pub mod schema;
 pub mod models;
+pub mod kana;
+pub mod furigana;
+pub mod srs;
+pub mod answer;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Package{
@@ -1,4 +1,5 @@
 use std::{hash::{Hash, Hasher}, fmt::Debug};
+use chrono::{DateTime, Utc};
 use serde::{Serialize, Deserialize};
 use diesel::prelude::*;
 use crate::schema::*;
@@ -10,6 +11,14 @@ pub struct User{
     pub username: String,
     pub hash: Vec<u8>,
     pub salt: Vec<u8>,
+    pub algorithm: String,
+    pub desired_retention: f64,
+    pub fsrs_weights: Option<Vec<Option<f64>>>,
+    pub disabled_cards: Vec<Option<String>>,
+    pub leech_threshold: i32,
+    pub leech_action: String,
+    pub lessons_per_day: i32,
+    pub default_preset_id: Option<i32>,
 }
 
 impl Hash for User{
@@ -39,7 +48,7 @@ pub struct NewUser{
     pub salt: Vec<u8>,
 }
 
-#[derive(Identifiable, Queryable, Associations)]
+#[derive(Identifiable, Queryable, Associations, Serialize, Deserialize)]
 #[diesel(table_name = groups, belongs_to(User))]
 pub struct Group{
     pub id: i32,
@@ -47,6 +56,7 @@ pub struct Group{
     pub colour: Option<String>,
     pub vocab: bool,
     pub user_id: i32,
+    pub preset_id: Option<i32>,
 }
 
 #[derive(Insertable, Serialize, Deserialize)]
@@ -57,7 +67,7 @@ pub struct NewGroup{
     pub vocab: bool,
     pub user_id: i32,
 }
-#[derive(Identifiable, Queryable, Associations)]
+#[derive(Identifiable, Queryable, Associations, Serialize, Deserialize)]
 #[diesel(table_name = kanji, belongs_to(User), belongs_to(Group))]
 pub struct Kanji{
     pub id: i32,
@@ -69,6 +79,12 @@ pub struct Kanji{
     pub vocab_refs: Vec<Option<String>>,
     pub user_id: i32,
     pub group_id: Option<i32>,
+    pub components: Vec<Option<String>>,
+    pub stroke_count: Option<i32>,
+    pub jlpt_level: Option<i32>,
+    pub grade: Option<i32>,
+    pub frequency_rank: Option<i32>,
+    pub learned_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Insertable, Serialize, Deserialize)]
@@ -82,9 +98,29 @@ pub struct NewKanji{
     pub vocab_refs: Vec<Option<String>>,
     pub user_id: i32,
     pub group_id: Option<i32>,
+    #[serde(default)]
+    pub components: Vec<Option<String>>,
+    #[serde(default)]
+    pub stroke_count: Option<i32>,
+    #[serde(default)]
+    pub jlpt_level: Option<i32>,
+    #[serde(default)]
+    pub grade: Option<i32>,
+    #[serde(default)]
+    pub frequency_rank: Option<i32>,
 }
 
-#[derive(Identifiable, Queryable, Associations)]
+// Metadata edits, an outer None leaves the column untouched while Some(None) clears it
+#[derive(AsChangeset, Default)]
+#[diesel(table_name = kanji)]
+pub struct KanjiMetadata{
+    pub stroke_count: Option<Option<i32>>,
+    pub jlpt_level: Option<Option<i32>>,
+    pub grade: Option<Option<i32>>,
+    pub frequency_rank: Option<Option<i32>>,
+}
+
+#[derive(Identifiable, Queryable, Associations, Serialize, Deserialize)]
 #[diesel(table_name = vocab, belongs_to(User), belongs_to(Group))]
 pub struct Vocab{
     pub id: i32,
@@ -95,6 +131,9 @@ pub struct Vocab{
     pub kanji_refs: Vec<Option<String>>,
     pub user_id: i32,
     pub group_id: Option<i32>,
+    pub exception: bool,
+    pub learned_at: Option<DateTime<Utc>>,
+    pub parts_of_speech: Vec<Option<String>>,
 }
 
 #[derive(Insertable, Serialize, Deserialize)]
@@ -107,4 +146,499 @@ pub struct NewVocab{
     pub kanji_refs: Vec<Option<String>>,
     pub user_id: i32,
     pub group_id: Option<i32>,
+    #[serde(default)]
+    pub exception: bool,
+    #[serde(default)]
+    pub parts_of_speech: Vec<Option<String>>,
+}
+
+#[derive(Identifiable, Queryable, Associations, Serialize)]
+#[diesel(table_name = vocab_readings, belongs_to(Vocab))]
+pub struct VocabReading{
+    pub id: i32,
+    pub reading: String,
+    pub position: i32,
+    pub kanji_symbol: String,
+    pub kind: String,
+    pub kanji_reading: String,
+    pub vocab_id: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = vocab_readings)]
+pub struct NewVocabReading{
+    pub reading: String,
+    pub position: i32,
+    pub kanji_symbol: String,
+    pub kind: String,
+    pub kanji_reading: String,
+    pub vocab_id: i32,
+}
+
+#[derive(Identifiable, Queryable)]
+#[diesel(table_name = radicals)]
+pub struct Radical{
+    pub id: i32,
+    pub symbol: String,
+    pub stroke_count: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = radicals)]
+pub struct NewRadical{
+    pub symbol: String,
+    pub stroke_count: i32,
+}
+
+#[derive(Identifiable, Queryable, Associations)]
+#[diesel(table_name = kanji_components, belongs_to(Radical))]
+pub struct KanjiComponent{
+    pub id: i32,
+    pub kanji_symbol: String,
+    pub radical_id: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = kanji_components)]
+pub struct NewKanjiComponent{
+    pub kanji_symbol: String,
+    pub radical_id: i32,
+}
+
+#[derive(Identifiable, Queryable, Serialize)]
+#[diesel(table_name = jmdict_entries)]
+pub struct JmdictEntry{
+    pub id: i32,
+    pub common: bool,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = jmdict_entries)]
+pub struct NewJmdictEntry{
+    pub id: i32,
+    pub common: bool,
+}
+
+#[derive(Identifiable, Queryable, Associations, Serialize)]
+#[diesel(table_name = jmdict_forms, belongs_to(JmdictEntry, foreign_key = entry_id))]
+pub struct JmdictForm{
+    pub id: i32,
+    pub text: String,
+    pub reading: bool,
+    pub common: bool,
+    pub restrictions: Vec<Option<String>>,
+    pub entry_id: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = jmdict_forms)]
+pub struct NewJmdictForm{
+    pub text: String,
+    pub reading: bool,
+    pub common: bool,
+    pub restrictions: Vec<Option<String>>,
+    pub entry_id: i32,
+}
+
+#[derive(Identifiable, Queryable, Associations, Serialize)]
+#[diesel(table_name = jmdict_senses, belongs_to(JmdictEntry, foreign_key = entry_id))]
+pub struct JmdictSense{
+    pub id: i32,
+    pub position: i32,
+    pub glosses: Vec<Option<String>>,
+    pub parts_of_speech: Vec<Option<String>>,
+    pub entry_id: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = jmdict_senses)]
+pub struct NewJmdictSense{
+    pub position: i32,
+    pub glosses: Vec<Option<String>>,
+    pub parts_of_speech: Vec<Option<String>>,
+    pub entry_id: i32,
+}
+
+#[derive(Identifiable, Queryable)]
+#[diesel(table_name = kanjidic)]
+pub struct KanjidicEntry{
+    pub id: i32,
+    pub symbol: String,
+    pub stroke_count: i32,
+    pub old_jlpt_level: Option<i32>,
+    pub grade: Option<i32>,
+    pub frequency_rank: Option<i32>,
+    pub meanings: Vec<Option<String>>,
+    pub onyomi: Vec<Option<String>>,
+    pub kunyomi: Vec<Option<String>>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = kanjidic)]
+pub struct NewKanjidicEntry{
+    pub symbol: String,
+    pub stroke_count: i32,
+    pub old_jlpt_level: Option<i32>,
+    pub grade: Option<i32>,
+    pub frequency_rank: Option<i32>,
+    pub meanings: Vec<Option<String>>,
+    pub onyomi: Vec<Option<String>>,
+    pub kunyomi: Vec<Option<String>>,
+}
+
+#[derive(Identifiable, Queryable, Associations, Serialize, Deserialize)]
+#[diesel(table_name = examples, belongs_to(User))]
+pub struct Example{
+    pub id: i32,
+    pub sentence: String,
+    pub translation: String,
+    pub reading: Option<String>,
+    pub user_id: i32,
+}
+
+#[derive(Insertable, Serialize, Deserialize)]
+#[diesel(table_name = examples)]
+pub struct NewExample{
+    pub sentence: String,
+    pub translation: String,
+    pub reading: Option<String>,
+    pub user_id: i32,
+}
+
+#[derive(AsChangeset, Default)]
+#[diesel(table_name = examples)]
+pub struct ExampleChanges{
+    pub sentence: Option<String>,
+    pub translation: Option<String>,
+    pub reading: Option<Option<String>>,
+}
+
+#[derive(Identifiable, Queryable, Associations, Serialize, Deserialize)]
+#[diesel(table_name = vocab_examples, belongs_to(Vocab), belongs_to(Example))]
+pub struct VocabExample{
+    pub id: i32,
+    pub vocab_id: i32,
+    pub example_id: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = vocab_examples)]
+pub struct NewVocabExample{
+    pub vocab_id: i32,
+    pub example_id: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = kanji_similarities)]
+pub struct NewKanjiSimilarity{
+    pub kanji_symbol: String,
+    pub similar_symbol: String,
+}
+
+#[derive(Identifiable, Queryable, Associations, Serialize, Deserialize)]
+#[diesel(table_name = similar_kanji, belongs_to(Kanji))]
+pub struct SimilarKanji{
+    pub id: i32,
+    pub kanji_id: i32,
+    pub similar_id: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = similar_kanji)]
+pub struct NewSimilarKanji{
+    pub kanji_id: i32,
+    pub similar_id: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RelationKind{
+    Synonym,
+    Antonym,
+    Transitive,
+    Intransitive,
+    SeeAlso,
+}
+
+impl RelationKind{
+    pub fn as_str(&self)-> &'static str{
+        match self{
+            RelationKind::Synonym => "synonym",
+            RelationKind::Antonym => "antonym",
+            RelationKind::Transitive => "transitive",
+            RelationKind::Intransitive => "intransitive",
+            RelationKind::SeeAlso => "see_also",
+        }
+    }
+
+    pub fn parse(kind: &str)-> Option<RelationKind>{
+        match kind{
+            "synonym" => Some(RelationKind::Synonym),
+            "antonym" => Some(RelationKind::Antonym),
+            "transitive" => Some(RelationKind::Transitive),
+            "intransitive" => Some(RelationKind::Intransitive),
+            "see_also" => Some(RelationKind::SeeAlso),
+            _ => None,
+        }
+    }
+
+    // Kind of the link stored in the other direction, 上げる is the transitive of 上がる
+    // so 上がる is the intransitive of 上げる
+    pub fn reverse(&self)-> RelationKind{
+        match self{
+            RelationKind::Transitive => RelationKind::Intransitive,
+            RelationKind::Intransitive => RelationKind::Transitive,
+            kind => *kind,
+        }
+    }
+}
+
+#[derive(Identifiable, Queryable, Associations, Serialize, Deserialize)]
+#[diesel(table_name = vocab_relations, belongs_to(Vocab))]
+pub struct VocabRelation{
+    pub id: i32,
+    pub kind: String,
+    pub vocab_id: i32,
+    pub related_id: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = vocab_relations)]
+pub struct NewVocabRelation{
+    pub kind: String,
+    pub vocab_id: i32,
+    pub related_id: i32,
+}
+
+// Direction a kanji or vocab is quizzed in, each one is scheduled as its own review
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Card{
+    Meaning,
+    Onyomi,
+    Kunyomi,
+    Reading,
+    Recall,
+}
+
+pub const KANJI_CARDS: [Card; 3] = [Card::Meaning, Card::Onyomi, Card::Kunyomi];
+pub const VOCAB_CARDS: [Card; 3] = [Card::Reading, Card::Meaning, Card::Recall];
+
+impl Card{
+    pub fn as_str(&self)-> &'static str{
+        match self{
+            Card::Meaning => "meaning",
+            Card::Onyomi => "onyomi",
+            Card::Kunyomi => "kunyomi",
+            Card::Reading => "reading",
+            Card::Recall => "recall",
+        }
+    }
+
+    // Card of the given item kind, only kanji have on'yomi and kun'yomi cards and only vocab recall cards
+    pub fn parse(kind: &str, card: &str)-> Option<Card>{
+        let cards = match kind{
+            "kanji" => KANJI_CARDS,
+            "vocab" => VOCAB_CARDS,
+            _ => return None,
+        };
+
+        cards.into_iter().find(|known| known.as_str() == card)
+    }
+
+    // Name the card is toggled under in the user's settings, e.g. kanji_onyomi
+    pub fn key(&self, kind: &str)-> String{
+        format!("{kind}_{}", self.as_str())
+    }
+}
+
+fn has_any(values: &[Option<String>])-> bool{
+    values.iter().flatten().any(|value| !value.trim().is_empty())
+}
+
+impl Kanji{
+    // Meaning is always quizzed, a reading card only exists while the kanji has readings of that kind
+    pub fn cards(&self)-> Vec<Card>{
+        KANJI_CARDS.into_iter()
+            .filter(|card| match card{
+                Card::Onyomi => has_any(&self.onyomi),
+                Card::Kunyomi => has_any(&self.kunyomi),
+                _ => true,
+            })
+            .collect()
+    }
+}
+
+impl Vocab{
+    pub fn cards(&self)-> Vec<Card>{
+        VOCAB_CARDS.into_iter()
+            .filter(|card| *card != Card::Reading || has_any(&self.reading))
+            .collect()
+    }
+}
+
+#[derive(Identifiable, Queryable, Associations, Serialize, Deserialize)]
+#[diesel(table_name = reviews, belongs_to(User), belongs_to(Kanji), belongs_to(Vocab))]
+pub struct Review{
+    pub id: i32,
+    pub ease: f64,
+    pub interval_days: i32,
+    pub due: DateTime<Utc>,
+    pub repetitions: i32,
+    pub lapses: i32,
+    pub user_id: i32,
+    pub kanji_id: Option<i32>,
+    pub vocab_id: Option<i32>,
+    pub stability: Option<f64>,
+    pub difficulty: Option<f64>,
+    pub last_review: Option<DateTime<Utc>>,
+    pub card: String,
+    pub leech: bool,
+    pub suspended: bool,
+    pub buried_until: Option<DateTime<Utc>>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = reviews)]
+pub struct NewReview{
+    pub ease: f64,
+    pub interval_days: i32,
+    pub due: DateTime<Utc>,
+    pub repetitions: i32,
+    pub lapses: i32,
+    pub user_id: i32,
+    pub kanji_id: Option<i32>,
+    pub vocab_id: Option<i32>,
+    pub stability: Option<f64>,
+    pub difficulty: Option<f64>,
+    pub last_review: Option<DateTime<Utc>>,
+    pub card: String,
+}
+
+#[derive(AsChangeset)]
+#[diesel(table_name = reviews, treat_none_as_null = true)]
+pub struct ReviewChanges{
+    pub ease: f64,
+    pub interval_days: i32,
+    pub due: DateTime<Utc>,
+    pub repetitions: i32,
+    pub lapses: i32,
+    pub stability: Option<f64>,
+    pub difficulty: Option<f64>,
+    pub last_review: Option<DateTime<Utc>>,
+}
+
+#[derive(Identifiable, Queryable, Associations, Serialize, Deserialize)]
+#[diesel(table_name = srs_presets, belongs_to(User))]
+pub struct SrsPreset{
+    pub id: i32,
+    pub title: String,
+    pub new_per_day: i32,
+    pub max_interval: i32,
+    pub learning_steps: Vec<Option<i32>>,
+    pub ease_bonus: f64,
+    pub desired_retention: Option<f64>,
+    pub user_id: i32,
+}
+
+#[derive(Insertable, AsChangeset, Deserialize)]
+#[diesel(table_name = srs_presets, treat_none_as_null = true)]
+pub struct NewSrsPreset{
+    pub title: String,
+    pub new_per_day: i32,
+    pub max_interval: i32,
+    pub learning_steps: Vec<Option<i32>>,
+    pub ease_bonus: f64,
+    pub desired_retention: Option<f64>,
+    #[serde(default)]
+    pub user_id: i32,
+}
+
+// A suspend, bury or reset over a set of reviews, the snapshot holds their prior state as JSON
+#[derive(Identifiable, Queryable, Associations)]
+#[diesel(table_name = review_actions, belongs_to(User))]
+pub struct ReviewAction{
+    pub id: i32,
+    pub action: String,
+    pub performed_at: DateTime<Utc>,
+    pub snapshot: String,
+    pub undone: bool,
+    pub user_id: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = review_actions)]
+pub struct NewReviewAction{
+    pub action: String,
+    pub performed_at: DateTime<Utc>,
+    pub snapshot: String,
+    pub user_id: i32,
+}
+
+// One answer, with the scheduling state it replaced so it can be undone
+#[derive(Identifiable, Queryable, Associations, Serialize, Deserialize)]
+#[diesel(table_name = review_logs, belongs_to(User), belongs_to(Review), belongs_to(ReviewSession, foreign_key = session_id))]
+pub struct ReviewLog{
+    pub id: i32,
+    pub grade: String,
+    pub reviewed_at: DateTime<Utc>,
+    pub elapsed_days: f64,
+    pub user_id: i32,
+    pub review_id: i32,
+    pub card: String,
+    pub response_ms: Option<i32>,
+    pub undone: bool,
+    pub prior_ease: f64,
+    pub prior_interval_days: i32,
+    pub prior_due: DateTime<Utc>,
+    pub prior_repetitions: i32,
+    pub prior_lapses: i32,
+    pub prior_stability: Option<f64>,
+    pub prior_difficulty: Option<f64>,
+    pub prior_last_review: Option<DateTime<Utc>>,
+    pub session_id: Option<i32>,
+    pub prior_leech: bool,
+    pub prior_suspended: bool,
+    // Other cards of the item this answer suspended when it made the card a leech
+    pub suspended_reviews: Vec<Option<i32>>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = review_logs)]
+pub struct NewReviewLog{
+    pub grade: String,
+    pub reviewed_at: DateTime<Utc>,
+    pub elapsed_days: f64,
+    pub user_id: i32,
+    pub review_id: i32,
+    pub card: String,
+    pub response_ms: Option<i32>,
+    pub prior_ease: f64,
+    pub prior_interval_days: i32,
+    pub prior_due: DateTime<Utc>,
+    pub prior_repetitions: i32,
+    pub prior_lapses: i32,
+    pub prior_stability: Option<f64>,
+    pub prior_difficulty: Option<f64>,
+    pub prior_last_review: Option<DateTime<Utc>>,
+    pub session_id: Option<i32>,
+    pub prior_leech: bool,
+    pub prior_suspended: bool,
+}
+
+#[derive(Identifiable, Queryable, Associations, Serialize, Deserialize)]
+#[diesel(table_name = review_sessions, belongs_to(User))]
+pub struct ReviewSession{
+    pub id: i32,
+    pub started_at: DateTime<Utc>,
+    pub group_title: Option<String>,
+    pub user_id: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = review_sessions)]
+pub struct NewReviewSession{
+    pub started_at: DateTime<Utc>,
+    pub group_title: Option<String>,
+    pub user_id: i32,
 }
@@ -1,5 +1,15 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    examples (id) {
+        id -> Int4,
+        sentence -> Text,
+        translation -> Text,
+        reading -> Nullable<Text>,
+        user_id -> Int4,
+    }
+}
+
 diesel::table! {
     groups (id) {
         id -> Int4,
@@ -7,6 +17,35 @@ diesel::table! {
         colour -> Nullable<Text>,
         vocab -> Bool,
         user_id -> Int4,
+        preset_id -> Nullable<Int4>,
+    }
+}
+
+diesel::table! {
+    jmdict_entries (id) {
+        id -> Int4,
+        common -> Bool,
+    }
+}
+
+diesel::table! {
+    jmdict_forms (id) {
+        id -> Int4,
+        text -> Text,
+        reading -> Bool,
+        common -> Bool,
+        restrictions -> Array<Nullable<Text>>,
+        entry_id -> Int4,
+    }
+}
+
+diesel::table! {
+    jmdict_senses (id) {
+        id -> Int4,
+        position -> Int4,
+        glosses -> Array<Nullable<Text>>,
+        parts_of_speech -> Array<Nullable<Text>>,
+        entry_id -> Int4,
     }
 }
 
@@ -21,6 +60,138 @@ diesel::table! {
         vocab_refs -> Array<Nullable<Text>>,
         user_id -> Int4,
         group_id -> Nullable<Int4>,
+        components -> Array<Nullable<Text>>,
+        stroke_count -> Nullable<Int4>,
+        jlpt_level -> Nullable<Int4>,
+        grade -> Nullable<Int4>,
+        frequency_rank -> Nullable<Int4>,
+        learned_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    kanji_components (id) {
+        id -> Int4,
+        kanji_symbol -> Text,
+        radical_id -> Int4,
+    }
+}
+
+diesel::table! {
+    kanji_similarities (id) {
+        id -> Int4,
+        kanji_symbol -> Text,
+        similar_symbol -> Text,
+    }
+}
+
+diesel::table! {
+    kanjidic (id) {
+        id -> Int4,
+        symbol -> Text,
+        stroke_count -> Int4,
+        old_jlpt_level -> Nullable<Int4>,
+        grade -> Nullable<Int4>,
+        frequency_rank -> Nullable<Int4>,
+        meanings -> Array<Nullable<Text>>,
+        onyomi -> Array<Nullable<Text>>,
+        kunyomi -> Array<Nullable<Text>>,
+    }
+}
+
+diesel::table! {
+    radicals (id) {
+        id -> Int4,
+        symbol -> Text,
+        stroke_count -> Int4,
+    }
+}
+
+diesel::table! {
+    review_actions (id) {
+        id -> Int4,
+        action -> Text,
+        performed_at -> Timestamptz,
+        snapshot -> Text,
+        undone -> Bool,
+        user_id -> Int4,
+    }
+}
+
+diesel::table! {
+    review_logs (id) {
+        id -> Int4,
+        grade -> Text,
+        reviewed_at -> Timestamptz,
+        elapsed_days -> Float8,
+        user_id -> Int4,
+        review_id -> Int4,
+        card -> Text,
+        response_ms -> Nullable<Int4>,
+        undone -> Bool,
+        prior_ease -> Float8,
+        prior_interval_days -> Int4,
+        prior_due -> Timestamptz,
+        prior_repetitions -> Int4,
+        prior_lapses -> Int4,
+        prior_stability -> Nullable<Float8>,
+        prior_difficulty -> Nullable<Float8>,
+        prior_last_review -> Nullable<Timestamptz>,
+        session_id -> Nullable<Int4>,
+        prior_leech -> Bool,
+        prior_suspended -> Bool,
+        suspended_reviews -> Array<Nullable<Int4>>,
+    }
+}
+
+diesel::table! {
+    review_sessions (id) {
+        id -> Int4,
+        started_at -> Timestamptz,
+        group_title -> Nullable<Text>,
+        user_id -> Int4,
+    }
+}
+
+diesel::table! {
+    reviews (id) {
+        id -> Int4,
+        ease -> Float8,
+        interval_days -> Int4,
+        due -> Timestamptz,
+        repetitions -> Int4,
+        lapses -> Int4,
+        user_id -> Int4,
+        kanji_id -> Nullable<Int4>,
+        vocab_id -> Nullable<Int4>,
+        stability -> Nullable<Float8>,
+        difficulty -> Nullable<Float8>,
+        last_review -> Nullable<Timestamptz>,
+        card -> Text,
+        leech -> Bool,
+        suspended -> Bool,
+        buried_until -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    similar_kanji (id) {
+        id -> Int4,
+        kanji_id -> Int4,
+        similar_id -> Int4,
+    }
+}
+
+diesel::table! {
+    srs_presets (id) {
+        id -> Int4,
+        title -> Text,
+        new_per_day -> Int4,
+        max_interval -> Int4,
+        learning_steps -> Array<Nullable<Int4>>,
+        ease_bonus -> Float8,
+        desired_retention -> Nullable<Float8>,
+        user_id -> Int4,
     }
 }
 
@@ -30,6 +201,14 @@ diesel::table! {
         username -> Text,
         hash -> Bytea,
         salt -> Bytea,
+        algorithm -> Text,
+        desired_retention -> Float8,
+        fsrs_weights -> Nullable<Array<Nullable<Float8>>>,
+        disabled_cards -> Array<Nullable<Text>>,
+        leech_threshold -> Int4,
+        leech_action -> Text,
+        lessons_per_day -> Int4,
+        default_preset_id -> Nullable<Int4>,
     }
 }
 
@@ -43,12 +222,78 @@ diesel::table! {
         kanji_refs -> Array<Nullable<Text>>,
         user_id -> Int4,
         group_id -> Nullable<Int4>,
+        exception -> Bool,
+        learned_at -> Nullable<Timestamptz>,
+        parts_of_speech -> Array<Nullable<Text>>,
+    }
+}
+
+diesel::table! {
+    vocab_examples (id) {
+        id -> Int4,
+        vocab_id -> Int4,
+        example_id -> Int4,
+    }
+}
+
+diesel::table! {
+    vocab_relations (id) {
+        id -> Int4,
+        kind -> Text,
+        vocab_id -> Int4,
+        related_id -> Int4,
     }
 }
 
+diesel::table! {
+    vocab_readings (id) {
+        id -> Int4,
+        reading -> Text,
+        position -> Int4,
+        kanji_symbol -> Text,
+        kind -> Text,
+        kanji_reading -> Text,
+        vocab_id -> Int4,
+    }
+}
+
+diesel::joinable!(groups -> srs_presets (preset_id));
+diesel::joinable!(jmdict_forms -> jmdict_entries (entry_id));
+diesel::joinable!(jmdict_senses -> jmdict_entries (entry_id));
+diesel::joinable!(kanji_components -> radicals (radical_id));
+diesel::joinable!(review_actions -> users (user_id));
+diesel::joinable!(review_logs -> review_sessions (session_id));
+diesel::joinable!(review_logs -> reviews (review_id));
+diesel::joinable!(review_logs -> users (user_id));
+diesel::joinable!(review_sessions -> users (user_id));
+diesel::joinable!(reviews -> kanji (kanji_id));
+diesel::joinable!(reviews -> users (user_id));
+diesel::joinable!(reviews -> vocab (vocab_id));
+diesel::joinable!(srs_presets -> users (user_id));
+diesel::joinable!(vocab_examples -> examples (example_id));
+diesel::joinable!(vocab_examples -> vocab (vocab_id));
+diesel::joinable!(vocab_readings -> vocab (vocab_id));
+
 diesel::allow_tables_to_appear_in_same_query!(
+    examples,
     groups,
+    jmdict_entries,
+    jmdict_forms,
+    jmdict_senses,
     kanji,
+    kanji_components,
+    kanji_similarities,
+    kanjidic,
+    radicals,
+    review_actions,
+    review_logs,
+    review_sessions,
+    reviews,
+    similar_kanji,
+    srs_presets,
     users,
     vocab,
+    vocab_examples,
+    vocab_readings,
+    vocab_relations,
 );
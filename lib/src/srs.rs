@@ -0,0 +1,579 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Serialize, Deserialize};
+
+// Ease every item starts out with and the floor SM-2 never lets it drop below
+pub const STARTING_EASE: f64 = 2.5;
+pub const MINIMUM_EASE: f64 = 1.3;
+// Easiest ease an FSRS difficulty maps back onto when switching to SM-2
+const MAXIMUM_EASE: f64 = 3.0;
+
+// FSRS-4.5 forgetting curve shape, R(t, S) = (1 + FACTOR * t / S) ^ DECAY
+const DECAY: f64 = -0.5;
+const FACTOR: f64 = 19.0 / 81.0;
+// Longest interval FSRS will schedule, in days
+const MAXIMUM_INTERVAL: f64 = 36500.0;
+
+pub const DEFAULT_RETENTION: f64 = 0.9;
+pub const DEFAULT_WEIGHTS: [f64; 17] = [
+    0.4872, 1.4003, 3.7145, 13.8206, 5.1618, 1.2298, 0.8975, 0.031, 1.6474,
+    0.1367, 1.0461, 2.1072, 0.0793, 0.3246, 1.587, 0.2272, 2.8755,
+];
+// Range each FSRS weight is clamped to while optimizing
+const WEIGHT_BOUNDS: [(f64, f64); 17] = [
+    (0.1, 100.0), (0.1, 100.0), (0.1, 100.0), (0.1, 100.0), (1.0, 10.0), (0.1, 5.0),
+    (0.1, 5.0), (0.0, 0.8), (0.0, 6.0), (0.0, 0.8), (0.01, 5.0), (0.2, 6.0),
+    (0.01, 0.4), (0.01, 0.9), (0.01, 4.0), (0.0, 1.0), (1.0, 6.0),
+];
+
+// Source of the current time, swapped out for a fixed clock when scheduling needs to be reproducible
+pub trait Clock{
+    fn now(&self)-> DateTime<Utc>;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock{
+    fn now(&self)-> DateTime<Utc>{
+        Utc::now()
+    }
+}
+
+pub struct FixedClock(pub DateTime<Utc>);
+
+impl Clock for FixedClock{
+    fn now(&self)-> DateTime<Utc>{
+        self.0
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Grade{
+    Again,
+    Hard,
+    Good,
+    Easy,
+}
+
+impl Grade{
+    // SM-2 recall quality on its 0-5 scale, anything under 3 is a failed recall
+    pub fn quality(&self)-> u8{
+        match self{
+            Grade::Again => 1,
+            Grade::Hard => 3,
+            Grade::Good => 4,
+            Grade::Easy => 5,
+        }
+    }
+
+    // FSRS rating on its 1-4 scale
+    pub fn rating(&self)-> u8{
+        match self{
+            Grade::Again => 1,
+            Grade::Hard => 2,
+            Grade::Good => 3,
+            Grade::Easy => 4,
+        }
+    }
+
+    pub fn as_str(&self)-> &'static str{
+        match self{
+            Grade::Again => "again",
+            Grade::Hard => "hard",
+            Grade::Good => "good",
+            Grade::Easy => "easy",
+        }
+    }
+
+    pub fn parse(grade: &str)-> Option<Grade>{
+        match grade{
+            "again" => Some(Grade::Again),
+            "hard" => Some(Grade::Hard),
+            "good" => Some(Grade::Good),
+            "easy" => Some(Grade::Easy),
+            _ => None,
+        }
+    }
+}
+
+// Scheduling state of one item, stability and difficulty are only kept while scheduled by FSRS
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ReviewState{
+    pub ease: f64,
+    pub interval_days: i32,
+    pub due: DateTime<Utc>,
+    pub repetitions: i32,
+    pub lapses: i32,
+    pub stability: Option<f64>,
+    pub difficulty: Option<f64>,
+    pub last_review: Option<DateTime<Utc>>,
+}
+
+impl ReviewState{
+    // State of an item that has never been reviewed, due straight away
+    pub fn new(clock: &dyn Clock)-> ReviewState{
+        ReviewState{
+            ease: STARTING_EASE,
+            interval_days: 0,
+            due: clock.now(),
+            repetitions: 0,
+            lapses: 0,
+            stability: None,
+            difficulty: None,
+            last_review: None,
+        }
+    }
+}
+
+pub trait Scheduler{
+    fn name(&self)-> &'static str;
+
+    // Next review state after answering an item with the given grade
+    fn schedule(&self, state: &ReviewState, grade: Grade, clock: &dyn Clock)-> ReviewState;
+
+    // Carries over a state last scheduled by another algorithm, leaving its due date alone
+    fn migrate(&self, state: &ReviewState)-> ReviewState;
+}
+
+// Next review state after answering an item with the given grade under SM-2
+pub fn sm2(state: &ReviewState, grade: Grade, clock: &dyn Clock)-> ReviewState{
+    let quality = f64::from(grade.quality());
+    let ease = (state.ease + 0.1 - (5.0 - quality) * (0.08 + (5.0 - quality) * 0.02)).max(MINIMUM_EASE);
+
+    let (interval_days, repetitions, lapses) = if grade == Grade::Again{
+        let lapses = if state.repetitions > 0{ state.lapses + 1 } else{ state.lapses };
+        (1, 0, lapses)
+    }
+    else{
+        let interval_days = match state.repetitions{
+            0 => 1,
+            1 => 6,
+            _ => (f64::from(state.interval_days) * ease).round() as i32,
+        };
+        (interval_days, state.repetitions + 1, state.lapses)
+    };
+
+    ReviewState{
+        ease,
+        interval_days,
+        due: clock.now() + Duration::days(i64::from(interval_days)),
+        repetitions,
+        lapses,
+        stability: state.stability,
+        difficulty: state.difficulty,
+        last_review: Some(clock.now()),
+    }
+}
+
+pub struct Sm2;
+
+impl Scheduler for Sm2{
+    fn name(&self)-> &'static str{
+        "sm2"
+    }
+
+    fn schedule(&self, state: &ReviewState, grade: Grade, clock: &dyn Clock)-> ReviewState{
+        sm2(state, grade, clock)
+    }
+
+    fn migrate(&self, state: &ReviewState)-> ReviewState{
+        ReviewState{
+            ease: state.difficulty.map_or(state.ease, ease_from_difficulty),
+            stability: None,
+            difficulty: None,
+            ..state.clone()
+        }
+    }
+}
+
+// Linear mapping between SM-2 ease (1.3 hardest) and FSRS difficulty (10 hardest)
+fn difficulty_from_ease(ease: f64)-> f64{
+    (10.0 - (ease - MINIMUM_EASE) / (MAXIMUM_EASE - MINIMUM_EASE) * 9.0).clamp(1.0, 10.0)
+}
+
+fn ease_from_difficulty(difficulty: f64)-> f64{
+    (MINIMUM_EASE + (10.0 - difficulty) / 9.0 * (MAXIMUM_EASE - MINIMUM_EASE)).clamp(MINIMUM_EASE, MAXIMUM_EASE)
+}
+
+// Probability of recalling an item with the given stability after elapsed days
+pub fn retrievability(elapsed_days: f64, stability: f64)-> f64{
+    (1.0 + FACTOR * elapsed_days / stability).powf(DECAY)
+}
+
+fn initial_stability(weights: &[f64], grade: Grade)-> f64{
+    weights[usize::from(grade.rating()) - 1].max(0.1)
+}
+
+fn initial_difficulty(weights: &[f64], grade: Grade)-> f64{
+    (weights[4] - (f64::from(grade.rating()) - 3.0) * weights[5]).clamp(1.0, 10.0)
+}
+
+fn next_difficulty(weights: &[f64], difficulty: f64, grade: Grade)-> f64{
+    let difficulty = difficulty - weights[6] * (f64::from(grade.rating()) - 3.0);
+    let reverted = weights[7] * initial_difficulty(weights, Grade::Good) + (1.0 - weights[7]) * difficulty;
+
+    reverted.clamp(1.0, 10.0)
+}
+
+fn next_stability(weights: &[f64], difficulty: f64, stability: f64, recall: f64, grade: Grade)-> f64{
+    if grade == Grade::Again{
+        let forgotten = weights[11]
+            * difficulty.powf(-weights[12])
+            * ((stability + 1.0).powf(weights[13]) - 1.0)
+            * (weights[14] * (1.0 - recall)).exp();
+
+        return forgotten.min(stability).max(0.1);
+    }
+
+    let hard_penalty = if grade == Grade::Hard{ weights[15] } else{ 1.0 };
+    let easy_bonus = if grade == Grade::Easy{ weights[16] } else{ 1.0 };
+
+    stability * (weights[8].exp()
+        * (11.0 - difficulty)
+        * stability.powf(-weights[9])
+        * ((weights[10] * (1.0 - recall)).exp() - 1.0)
+        * hard_penalty
+        * easy_bonus
+        + 1.0)
+}
+
+pub struct Fsrs{
+    pub weights: Vec<f64>,
+    pub desired_retention: f64,
+}
+
+impl Default for Fsrs{
+    fn default()-> Fsrs{
+        Fsrs{
+            weights: DEFAULT_WEIGHTS.to_vec(),
+            desired_retention: DEFAULT_RETENTION,
+        }
+    }
+}
+
+impl Fsrs{
+    // Days until recall is expected to drop to the desired retention
+    pub fn next_interval(&self, stability: f64)-> i32{
+        let interval = stability / FACTOR * (self.desired_retention.powf(1.0 / DECAY) - 1.0);
+
+        interval.round().clamp(1.0, MAXIMUM_INTERVAL) as i32
+    }
+}
+
+impl Scheduler for Fsrs{
+    fn name(&self)-> &'static str{
+        "fsrs"
+    }
+
+    fn schedule(&self, state: &ReviewState, grade: Grade, clock: &dyn Clock)-> ReviewState{
+        let now = clock.now();
+
+        let (stability, difficulty) = match (state.stability, state.difficulty, state.last_review){
+            (Some(stability), Some(difficulty), Some(last_review)) =>{
+                let elapsed_days = ((now - last_review).num_seconds() as f64 / 86400.0).max(0.0);
+                let recall = retrievability(elapsed_days, stability);
+
+                (next_stability(&self.weights, difficulty, stability, recall, grade),
+                    next_difficulty(&self.weights, difficulty, grade))
+            }
+            _ => (initial_stability(&self.weights, grade), initial_difficulty(&self.weights, grade)),
+        };
+
+        let (repetitions, lapses) = if grade == Grade::Again{
+            (0, if state.repetitions > 0{ state.lapses + 1 } else{ state.lapses })
+        }
+        else{
+            (state.repetitions + 1, state.lapses)
+        };
+        let interval_days = self.next_interval(stability);
+
+        ReviewState{
+            ease: state.ease,
+            interval_days,
+            due: now + Duration::days(i64::from(interval_days)),
+            repetitions,
+            lapses,
+            stability: Some(stability),
+            difficulty: Some(difficulty),
+            last_review: Some(now),
+        }
+    }
+
+    fn migrate(&self, state: &ReviewState)-> ReviewState{
+        // Never reviewed, FSRS seeds it from the first grade instead
+        if state.last_review.is_none() && state.repetitions == 0{
+            return ReviewState{ stability: None, difficulty: None, ..state.clone() };
+        }
+
+        // An SM-2 interval was picked so the item is still just about remembered when due,
+        // which is roughly what FSRS calls stability
+        ReviewState{
+            stability: Some(f64::from(state.interval_days.max(1))),
+            difficulty: Some(difficulty_from_ease(state.ease)),
+            last_review: state.last_review
+                .or(Some(state.due - Duration::days(i64::from(state.interval_days)))),
+            ..state.clone()
+        }
+    }
+}
+
+// Scheduling settings shared by a set of groups, the default leaves both algorithms untouched
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Preset{
+    pub new_per_day: i32,
+    pub max_interval: i32,
+    // Intervals in days of the first successful reviews, and of a review right after a lapse
+    pub learning_steps: Vec<i32>,
+    // Multiplier on the interval of an easy answer
+    pub ease_bonus: f64,
+    pub desired_retention: Option<f64>,
+}
+
+impl Default for Preset{
+    fn default()-> Preset{
+        Preset{
+            new_per_day: 20,
+            max_interval: MAXIMUM_INTERVAL as i32,
+            learning_steps: Vec::new(),
+            ease_bonus: 1.0,
+            desired_retention: None,
+        }
+    }
+}
+
+// Reshapes the interval a scheduler picked to a preset's learning steps, easy bonus and interval cap
+pub fn apply_preset(state: ReviewState, grade: Grade, preset: &Preset, clock: &dyn Clock)-> ReviewState{
+    let mut interval_days = state.interval_days;
+
+    if let Some(first_step) = preset.learning_steps.first(){
+        if grade == Grade::Again{
+            interval_days = *first_step;
+        }
+        else if let Some(step) = preset.learning_steps.get((state.repetitions - 1).max(0) as usize){
+            interval_days = *step;
+        }
+    }
+    if grade == Grade::Easy{
+        interval_days = (f64::from(interval_days) * preset.ease_bonus).round() as i32;
+    }
+    let interval_days = interval_days.clamp(1, preset.max_interval.max(1));
+
+    ReviewState{
+        interval_days,
+        due: clock.now() + Duration::days(i64::from(interval_days)),
+        ..state
+    }
+}
+
+// One item's answers in order, each with the days elapsed since the answer before it
+pub type ReviewHistory = Vec<(Grade, f64)>;
+
+// Mean log loss of the recall FSRS predicts against what actually happened
+fn fsrs_loss(weights: &[f64], histories: &[ReviewHistory])-> f64{
+    let mut loss = 0.0;
+    let mut count = 0;
+
+    for history in histories{
+        let mut memory = None::<(f64, f64)>;
+
+        for (grade, elapsed_days) in history{
+            memory = Some(match memory{
+                Some((stability, difficulty)) =>{
+                    let recall = retrievability(*elapsed_days, stability).clamp(0.0001, 0.9999);
+                    let recalled = if *grade == Grade::Again{ 0.0 } else{ 1.0 };
+
+                    loss -= recalled * recall.ln() + (1.0 - recalled) * (1.0 - recall).ln();
+                    count += 1;
+
+                    (next_stability(weights, difficulty, stability, recall, *grade),
+                        next_difficulty(weights, difficulty, *grade))
+                }
+                None => (initial_stability(weights, *grade), initial_difficulty(weights, *grade)),
+            });
+        }
+    }
+
+    if count == 0{ 0.0 } else{ loss / f64::from(count) }
+}
+
+// Fits FSRS weights to a user's review histories with Adam over numerical gradients,
+// deterministic so the same log always produces the same weights
+pub fn optimize_fsrs(histories: &[ReviewHistory], initial: &[f64], epochs: usize)-> Vec<f64>{
+    const LEARNING_RATE: f64 = 0.02;
+    const STEP: f64 = 0.0001;
+    const BETA1: f64 = 0.9;
+    const BETA2: f64 = 0.999;
+
+    let mut weights = initial.to_vec();
+    let mut best = (fsrs_loss(&weights, histories), weights.clone());
+    let mut moment = vec![0.0; weights.len()];
+    let mut velocity = vec![0.0; weights.len()];
+
+    for epoch in 1..=epochs{
+        let gradient = (0..weights.len())
+            .map(|idx|{
+                let mut ahead = weights.clone();
+                let mut behind = weights.clone();
+                ahead[idx] += STEP;
+                behind[idx] -= STEP;
+
+                (fsrs_loss(&ahead, histories) - fsrs_loss(&behind, histories)) / (2.0 * STEP)
+            })
+            .collect::<Vec<f64>>();
+
+        for idx in 0..weights.len(){
+            moment[idx] = BETA1 * moment[idx] + (1.0 - BETA1) * gradient[idx];
+            velocity[idx] = BETA2 * velocity[idx] + (1.0 - BETA2) * gradient[idx].powi(2);
+
+            let moment_hat = moment[idx] / (1.0 - BETA1.powi(epoch as i32));
+            let velocity_hat = velocity[idx] / (1.0 - BETA2.powi(epoch as i32));
+            let (lower, upper) = WEIGHT_BOUNDS[idx];
+
+            weights[idx] = (weights[idx] - LEARNING_RATE * moment_hat / (velocity_hat.sqrt() + 1e-8)).clamp(lower, upper);
+        }
+
+        let loss = fsrs_loss(&weights, histories);
+        if loss < best.0{
+            best = (loss, weights.clone());
+        }
+    }
+
+    best.1
+}
+
+#[cfg(test)]
+mod tests{
+    use chrono::TimeZone;
+    use super::*;
+
+    fn clock()-> FixedClock{
+        FixedClock(Utc.with_ymd_and_hms(2024, 1, 1, 9, 0, 0).unwrap())
+    }
+
+    // Answers each grade in turn on the day the previous answer scheduled it for
+    fn answer_all(scheduler: &dyn Scheduler, grades: &[Grade])-> Vec<ReviewState>{
+        let mut state = ReviewState::new(&clock());
+        grades.iter()
+            .map(|grade|{
+                state = scheduler.schedule(&state, *grade, &FixedClock(state.due));
+                state.clone()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sm2_intervals_grow_by_ease(){
+        let states = answer_all(&Sm2, &[Grade::Good; 5]);
+
+        let intervals = states.iter().map(|state| state.interval_days).collect::<Vec<i32>>();
+        assert_eq!(intervals, [1, 6, 15, 38, 95]);
+        assert!(states.iter().all(|state| state.ease == STARTING_EASE));
+        assert_eq!(states[4].repetitions, 5);
+        assert_eq!(states[0].due, clock().now() + Duration::days(1));
+        assert_eq!(states[0].last_review, Some(clock().now()));
+    }
+
+    #[test]
+    fn sm2_ease_follows_grade(){
+        let ease = |grade| sm2(&ReviewState::new(&clock()), grade, &clock()).ease;
+
+        assert!((ease(Grade::Easy) - 2.6).abs() < 1e-9);
+        assert!((ease(Grade::Good) - 2.5).abs() < 1e-9);
+        assert!((ease(Grade::Hard) - 2.36).abs() < 1e-9);
+        assert!((ease(Grade::Again) - 1.96).abs() < 1e-9);
+    }
+
+    #[test]
+    fn sm2_ease_never_drops_below_minimum(){
+        let states = answer_all(&Sm2, &[Grade::Again; 4]);
+
+        let eases = states.iter().map(|state| (state.ease * 100.0).round() / 100.0).collect::<Vec<f64>>();
+        assert_eq!(eases, [1.96, 1.42, MINIMUM_EASE, MINIMUM_EASE]);
+    }
+
+    #[test]
+    fn sm2_lapse_restarts_item(){
+        let states = answer_all(&Sm2, &[Grade::Good, Grade::Good, Grade::Good, Grade::Again, Grade::Good]);
+
+        assert_eq!((states[3].interval_days, states[3].repetitions, states[3].lapses), (1, 0, 1));
+        assert_eq!((states[4].interval_days, states[4].repetitions, states[4].lapses), (1, 1, 1));
+        // Failing an item never recalled isn't a lapse
+        assert_eq!(answer_all(&Sm2, &[Grade::Again])[0].lapses, 0);
+    }
+
+    #[test]
+    fn fsrs_first_answer_seeds_stability(){
+        let fsrs = Fsrs::default();
+        let first = |grade| fsrs.schedule(&ReviewState::new(&clock()), grade, &clock());
+
+        // At 90% retention the interval is the stability itself
+        assert_eq!(first(Grade::Again).interval_days, 1);
+        assert_eq!(first(Grade::Hard).interval_days, 1);
+        assert_eq!(first(Grade::Good).interval_days, 4);
+        assert_eq!(first(Grade::Easy).interval_days, 14);
+        assert_eq!(first(Grade::Good).stability, Some(DEFAULT_WEIGHTS[2]));
+        assert_eq!(first(Grade::Good).ease, STARTING_EASE);
+    }
+
+    #[test]
+    fn fsrs_stability_grows_on_recall(){
+        let states = answer_all(&Fsrs::default(), &[Grade::Good, Grade::Good, Grade::Good, Grade::Again]);
+
+        let stability = states.iter().map(|state| state.stability.unwrap()).collect::<Vec<f64>>();
+        assert!(stability[1] > stability[0] && stability[2] > stability[1]);
+        assert!(stability[3] < stability[2]);
+        assert!(states[1].interval_days > states[0].interval_days);
+        assert_eq!((states[3].repetitions, states[3].lapses), (0, 1));
+    }
+
+    #[test]
+    fn fsrs_lower_retention_spaces_further(){
+        let strict = Fsrs{ desired_retention: 0.95, ..Fsrs::default() };
+        let lenient = Fsrs{ desired_retention: 0.8, ..Fsrs::default() };
+
+        assert!(strict.next_interval(10.0) < 10);
+        assert!(lenient.next_interval(10.0) > 10);
+    }
+
+    #[test]
+    fn migrate_between_schedulers(){
+        let sm2_state = answer_all(&Sm2, &[Grade::Good; 3]).pop().unwrap();
+
+        let fsrs_state = Fsrs::default().migrate(&sm2_state);
+        assert_eq!(fsrs_state.stability, Some(15.0));
+        assert_eq!(fsrs_state.due, sm2_state.due);
+        assert!((Sm2.migrate(&fsrs_state).ease - sm2_state.ease).abs() < 1e-9);
+
+        // Never reviewed, nothing to carry over
+        let new_state = Fsrs::default().migrate(&ReviewState::new(&clock()));
+        assert_eq!(new_state.stability, None);
+    }
+
+    #[test]
+    fn preset_learning_steps_override_first_intervals(){
+        let preset = Preset{ learning_steps: vec![1, 3], ..Preset::default() };
+        let mut state = ReviewState::new(&clock());
+        let mut intervals = Vec::new();
+        for grade in [Grade::Good, Grade::Good, Grade::Good, Grade::Again]{
+            state = apply_preset(sm2(&state, grade, &clock()), grade, &preset, &clock());
+            intervals.push(state.interval_days);
+        }
+
+        assert_eq!(intervals, [1, 3, 8, 1]);
+        assert_eq!(state.due, clock().now() + Duration::days(1));
+    }
+
+    #[test]
+    fn preset_easy_bonus_and_cap(){
+        let state = ReviewState{ interval_days: 10, repetitions: 3, ..ReviewState::new(&clock()) };
+
+        let bonus = Preset{ ease_bonus: 1.5, ..Preset::default() };
+        assert_eq!(apply_preset(state.clone(), Grade::Easy, &bonus, &clock()).interval_days, 15);
+        assert_eq!(apply_preset(state.clone(), Grade::Good, &bonus, &clock()).interval_days, 10);
+
+        let capped = Preset{ max_interval: 7, ..bonus };
+        let capped_state = apply_preset(state, Grade::Easy, &capped, &clock());
+        assert_eq!(capped_state.interval_days, 7);
+        assert_eq!(capped_state.due, clock().now() + Duration::days(7));
+    }
+}
@@ -0,0 +1,286 @@
+use chrono::{DateTime, Duration, Utc};
+use diesel::{
+    pg::PgConnection,
+    prelude::*,
+};
+use lib::schema::*;
+use lib::models::*;
+use lib::srs::{ReviewState, SystemClock};
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+use crate::commands::{Eval, establish_connection};
+use crate::review::{review_changes, review_item, review_state};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ReviewActionKind{
+    Suspend,
+    Unsuspend,
+    Bury,
+    Reset,
+}
+
+impl ReviewActionKind{
+    pub fn as_str(&self)-> &'static str{
+        match self{
+            ReviewActionKind::Suspend => "suspend",
+            ReviewActionKind::Unsuspend => "unsuspend",
+            ReviewActionKind::Bury => "bury",
+            ReviewActionKind::Reset => "reset",
+        }
+    }
+}
+
+// Everything a suspend, bury or reset can change about a review
+#[derive(Serialize, Deserialize)]
+struct ReviewSnapshot{
+    review_id: i32,
+    state: ReviewState,
+    leech: bool,
+    suspended: bool,
+    buried_until: Option<DateTime<Utc>>,
+}
+
+// Reviews an action applies to: one item, optionally one card of it, a whole group, or every
+// kanji and vocab whose symbol, phrase or meaning contains a search term
+fn target_reviews(connection: &mut PgConnection, user: &User, payload: &Value)-> Eval<Vec<Review>>{
+    let kind = payload["kind"].as_str();
+    if !matches!(kind, None | Some("kanji") | Some("vocab")){
+        return Err("INVALID_KIND");
+    }
+
+    let (kanji_ids, vocab_ids) = if let Some(item) = payload["item"].as_str(){
+        let ids = match kind{
+            Some("kanji") => (Kanji::belonging_to(user)
+                .filter(kanji::symbol.eq(item))
+                .select(kanji::id)
+                .load::<i32>(connection)
+                .unwrap_or_default(), Vec::new()),
+            Some(_) => (Vec::new(), Vocab::belonging_to(user)
+                .filter(vocab::phrase.eq(item))
+                .select(vocab::id)
+                .load::<i32>(connection)
+                .unwrap_or_default()),
+            None => return Err("INVALID_FORMAT"),
+        };
+        if ids.0.is_empty() && ids.1.is_empty(){
+            return Err("INVALID_ITEM");
+        }
+
+        ids
+    }
+    else if let Some(group_title) = payload["group_title"].as_str(){
+        let group_ids = Group::belonging_to(user)
+            .filter(groups::title.eq(group_title))
+            .select(groups::id)
+            .load::<i32>(connection)
+            .unwrap_or_default();
+        if group_ids.is_empty(){
+            return Err("INVALID_GROUP");
+        }
+
+        (Kanji::belonging_to(user)
+            .filter(kanji::group_id.eq_any(&group_ids))
+            .select(kanji::id)
+            .load::<i32>(connection)
+            .unwrap_or_default(),
+        Vocab::belonging_to(user)
+            .filter(vocab::group_id.eq_any(&group_ids))
+            .select(vocab::id)
+            .load::<i32>(connection)
+            .unwrap_or_default())
+    }
+    else if let Some(search) = payload["search"].as_str().filter(|search| !search.trim().is_empty()){
+        // Wildcards typed in the search match themselves, postgres escapes with a backslash by default
+        let escaped = search.trim()
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        let pattern = format!("%{escaped}%");
+
+        (Kanji::belonging_to(user)
+            .filter(kanji::symbol.eq(search.trim()).or(kanji::meaning.ilike(&pattern)))
+            .select(kanji::id)
+            .load::<i32>(connection)
+            .unwrap_or_default(),
+        Vocab::belonging_to(user)
+            .filter(vocab::phrase.like(&pattern).or(vocab::meaning.ilike(&pattern)))
+            .select(vocab::id)
+            .load::<i32>(connection)
+            .unwrap_or_default())
+    }
+    else{
+        return Err("INVALID_FORMAT");
+    };
+
+    let kanji_ids = if kind == Some("vocab"){ Vec::new() } else{ kanji_ids };
+    let vocab_ids = if kind == Some("kanji"){ Vec::new() } else{ vocab_ids };
+
+    let mut query = Review::belonging_to(user)
+        .filter(reviews::kanji_id.eq_any(kanji_ids).or(reviews::vocab_id.eq_any(vocab_ids)))
+        .into_boxed();
+    if let Some(card) = payload["card"].as_str(){
+        query = query.filter(reviews::card.eq(card));
+    }
+
+    Ok(query.order(reviews::id)
+        .load::<Review>(connection)
+        .unwrap_or_default())
+}
+
+// Suspends, unsuspends, buries until tomorrow or resets the progress of the targeted reviews,
+// keeping what they were before so the whole action can be undone
+pub fn apply_review_action(user: &User, payload: String, action: ReviewActionKind)-> Eval<String>{
+    let connection = &mut establish_connection();
+
+    if users::table.find(user.id)
+        .first::<User>(connection).is_err(){
+        return Err("INVALID_USER")
+    }
+
+    let payload = serde_json::from_str::<Value>(&payload)
+        .map_err(|_| "INVALID_FORMAT")?;
+    let targets = target_reviews(connection, user, &payload)?;
+
+    let now = Utc::now();
+    let tomorrow = (now + Duration::days(1)).date_naive()
+        .and_hms_opt(0, 0, 0)
+        .unwrap_or_default()
+        .and_utc();
+
+    let snapshot = targets.iter()
+        .map(|review| ReviewSnapshot{
+            review_id: review.id,
+            state: review_state(review),
+            leech: review.leech,
+            suspended: review.suspended,
+            buried_until: review.buried_until,
+        })
+        .collect::<Vec<ReviewSnapshot>>();
+
+    let action_id = connection.transaction::<_, diesel::result::Error, _>(|connection|{
+        for review in &targets{
+            match action{
+                ReviewActionKind::Suspend => diesel::update(review)
+                    .set(reviews::suspended.eq(true))
+                    .execute(connection)?,
+                ReviewActionKind::Unsuspend => diesel::update(review)
+                    .set(reviews::suspended.eq(false))
+                    .execute(connection)?,
+                ReviewActionKind::Bury => diesel::update(review)
+                    .set(reviews::buried_until.eq(tomorrow))
+                    .execute(connection)?,
+                ReviewActionKind::Reset => diesel::update(review)
+                    .set((
+                        &review_changes(&ReviewState::new(&SystemClock)),
+                        reviews::leech.eq(false),
+                        reviews::suspended.eq(false),
+                        reviews::buried_until.eq(None::<DateTime<Utc>>),
+                    ))
+                    .execute(connection)?,
+            };
+        }
+
+        diesel::insert_into(review_actions::table)
+            .values(&NewReviewAction{
+                action: action.as_str().to_owned(),
+                performed_at: now,
+                snapshot: serde_json::to_string(&snapshot).unwrap_or_default(),
+                user_id: user.id,
+            })
+            .returning(review_actions::id)
+            .get_result::<i32>(connection)
+    }).map_err(|_| "ACTION_FAILED")?;
+
+    Ok(json!({
+        "action_id": action_id,
+        "affected": targets.len(),
+    }).to_string())
+}
+
+// Puts back the reviews touched by the user's latest action, or by the given one
+pub fn undo_action(user: &User, payload: String)-> Eval<String>{
+    let connection = &mut establish_connection();
+
+    if users::table.find(user.id)
+        .first::<User>(connection).is_err(){
+        return Err("INVALID_USER")
+    }
+
+    let payload = if payload.is_empty(){
+        Value::Null
+    }
+    else if let Ok(payload) = serde_json::from_str::<Value>(&payload){
+        payload
+    }
+    else{
+        return Err("INVALID_FORMAT");
+    };
+
+    let mut query = ReviewAction::belonging_to(user)
+        .filter(review_actions::undone.eq(false))
+        .into_boxed();
+    if let Some(action_id) = payload["action_id"].as_i64(){
+        query = query.filter(review_actions::id.eq(action_id as i32));
+    }
+
+    let action = query.order(review_actions::id.desc())
+        .first::<ReviewAction>(connection)
+        .map_err(|_| "NOTHING_TO_UNDO")?;
+    let snapshot = serde_json::from_str::<Vec<ReviewSnapshot>>(&action.snapshot)
+        .map_err(|_| "NOTHING_TO_UNDO")?;
+
+    // Cards answered since a reset have a newer schedule than the one it replaced, putting that back
+    // would throw those answers away while their logs stay, so they're left as they are
+    let reset = action.action == ReviewActionKind::Reset.as_str();
+    let reviews = reviews::table.filter(reviews::id.eq_any(snapshot.iter().map(|prior| prior.review_id)))
+        .load::<Review>(connection)
+        .unwrap_or_default();
+    let (restored, skipped) = reviews.iter()
+        .partition::<Vec<&Review>, _>(|review| !reset || review.last_review.is_none_or(|last_review| last_review <= action.performed_at));
+
+    connection.transaction::<_, diesel::result::Error, _>(|connection|{
+        // Reviews of items deleted since are skipped. Only a reset touched the schedule, the other
+        // actions leave progress made since they were applied alone
+        for prior in snapshot.iter().filter(|prior| restored.iter().any(|review| review.id == prior.review_id)){
+            let review = reviews::table.find(prior.review_id);
+            if reset{
+                diesel::update(review)
+                    .set((
+                        &review_changes(&prior.state),
+                        reviews::leech.eq(prior.leech),
+                        reviews::suspended.eq(prior.suspended),
+                        reviews::buried_until.eq(prior.buried_until),
+                    ))
+                    .execute(connection)?;
+            }
+            else{
+                diesel::update(review)
+                    .set((
+                        reviews::suspended.eq(prior.suspended),
+                        reviews::buried_until.eq(prior.buried_until),
+                    ))
+                    .execute(connection)?;
+            }
+        }
+
+        diesel::update(&action)
+            .set(review_actions::undone.eq(true))
+            .execute(connection)?;
+
+        Ok(())
+    }).map_err(|_| "ACTION_FAILED")?;
+
+    let skipped = skipped.into_iter()
+        .map(|review|{
+            let (kind, item) = review_item(connection, review);
+            json!({ "kind": kind, "item": item, "card": review.card })
+        })
+        .collect::<Vec<Value>>();
+
+    Ok(json!({
+        "action_id": action.id,
+        "action": action.action,
+        "restored": restored.len(),
+        "skipped": skipped,
+    }).to_string())
+}
@@ -0,0 +1,147 @@
+use std::{cmp::Reverse, collections::HashMap};
+use diesel::{
+    pg::PgConnection,
+    prelude::*,
+};
+use lib::schema::*;
+use lib::models::*;
+use lib::kana::is_kanji;
+use serde_json::{json, Value};
+use crate::commands::{Eval, establish_connection};
+use crate::spreadsheet::{DEFAULT_SEPARATOR, KANJI_COLUMNS, join_cell};
+
+// Kanji of a text with how often each appears, in order of first appearance
+fn text_kanji(text: &str)-> Vec<(String, usize)>{
+    let mut positions = HashMap::new();
+    let mut counts: Vec<(String, usize)> = Vec::new();
+
+    // The iteration mark repeats the kanji before it rather than being one to learn
+    for symbol in text.chars().filter(|c| is_kanji(*c) && *c != '々'){
+        let position = *positions.entry(symbol).or_insert_with(||{
+            counts.push((symbol.to_string(), 0));
+            counts.len() - 1
+        });
+        counts[position].1 += 1;
+    }
+
+    counts
+}
+
+// Sheet of the unknown kanji in IMPORT_CSV's kanji layout filled in from KANJIDIC2, along with the
+// ones it has no entry for. Those are left off since a row without a meaning wouldn't import
+fn unknown_sheet(connection: &mut PgConnection, unknown: &[(String, usize)])-> Eval<(String, Vec<String>)>{
+    let symbols = unknown.iter().map(|(symbol, _)| symbol).collect::<Vec<&String>>();
+    let entries = kanjidic::table.filter(kanjidic::symbol.eq_any(symbols))
+        .load::<KanjidicEntry>(connection)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|entry| (entry.symbol.to_owned(), entry))
+        .collect::<HashMap<String, KanjidicEntry>>();
+
+    let metadata_cell = |value: Option<i32>| value.map(|value| value.to_string()).unwrap_or_default();
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    let write_error = |_| "INVALID_FORMAT";
+    let mut unlisted = Vec::new();
+    writer.write_record(KANJI_COLUMNS).map_err(write_error)?;
+    for (symbol, _) in unknown{
+        let (entry, meaning) = match entries.get(symbol).map(|entry| (entry, join_cell(&entry.meanings, ", "))){
+            Some((entry, meaning)) if !meaning.is_empty() => (entry, meaning),
+            _ =>{
+                unlisted.push(symbol.to_owned());
+                continue;
+            }
+        };
+
+        writer.write_record([
+            symbol.to_owned(),
+            meaning,
+            join_cell(&entry.onyomi, DEFAULT_SEPARATOR),
+            join_cell(&entry.kunyomi, DEFAULT_SEPARATOR),
+            String::new(),
+            String::new(),
+            String::new(),
+            entry.stroke_count.to_string(),
+            // KANJIDIC2's jlpt is the old scale, the N level is left for the user
+            String::new(),
+            metadata_cell(entry.grade),
+            metadata_cell(entry.frequency_rank),
+        ]).map_err(write_error)?;
+    }
+
+    let sheet = writer.into_inner()
+        .ok()
+        .and_then(|text| String::from_utf8(text).ok())
+        .ok_or("INVALID_FORMAT")?;
+
+    Ok((sheet, unlisted))
+}
+
+// Splits the kanji of a passage into ones the user has and ones they don't, with the share of the
+// passage's kanji they already know and their vocab it uses. The unknown kanji come back as a
+// sheet IMPORT_CSV creates them from, apart from ones KANJIDIC2 can't fill in
+pub fn analyze_text(user: &User, payload: String)-> Eval<String>{
+    let connection = &mut establish_connection();
+
+    if users::table.find(user.id)
+        .first::<User>(connection).is_err(){
+        return Err("INVALID_USER")
+    }
+
+    if let Ok(payload) = serde_json::from_str::<Value>(&payload){
+        if let Some(text) = payload["text"].as_str(){
+            let counts = text_kanji(text);
+            let symbols = counts.iter().map(|(symbol, _)| symbol).collect::<Vec<&String>>();
+            let known_symbols = Kanji::belonging_to(user)
+                .filter(kanji::symbol.eq_any(symbols))
+                .select(kanji::symbol)
+                .load::<String>(connection)
+                .unwrap_or_default();
+
+            let (mut known, mut unknown) = counts.into_iter()
+                .partition::<Vec<(String, usize)>, _>(|(symbol, _)| known_symbols.contains(symbol));
+            known.sort_by_key(|(_, count)| Reverse(*count));
+            unknown.sort_by_key(|(_, count)| Reverse(*count));
+
+            let known_count = known.iter().map(|(_, count)| count).sum::<usize>();
+            let total_count = known_count + unknown.iter().map(|(_, count)| count).sum::<usize>();
+            let coverage = if total_count == 0{
+                100.0
+            }
+            else{
+                (known_count as f64 / total_count as f64 * 10000.0).round() / 100.0
+            };
+
+            let mut vocab = Vocab::belonging_to(user)
+                .select(vocab::phrase)
+                .load::<String>(connection)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|phrase|{
+                    let count = text.matches(phrase.as_str()).count();
+                    (phrase, count)
+                })
+                .filter(|(_, count)| *count > 0)
+                .collect::<Vec<(String, usize)>>();
+            vocab.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+            let (sheet, unlisted) = unknown_sheet(connection, &unknown)?;
+            let listing = |items: &[(String, usize)], key: &str| items.iter()
+                .map(|(item, count)| json!({ key: item, "count": count }))
+                .collect::<Vec<Value>>();
+
+            return Ok(json!({
+                "known": listing(&known, "symbol"),
+                "unknown": listing(&unknown, "symbol"),
+                "known_count": known_count,
+                "total_count": total_count,
+                "coverage": coverage,
+                "vocab": listing(&vocab, "phrase"),
+                "unknown_sheet": sheet,
+                "unlisted": unlisted,
+            }).to_string());
+        }
+    }
+
+    Err("INVALID_FORMAT")
+}
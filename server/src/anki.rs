@@ -0,0 +1,1049 @@
+use std::{collections::{BTreeSet, HashMap, HashSet}, fs, io::{Cursor, Read, Write}, sync::LazyLock};
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use chrono::{DateTime, Utc};
+use diesel::{
+    pg::PgConnection,
+    prelude::*,
+};
+use lib::schema::*;
+use lib::models::*;
+use lib::srs::*;
+use regex::Regex;
+use rusqlite::OpenFlags;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use crate::commands::{Eval, establish_connection, insert_kanji, insert_vocab};
+use crate::dictionary::CHUNK_SIZE;
+use crate::presets::PresetIndex;
+use crate::review::{review_changes, user_scheduler};
+
+// Anki's built in deck, notes left in it don't get a group
+const DEFAULT_DECK_ID: i64 = 1;
+
+// Note fields are cleaned up one by one, so the patterns are only compiled once
+static LINE_BREAK: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)<br\s*/?>|</div>").unwrap());
+static HTML_TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<[^>]*>").unwrap());
+static FURIGANA: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\[[^\]]*\]").unwrap());
+
+struct AnkiModel{
+    name: String,
+    fields: Vec<String>,
+}
+
+struct AnkiNote{
+    id: i64,
+    model_id: i64,
+    fields: Vec<String>,
+}
+
+struct AnkiCard{
+    id: i64,
+    note_id: i64,
+    deck_id: i64,
+    ord: i64,
+    queue: i64,
+}
+
+struct AnkiRevlog{
+    card_id: i64,
+    reviewed_at: i64,
+    ease: i64,
+    time: i64,
+    kind: i64,
+}
+
+struct AnkiCollection{
+    models: HashMap<i64, AnkiModel>,
+    decks: HashMap<i64, String>,
+    notes: Vec<AnkiNote>,
+    cards: Vec<AnkiCard>,
+    revlog: Vec<AnkiRevlog>,
+}
+
+// Which kms item a note type becomes, fields maps kms fields (symbol, phrase, meaning, onyomi,
+// kunyomi, reading, description) to the note type's field names and cards Anki card ordinals to kms cards
+#[derive(Deserialize)]
+pub struct NoteMapping{
+    kind: String,
+    fields: HashMap<String, String>,
+    #[serde(default)]
+    cards: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct AnkiImport{
+    // The package as base64, requests are one line of JSON so raw bytes can't be sent
+    #[serde(default)]
+    file: String,
+    mapping: HashMap<String, NoteMapping>,
+    #[serde(default)]
+    history: bool,
+    #[serde(default)]
+    dry_run: bool,
+}
+
+fn query_collection(path: &std::path::Path)-> rusqlite::Result<AnkiCollection>{
+    let sqlite = rusqlite::Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+
+    let (models_json, decks_json) = sqlite.query_row("SELECT models, decks FROM col", [], |row|{
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+    })?;
+
+    let mut models = HashMap::new();
+    let mut decks = HashMap::new();
+    let models_json = serde_json::from_str::<Value>(&models_json).unwrap_or_default();
+    let decks_json = serde_json::from_str::<Value>(&decks_json).unwrap_or_default();
+
+    if let (Some(models_json), Some(decks_json)) = (models_json.as_object(), decks_json.as_object()){
+        for (model_id, model) in models_json{
+            let mut fields = model["flds"].as_array()
+                .map(|fields| fields.iter()
+                    .map(|field| (field["ord"].as_i64().unwrap_or(0), field["name"].as_str().unwrap_or_default().to_owned()))
+                    .collect::<Vec<(i64, String)>>())
+                .unwrap_or_default();
+            fields.sort();
+
+            models.insert(model_id.parse::<i64>().unwrap_or_default(), AnkiModel{
+                name: model["name"].as_str().unwrap_or_default().to_owned(),
+                fields: fields.into_iter().map(|(_, name)| name).collect(),
+            });
+        }
+        for (deck_id, deck) in decks_json{
+            decks.insert(deck_id.parse::<i64>().unwrap_or_default(), deck["name"].as_str().unwrap_or_default().to_owned());
+        }
+    }
+
+    // Newer collections keep note types and decks in their own tables, with \x1f between deck levels
+    if models.is_empty(){
+        let mut notetypes = sqlite.prepare("SELECT id, name FROM notetypes")?;
+        for notetype in notetypes.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?{
+            let (model_id, name) = notetype?;
+            models.insert(model_id, AnkiModel{ name, fields: Vec::new() });
+        }
+
+        let mut fields = sqlite.prepare("SELECT ntid, name FROM fields ORDER BY ntid, ord")?;
+        for field in fields.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?{
+            let (model_id, name) = field?;
+            if let Some(model) = models.get_mut(&model_id){
+                model.fields.push(name);
+            }
+        }
+
+        let mut deck_rows = sqlite.prepare("SELECT id, name FROM decks")?;
+        for deck in deck_rows.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?{
+            let (deck_id, name) = deck?;
+            decks.insert(deck_id, name.replace('\x1f', "::"));
+        }
+    }
+
+    let notes = sqlite.prepare("SELECT id, mid, flds FROM notes ORDER BY id")?
+        .query_map([], |row| Ok(AnkiNote{
+            id: row.get(0)?,
+            model_id: row.get(1)?,
+            fields: row.get::<_, String>(2)?.split('\x1f').map(|field| field.to_owned()).collect(),
+        }))?
+        .collect::<rusqlite::Result<Vec<AnkiNote>>>()?;
+
+    let cards = sqlite.prepare("SELECT id, nid, did, ord, queue FROM cards ORDER BY nid, ord")?
+        .query_map([], |row| Ok(AnkiCard{
+            id: row.get(0)?,
+            note_id: row.get(1)?,
+            deck_id: row.get(2)?,
+            ord: row.get(3)?,
+            queue: row.get(4)?,
+        }))?
+        .collect::<rusqlite::Result<Vec<AnkiCard>>>()?;
+
+    let revlog = sqlite.prepare("SELECT cid, id, ease, time, type FROM revlog ORDER BY id")?
+        .query_map([], |row| Ok(AnkiRevlog{
+            card_id: row.get(0)?,
+            reviewed_at: row.get(1)?,
+            ease: row.get(2)?,
+            time: row.get(3)?,
+            kind: row.get(4)?,
+        }))?
+        .collect::<rusqlite::Result<Vec<AnkiRevlog>>>()?;
+
+    Ok(AnkiCollection{ models, decks, notes, cards, revlog })
+}
+
+// Opens the SQLite collection inside an .apkg, preferring the 2.1 collection over the legacy one
+fn read_apkg(file: &[u8])-> Eval<AnkiCollection>{
+    let mut archive = zip::ZipArchive::new(Cursor::new(file))
+        .map_err(|_| "INVALID_FILE")?;
+
+    let entry_name = ["collection.anki21", "collection.anki2"].into_iter()
+        .find(|name| archive.file_names().any(|file_name| file_name == *name));
+    let entry_name = match entry_name{
+        Some(entry_name) => entry_name,
+        None if archive.file_names().any(|file_name| file_name == "collection.anki21b") => return Err("UNSUPPORTED_COLLECTION"),
+        None => return Err("INVALID_FILE"),
+    };
+
+    let mut collection = Vec::new();
+    archive.by_name(entry_name)
+        .map_err(|_| "INVALID_FILE")?
+        .read_to_end(&mut collection)
+        .map_err(|_| "INVALID_FILE")?;
+
+    // SQLite only opens collections from disk
+    let path = std::env::temp_dir().join(format!("kms-{}-{}.anki2",
+        std::process::id(),
+        Utc::now().timestamp_nanos_opt().unwrap_or_default()));
+    fs::write(&path, collection).map_err(|_| "IMPORT_FAILED")?;
+    let result = query_collection(&path);
+    fs::remove_file(&path).ok();
+
+    result.map_err(|_| "INVALID_FILE")
+}
+
+// Plain text of a note field, without its HTML
+fn field_text(field: &str)-> String{
+    let text = LINE_BREAK.replace_all(field, "\n");
+    let text = HTML_TAG.replace_all(&text, "");
+
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&amp;", "&")
+        .trim()
+        .to_owned()
+}
+
+// Symbol or phrase of a note field, Anki's furigana brackets dropped, e.g. 食[た]べる
+fn field_symbol(field: &str)-> String{
+    FURIGANA.replace_all(&field_text(field), "")
+        .split_whitespace()
+        .collect()
+}
+
+fn field_list(field: &str)-> Vec<Option<String>>{
+    field_text(field)
+        .split(|c: char| c.is_whitespace() || "、,，;；/".contains(c))
+        .filter(|reading| !reading.is_empty())
+        .map(|reading| Some(reading.to_owned()))
+        .collect()
+}
+
+// Anki card ordinals mapped to kms cards, the first template defaults to the meaning card
+fn mapped_cards(mapping: &NoteMapping)-> Eval<HashMap<i64, Card>>{
+    if mapping.cards.is_empty(){
+        return Ok(HashMap::from([(0, Card::Meaning)]));
+    }
+
+    mapping.cards.iter()
+        .map(|(ord, card)| Ok((
+            ord.parse::<i64>().map_err(|_| "INVALID_MAPPING")?,
+            Card::parse(&mapping.kind, card).ok_or("INVALID_MAPPING")?,
+        )))
+        .collect()
+}
+
+fn validate_mapping(mapping: &HashMap<String, NoteMapping>)-> Eval<()>{
+    for note_mapping in mapping.values(){
+        let (required, optional) = match note_mapping.kind.as_str(){
+            "kanji" => (["symbol", "meaning"], &["onyomi", "kunyomi", "description"][..]),
+            "vocab" => (["phrase", "meaning"], &["reading", "description"][..]),
+            _ => return Err("INVALID_MAPPING"),
+        };
+
+        if !required.iter().all(|field| note_mapping.fields.contains_key(*field))
+            || !note_mapping.fields.keys().all(|field| required.contains(&field.as_str()) || optional.contains(&field.as_str())){
+            return Err("INVALID_MAPPING");
+        }
+        mapped_cards(note_mapping)?;
+    }
+
+    Ok(())
+}
+
+fn find_or_create_group(connection: &mut PgConnection, user: &User, title: &str, vocab: bool)-> QueryResult<i32>{
+    if let Ok(group_id) = Group::belonging_to(user)
+        .filter(groups::title.eq(title))
+        .filter(groups::vocab.eq(vocab))
+        .select(groups::id)
+        .first::<i32>(connection){
+        return Ok(group_id);
+    }
+
+    diesel::insert_into(groups::table)
+        .values(&NewGroup{ title: title.to_owned(), colour: None, vocab, user_id: user.id })
+        .returning(groups::id)
+        .get_result::<i32>(connection)
+}
+
+// Replays an Anki card's answers through the user's scheduler onto the kms review it maps to,
+// returns how many answers were carried over
+fn carry_over_history(connection: &mut PgConnection, user: &User, review: &Review, revlog: &[&AnkiRevlog], preset: &Preset, suspended: bool)-> QueryResult<usize>{
+    let scheduler = user_scheduler(user, preset);
+    let mut state = ReviewState::new(&SystemClock);
+    let mut logs = Vec::new();
+
+    // Manual reschedules aren't answers
+    for entry in revlog.iter().filter(|entry| (1..=4).contains(&entry.ease) && entry.kind != 4){
+        let grade = [Grade::Again, Grade::Hard, Grade::Good, Grade::Easy][entry.ease as usize - 1];
+        let reviewed_at = DateTime::<Utc>::from_timestamp_millis(entry.reviewed_at).unwrap_or_default();
+        let prior = state.clone();
+        let elapsed_days = prior.last_review
+            .map(|last_review| ((reviewed_at - last_review).num_seconds() as f64 / 86400.0).max(0.0))
+            .unwrap_or(0.0);
+        state = apply_preset(scheduler.schedule(&prior, grade, &FixedClock(reviewed_at)), grade, preset, &FixedClock(reviewed_at));
+
+        logs.push(NewReviewLog{
+            grade: grade.as_str().to_owned(),
+            reviewed_at,
+            elapsed_days,
+            user_id: user.id,
+            review_id: review.id,
+            card: review.card.to_owned(),
+            response_ms: i32::try_from(entry.time).ok(),
+            prior_ease: prior.ease,
+            prior_interval_days: prior.interval_days,
+            prior_due: prior.due,
+            prior_repetitions: prior.repetitions,
+            prior_lapses: prior.lapses,
+            prior_stability: prior.stability,
+            prior_difficulty: prior.difficulty,
+            prior_last_review: prior.last_review,
+            session_id: None,
+            prior_leech: false,
+            prior_suspended: false,
+        });
+    }
+
+    if logs.is_empty(){
+        return Ok(0);
+    }
+
+    let leech = state.lapses >= user.leech_threshold;
+    diesel::update(review)
+        .set((
+            &review_changes(&state),
+            reviews::leech.eq(leech),
+            reviews::suspended.eq(suspended || (leech && user.leech_action == "suspend")),
+        ))
+        .execute(connection)?;
+
+    for chunk in logs.chunks(CHUNK_SIZE){
+        diesel::insert_into(review_logs::table)
+            .values(chunk)
+            .execute(connection)?;
+    }
+
+    Ok(logs.len())
+}
+
+// Maps the collection's notes onto kms kanji and vocab, reporting what would be created and what's
+// skipped, and creates it all unless it's a dry run
+fn import_collection(connection: &mut PgConnection, user: &User, collection: AnkiCollection, import: &AnkiImport)-> Eval<Value>{
+    validate_mapping(&import.mapping)?;
+
+    let existing_kanji = Kanji::belonging_to(user)
+        .select(kanji::symbol)
+        .load::<String>(connection)
+        .unwrap_or_default()
+        .into_iter()
+        .collect::<HashSet<String>>();
+    let existing_vocab = Vocab::belonging_to(user)
+        .select(vocab::phrase)
+        .load::<String>(connection)
+        .unwrap_or_default()
+        .into_iter()
+        .collect::<HashSet<String>>();
+    let existing_groups = Group::belonging_to(user)
+        .select((groups::title, groups::vocab))
+        .load::<(String, bool)>(connection)
+        .unwrap_or_default()
+        .into_iter()
+        .collect::<HashSet<(String, bool)>>();
+
+    let mut note_cards = HashMap::<i64, Vec<&AnkiCard>>::new();
+    for card in &collection.cards{
+        note_cards.entry(card.note_id).or_default().push(card);
+    }
+    let mut card_revlog = HashMap::<i64, Vec<&AnkiRevlog>>::new();
+    for entry in &collection.revlog{
+        card_revlog.entry(entry.card_id).or_default().push(entry);
+    }
+
+    let mut new_kanji = Vec::new();
+    let mut new_vocab = Vec::new();
+    let mut duplicate_kanji = Vec::new();
+    let mut duplicate_vocab = Vec::new();
+    let mut new_groups = BTreeSet::new();
+    let mut skipped = Vec::new();
+    let mut seen = HashSet::new();
+
+    for note in &collection.notes{
+        let model = collection.models.get(&note.model_id);
+        let Some((model, mapping)) = model.and_then(|model| Some((model, import.mapping.get(&model.name)?))) else{
+            skipped.push(json!({ "note_id": note.id, "reason": "unmapped" }));
+            continue;
+        };
+
+        let field = |kms_field: &str|{
+            mapping.fields.get(kms_field)
+                .and_then(|anki_field| model.fields.iter().position(|name| name == anki_field))
+                .and_then(|position| note.fields.get(position))
+                .map(|field| field.as_str())
+                .unwrap_or_default()
+        };
+
+        let vocab = mapping.kind == "vocab";
+        let item = field_symbol(field(if vocab{ "phrase" } else{ "symbol" }));
+        let meaning = field_text(field("meaning"));
+        if item.is_empty() || meaning.is_empty(){
+            skipped.push(json!({ "note_id": note.id, "reason": "missing_field" }));
+            continue;
+        }
+
+        let existing = if vocab{ &existing_vocab } else{ &existing_kanji };
+        if existing.contains(&item) || !seen.insert((vocab, item.to_owned())){
+            if vocab{ duplicate_vocab.push(item) } else{ duplicate_kanji.push(item) }
+            continue;
+        }
+
+        // A note's group is the deck of its first card
+        let cards = note_cards.remove(&note.id).unwrap_or_default();
+        let group_title = cards.first()
+            .filter(|card| card.deck_id != DEFAULT_DECK_ID)
+            .and_then(|card| collection.decks.get(&card.deck_id))
+            .cloned();
+        if let Some(group_title) = &group_title{
+            if !existing_groups.contains(&(group_title.to_owned(), vocab)){
+                new_groups.insert((group_title.to_owned(), vocab));
+            }
+        }
+
+        let description = Some(field_text(field("description"))).filter(|description| !description.is_empty());
+        if vocab{
+            new_vocab.push((NewVocab{
+                phrase: item,
+                meaning,
+                reading: field_list(field("reading")),
+                description,
+                kanji_refs: Vec::new(),
+                user_id: user.id,
+                group_id: None,
+                exception: false,
+                parts_of_speech: Vec::new(),
+            }, group_title, cards, mapping));
+        }
+        else{
+            new_kanji.push((NewKanji{
+                symbol: item,
+                meaning,
+                onyomi: field_list(field("onyomi")),
+                kunyomi: field_list(field("kunyomi")),
+                description,
+                vocab_refs: Vec::new(),
+                user_id: user.id,
+                group_id: None,
+                components: Vec::new(),
+                stroke_count: None,
+                jlpt_level: None,
+                grade: None,
+                frequency_rank: None,
+            }, group_title, cards, mapping));
+        }
+    }
+
+    let history_answers = |cards: &[&AnkiCard], mapping: &NoteMapping|{
+        let card_map = mapped_cards(mapping).unwrap_or_default();
+        cards.iter()
+            .filter(|card| card_map.contains_key(&card.ord))
+            .map(|card| card_revlog.get(&card.id).map_or(0, |revlog| revlog.len()))
+            .sum::<usize>()
+    };
+    let mut reviews = if import.history{
+        new_kanji.iter().map(|(_, _, cards, mapping)| history_answers(cards, mapping))
+            .chain(new_vocab.iter().map(|(_, _, cards, mapping)| history_answers(cards, mapping)))
+            .sum::<usize>()
+    }
+    else{
+        0
+    };
+
+    let mut report = json!({
+        "dry_run": import.dry_run,
+        "kanji": {
+            "created": new_kanji.iter().map(|(user_kanji, ..)| &user_kanji.symbol).collect::<Vec<&String>>(),
+            "duplicates": duplicate_kanji,
+        },
+        "vocab": {
+            "created": new_vocab.iter().map(|(user_vocab, ..)| &user_vocab.phrase).collect::<Vec<&String>>(),
+            "duplicates": duplicate_vocab,
+        },
+        "groups": new_groups.iter()
+            .map(|(title, vocab)| json!({ "title": title, "vocab": vocab }))
+            .collect::<Vec<Value>>(),
+        "skipped": skipped,
+    });
+    if import.dry_run{
+        report["reviews"] = json!(reviews);
+        return Ok(report);
+    }
+
+    let user = users::table.find(user.id)
+        .first::<User>(connection)
+        .map_err(|_| "INVALID_USER")?;
+    let index = PresetIndex::load(connection, &user);
+
+    reviews = connection.transaction::<_, diesel::result::Error, _>(|connection|{
+        let mut reviews = 0;
+        // Kanji go in first so the vocab built from them link up as they're created
+        let items = new_kanji.into_iter()
+            .map(|(payload, group_title, cards, mapping)| (Some(payload), None, group_title, cards, mapping))
+            .chain(new_vocab.into_iter()
+                .map(|(payload, group_title, cards, mapping)| (None, Some(payload), group_title, cards, mapping)));
+
+        for (kanji_payload, vocab_payload, group_title, cards, mapping) in items{
+            let group_id = match &group_title{
+                Some(group_title) => Some(find_or_create_group(connection, &user, group_title, vocab_payload.is_some())?),
+                None => None,
+            };
+
+            let item_reviews = if let Some(mut payload) = kanji_payload{
+                payload.group_id = group_id;
+                let user_kanji = insert_kanji(connection, &user, payload)
+                    .map_err(|_| diesel::result::Error::RollbackTransaction)?;
+                Review::belonging_to(&user_kanji).load::<Review>(connection)?
+            }
+            else if let Some(mut payload) = vocab_payload{
+                payload.group_id = group_id;
+                let user_vocab = insert_vocab(connection, &user, payload)
+                    .map_err(|_| diesel::result::Error::RollbackTransaction)?;
+                Review::belonging_to(&user_vocab).load::<Review>(connection)?
+            }
+            else{
+                continue;
+            };
+
+            if !import.history{
+                continue;
+            }
+
+            let preset = index.preset(index.preset_id(group_id));
+            let card_map = mapped_cards(mapping).unwrap_or_default();
+            let mut first_reviews = Vec::new();
+            for card in &cards{
+                let (Some(kms_card), Some(revlog)) = (card_map.get(&card.ord), card_revlog.get(&card.id)) else{
+                    continue;
+                };
+                let Some(review) = item_reviews.iter().find(|review| review.card == kms_card.as_str()) else{
+                    continue;
+                };
+
+                let answers = carry_over_history(connection, &user, review, revlog, &preset, card.queue == -1)?;
+                if answers > 0{
+                    reviews += answers;
+                    first_reviews.extend(revlog.first().map(|entry| entry.reviewed_at));
+                }
+            }
+
+            // Items studied in Anki skip the lesson queue
+            if let (Some(first_review), Some(review)) = (first_reviews.iter().min(), item_reviews.first()){
+                let learned_at = DateTime::<Utc>::from_timestamp_millis(*first_review).unwrap_or_default();
+                match (review.kanji_id, review.vocab_id){
+                    (Some(kanji_id), _) => diesel::update(kanji::table.find(kanji_id))
+                        .set(kanji::learned_at.eq(learned_at))
+                        .execute(connection)?,
+                    (_, Some(vocab_id)) => diesel::update(vocab::table.find(vocab_id))
+                        .set(vocab::learned_at.eq(learned_at))
+                        .execute(connection)?,
+                    _ => 0,
+                };
+            }
+        }
+
+        Ok(reviews)
+    }).map_err(|_| "IMPORT_FAILED")?;
+
+    report["reviews"] = json!(reviews);
+    Ok(report)
+}
+
+pub fn import_anki(user: &User, payload: String)-> Eval<String>{
+    let connection = &mut establish_connection();
+
+    if users::table.find(user.id)
+        .first::<User>(connection).is_err(){
+        return Err("INVALID_USER")
+    }
+
+    if let Ok(payload) = serde_json::from_str::<AnkiImport>(&payload){
+        let file = BASE64.decode(payload.file.trim()).map_err(|_| "INVALID_FILE")?;
+        let collection = read_apkg(&file)?;
+        return Ok(import_collection(connection, user, collection, &payload)?.to_string());
+    }
+
+    Err("INVALID_FORMAT")
+}
+
+// Command line import, the mapping file holds the same JSON as an import request's mapping
+pub fn import_anki_file(user_username: &str, apkg_path: &str, mapping_path: &str, history: bool, dry_run: bool)-> Eval<Value>{
+    let connection = &mut establish_connection();
+
+    let user = users::table.filter(users::username.eq(user_username))
+        .first::<User>(connection)
+        .map_err(|_| "INVALID_USER")?;
+
+    let mapping = fs::read_to_string(mapping_path).ok()
+        .and_then(|mapping| serde_json::from_str::<HashMap<String, NoteMapping>>(&mapping).ok())
+        .ok_or("INVALID_MAPPING")?;
+    let file = fs::read(apkg_path).map_err(|_| "INVALID_FILE")?;
+
+    let collection = read_apkg(&file)?;
+    import_collection(connection, &user, collection, &AnkiImport{ file: String::new(), mapping, history, dry_run })
+}
+
+// Fixed timestamps and id bases so exporting the same items always produces the same package
+const EXPORT_TIMESTAMP: i64 = 1_600_000_000;
+const KANJI_MODEL_ID: i64 = 1_600_000_000_001;
+const VOCAB_MODEL_ID: i64 = 1_600_000_000_002;
+const DECK_ID_BASE: i64 = 1_600_000_000_100;
+const NOTE_ID_BASE: i64 = 1_600_000_001_000;
+
+const APKG_SCHEMA: &str = "
+    CREATE TABLE col (id INTEGER PRIMARY KEY, crt INTEGER NOT NULL, mod INTEGER NOT NULL, scm INTEGER NOT NULL,
+        ver INTEGER NOT NULL, dty INTEGER NOT NULL, usn INTEGER NOT NULL, ls INTEGER NOT NULL, conf TEXT NOT NULL,
+        models TEXT NOT NULL, decks TEXT NOT NULL, dconf TEXT NOT NULL, tags TEXT NOT NULL);
+    CREATE TABLE notes (id INTEGER PRIMARY KEY, guid TEXT NOT NULL, mid INTEGER NOT NULL, mod INTEGER NOT NULL,
+        usn INTEGER NOT NULL, tags TEXT NOT NULL, flds TEXT NOT NULL, sfld INTEGER NOT NULL, csum INTEGER NOT NULL,
+        flags INTEGER NOT NULL, data TEXT NOT NULL);
+    CREATE TABLE cards (id INTEGER PRIMARY KEY, nid INTEGER NOT NULL, did INTEGER NOT NULL, ord INTEGER NOT NULL,
+        mod INTEGER NOT NULL, usn INTEGER NOT NULL, type INTEGER NOT NULL, queue INTEGER NOT NULL, due INTEGER NOT NULL,
+        ivl INTEGER NOT NULL, factor INTEGER NOT NULL, reps INTEGER NOT NULL, lapses INTEGER NOT NULL, left INTEGER NOT NULL,
+        odue INTEGER NOT NULL, odid INTEGER NOT NULL, flags INTEGER NOT NULL, data TEXT NOT NULL);
+    CREATE TABLE revlog (id INTEGER PRIMARY KEY, cid INTEGER NOT NULL, usn INTEGER NOT NULL, ease INTEGER NOT NULL,
+        ivl INTEGER NOT NULL, lastIvl INTEGER NOT NULL, factor INTEGER NOT NULL, time INTEGER NOT NULL, type INTEGER NOT NULL);
+    CREATE TABLE graves (usn INTEGER NOT NULL, oid INTEGER NOT NULL, type INTEGER NOT NULL);
+    CREATE INDEX ix_notes_usn ON notes (usn);
+    CREATE INDEX ix_cards_usn ON cards (usn);
+    CREATE INDEX ix_revlog_usn ON revlog (usn);
+    CREATE INDEX ix_cards_nid ON cards (nid);
+    CREATE INDEX ix_cards_sched ON cards (did, queue, due);
+    CREATE INDEX ix_revlog_cid ON revlog (cid);
+    CREATE INDEX ix_notes_csum ON notes (csum);";
+
+const CARD_CSS: &str = ".card { font-family: sans-serif; font-size: 24px; text-align: center; }\n.item { font-size: 64px; }";
+
+// One exported note, fields in the order of its note type
+struct ExportNote{
+    model_id: i64,
+    guid_key: String,
+    fields: Vec<String>,
+    deck: Option<String>,
+}
+
+fn escape_html(text: &str)-> String{
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('\n', "<br>")
+}
+
+fn join_field(values: &[Option<String>])-> String{
+    escape_html(&values.iter().flatten().map(|value| value.as_str()).collect::<Vec<&str>>().join("、"))
+}
+
+fn note_model(id: i64, name: &str, fields: &[&str], templates: &[(&str, &str, &str)])-> Value{
+    json!({
+        "id": id,
+        "name": name,
+        "type": 0,
+        "mod": EXPORT_TIMESTAMP,
+        "usn": -1,
+        "sortf": 0,
+        "did": DEFAULT_DECK_ID,
+        "tmpls": templates.iter().enumerate().map(|(ord, (name, qfmt, afmt))| json!({
+            "name": name,
+            "ord": ord,
+            "qfmt": qfmt,
+            "afmt": afmt,
+            "did": null,
+            "bqfmt": "",
+            "bafmt": "",
+        })).collect::<Vec<Value>>(),
+        "flds": fields.iter().enumerate().map(|(ord, name)| json!({
+            "name": name,
+            "ord": ord,
+            "sticky": false,
+            "rtl": false,
+            "font": "Arial",
+            "size": 20,
+            "media": [],
+        })).collect::<Vec<Value>>(),
+        "css": CARD_CSS,
+        "latexPre": "",
+        "latexPost": "",
+        "tags": [],
+        "vers": [],
+        "req": (0..templates.len()).map(|ord| json!([ord, "all", [ord]])).collect::<Vec<Value>>(),
+    })
+}
+
+fn export_models()-> Value{
+    json!({
+        KANJI_MODEL_ID.to_string(): note_model(KANJI_MODEL_ID, "kms Kanji",
+            &["Symbol", "Meaning", "Onyomi", "Kunyomi", "Description", "Vocab"],
+            &[("Meaning",
+                "<div class=\"item\">{{Symbol}}</div>",
+                "{{FrontSide}}<hr id=\"answer\">{{Meaning}}{{#Onyomi}}<br>音: {{Onyomi}}{{/Onyomi}}{{#Kunyomi}}<br>訓: {{Kunyomi}}{{/Kunyomi}}{{#Description}}<br><br>{{Description}}{{/Description}}{{#Vocab}}<br><br>{{Vocab}}{{/Vocab}}")]),
+        VOCAB_MODEL_ID.to_string(): note_model(VOCAB_MODEL_ID, "kms Vocab",
+            &["Phrase", "Meaning", "Reading", "Description", "Kanji"],
+            &[("Meaning",
+                "<div class=\"item\">{{Phrase}}</div>",
+                "{{FrontSide}}<hr id=\"answer\">{{Reading}}<br>{{Meaning}}{{#Description}}<br><br>{{Description}}{{/Description}}{{#Kanji}}<br><br>{{Kanji}}{{/Kanji}}"),
+            ("Recall",
+                "{{Meaning}}",
+                "{{FrontSide}}<hr id=\"answer\"><div class=\"item\">{{Phrase}}</div>{{Reading}}")]),
+    })
+}
+
+fn export_deck(id: i64, name: &str)-> Value{
+    json!({
+        "id": id,
+        "name": name,
+        "desc": "",
+        "mod": EXPORT_TIMESTAMP,
+        "usn": -1,
+        "collapsed": false,
+        "browserCollapsed": false,
+        "newToday": [0, 0],
+        "revToday": [0, 0],
+        "lrnToday": [0, 0],
+        "timeToday": [0, 0],
+        "dyn": 0,
+        "conf": 1,
+        "extendNew": 10,
+        "extendRev": 50,
+    })
+}
+
+fn deck_config()-> Value{
+    json!({ "1": {
+        "id": 1,
+        "name": "Default",
+        "mod": 0,
+        "usn": 0,
+        "maxTaken": 60,
+        "autoplay": true,
+        "timer": 0,
+        "replayq": true,
+        "dyn": false,
+        "new": { "bury": true, "delays": [1, 10], "initialFactor": 2500, "ints": [1, 4, 7], "order": 1, "perDay": 20, "separate": true },
+        "lapse": { "delays": [10], "leechAction": 0, "leechFails": 8, "minInt": 1, "mult": 0 },
+        "rev": { "bury": true, "ease4": 1.3, "fuzz": 0.05, "ivlFct": 1, "maxIvl": 36500, "minSpace": 1, "perDay": 100 },
+    }})
+}
+
+fn write_collection(path: &std::path::Path, notes: &[ExportNote])-> rusqlite::Result<()>{
+    let sqlite = rusqlite::Connection::open(path)?;
+    sqlite.execute_batch(APKG_SCHEMA)?;
+
+    let deck_names = notes.iter()
+        .filter_map(|note| note.deck.clone())
+        .collect::<BTreeSet<String>>();
+    let deck_ids = deck_names.iter()
+        .enumerate()
+        .map(|(idx, name)| (name.to_owned(), DECK_ID_BASE + idx as i64 + 1))
+        .collect::<HashMap<String, i64>>();
+
+    let mut decks = serde_json::Map::new();
+    decks.insert(DEFAULT_DECK_ID.to_string(), export_deck(DEFAULT_DECK_ID, "Default"));
+    for name in &deck_names{
+        decks.insert(deck_ids[name].to_string(), export_deck(deck_ids[name], name));
+    }
+
+    let conf = json!({
+        "activeDecks": [DEFAULT_DECK_ID],
+        "curDeck": DEFAULT_DECK_ID,
+        "newSpread": 0,
+        "collapseTime": 1200,
+        "timeLim": 0,
+        "estTimes": true,
+        "dueCounts": true,
+        "curModel": null,
+        "nextPos": notes.len() + 1,
+        "sortType": "noteFld",
+        "sortBackwards": false,
+        "addToCur": true,
+    });
+
+    let transaction = sqlite.unchecked_transaction()?;
+    transaction.execute("INSERT INTO col VALUES (1, ?1, ?2, ?3, 11, 0, 0, 0, ?4, ?5, ?6, ?7, '{}')", rusqlite::params![
+        EXPORT_TIMESTAMP,
+        EXPORT_TIMESTAMP * 1000,
+        EXPORT_TIMESTAMP * 1000,
+        conf.to_string(),
+        export_models().to_string(),
+        Value::Object(decks).to_string(),
+        deck_config().to_string(),
+    ])?;
+
+    for (position, note) in notes.iter().enumerate(){
+        let note_id = NOTE_ID_BASE + position as i64;
+        let digest = sha1_smol::Sha1::from(&note.guid_key).digest().to_string();
+        let sort_field = field_text(&note.fields[0]);
+        let checksum = i64::from_str_radix(&sha1_smol::Sha1::from(&sort_field).digest().to_string()[..8], 16).unwrap_or(0);
+
+        transaction.execute("INSERT INTO notes VALUES (?1, ?2, ?3, ?4, -1, '', ?5, ?6, ?7, 0, '')", rusqlite::params![
+            note_id,
+            &digest[..16],
+            note.model_id,
+            EXPORT_TIMESTAMP,
+            note.fields.join("\x1f"),
+            sort_field,
+            checksum,
+        ])?;
+
+        let deck_id = note.deck.as_ref().map_or(DEFAULT_DECK_ID, |deck| deck_ids[deck]);
+        let templates = if note.model_id == VOCAB_MODEL_ID{ 2 } else{ 1 };
+        for ord in 0..templates{
+            transaction.execute("INSERT INTO cards VALUES (?1, ?2, ?3, ?4, ?5, -1, 0, 0, ?6, 0, 0, 0, 0, 0, 0, 0, 0, '')", rusqlite::params![
+                note_id * 10 + ord,
+                note_id,
+                deck_id,
+                ord,
+                EXPORT_TIMESTAMP,
+                position as i64 + 1,
+            ])?;
+        }
+    }
+
+    transaction.commit()
+}
+
+// Builds an .apkg of the given kanji and vocab with each group as a deck, the same items always
+// give byte for byte the same package
+pub fn write_apkg(user_kanji: &[Kanji], user_vocab: &[Vocab], group_titles: &HashMap<i32, String>)-> Eval<Vec<u8>>{
+    let deck = |group_id: Option<i32>| group_id.and_then(|group_id| group_titles.get(&group_id).cloned());
+
+    let mut notes = user_kanji.iter()
+        .map(|user_kanji| ExportNote{
+            model_id: KANJI_MODEL_ID,
+            guid_key: format!("kanji:{}", user_kanji.symbol),
+            fields: vec![
+                escape_html(&user_kanji.symbol),
+                escape_html(&user_kanji.meaning),
+                join_field(&user_kanji.onyomi),
+                join_field(&user_kanji.kunyomi),
+                escape_html(user_kanji.description.as_deref().unwrap_or_default()),
+                join_field(&user_kanji.vocab_refs),
+            ],
+            deck: deck(user_kanji.group_id),
+        })
+        .chain(user_vocab.iter().map(|user_vocab| ExportNote{
+            model_id: VOCAB_MODEL_ID,
+            guid_key: format!("vocab:{}", user_vocab.phrase),
+            fields: vec![
+                escape_html(&user_vocab.phrase),
+                escape_html(&user_vocab.meaning),
+                join_field(&user_vocab.reading),
+                escape_html(user_vocab.description.as_deref().unwrap_or_default()),
+                join_field(&user_vocab.kanji_refs),
+            ],
+            deck: deck(user_vocab.group_id),
+        }))
+        .collect::<Vec<ExportNote>>();
+    notes.sort_by(|a, b| (a.model_id, &a.guid_key).cmp(&(b.model_id, &b.guid_key)));
+
+    let path = std::env::temp_dir().join(format!("kms-{}-{}.anki2",
+        std::process::id(),
+        Utc::now().timestamp_nanos_opt().unwrap_or_default()));
+    let collection = write_collection(&path, &notes).ok()
+        .and_then(|_| fs::read(&path).ok());
+    fs::remove_file(&path).ok();
+    let collection = collection.ok_or("EXPORT_FAILED")?;
+
+    let mut archive = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = zip::write::FileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated)
+        .last_modified_time(zip::DateTime::default());
+    for (name, contents) in [("collection.anki2", collection.as_slice()), ("media", b"{}".as_slice())]{
+        archive.start_file(name, options).map_err(|_| "EXPORT_FAILED")?;
+        archive.write_all(contents).map_err(|_| "EXPORT_FAILED")?;
+    }
+
+    archive.finish()
+        .map(|archive| archive.into_inner())
+        .map_err(|_| "EXPORT_FAILED")
+}
+
+// The user's kanji and vocab, only those in groups with the given titles if any are given
+fn export_items(connection: &mut PgConnection, user: &User, group_titles: Option<&[String]>)-> Eval<Vec<u8>>{
+    let groups = Group::belonging_to(user)
+        .load::<Group>(connection)
+        .unwrap_or_default();
+
+    let mut kanji_query = Kanji::belonging_to(user).into_boxed();
+    let mut vocab_query = Vocab::belonging_to(user).into_boxed();
+    if let Some(group_titles) = group_titles{
+        if !group_titles.iter().all(|title| groups.iter().any(|group| group.title == *title)){
+            return Err("INVALID_GROUP");
+        }
+
+        let group_ids = groups.iter()
+            .filter(|group| group_titles.contains(&group.title))
+            .map(|group| group.id)
+            .collect::<Vec<i32>>();
+        kanji_query = kanji_query.filter(kanji::group_id.eq_any(group_ids.clone()));
+        vocab_query = vocab_query.filter(vocab::group_id.eq_any(group_ids));
+    }
+
+    let user_kanji = kanji_query.load::<Kanji>(connection).unwrap_or_default();
+    let user_vocab = vocab_query.load::<Vocab>(connection).unwrap_or_default();
+    let group_titles = groups.into_iter()
+        .map(|group| (group.id, group.title))
+        .collect::<HashMap<i32, String>>();
+
+    write_apkg(&user_kanji, &user_vocab, &group_titles)
+}
+
+pub fn export_anki(user: &User, payload: String)-> Eval<String>{
+    let connection = &mut establish_connection();
+
+    if users::table.find(user.id)
+        .first::<User>(connection).is_err(){
+        return Err("INVALID_USER")
+    }
+
+    let payload = if payload.is_empty(){
+        Value::Null
+    }
+    else if let Ok(payload) = serde_json::from_str::<Value>(&payload){
+        payload
+    }
+    else{
+        return Err("INVALID_FORMAT");
+    };
+
+    let group_titles = match &payload["group_titles"]{
+        Value::Null => None,
+        group_titles => Some(serde_json::from_value::<Vec<String>>(group_titles.clone())
+            .map_err(|_| "INVALID_FORMAT")?),
+    };
+
+    let file = export_items(connection, user, group_titles.as_deref())?;
+    Ok(json!({ "file": BASE64.encode(file) }).to_string())
+}
+
+// Command line export, returns the size of the written package
+pub fn export_anki_file(user_username: &str, apkg_path: &str, group_titles: &[String])-> Eval<usize>{
+    let connection = &mut establish_connection();
+
+    let user = users::table.filter(users::username.eq(user_username))
+        .first::<User>(connection)
+        .map_err(|_| "INVALID_USER")?;
+
+    let group_titles = Some(group_titles).filter(|group_titles| !group_titles.is_empty());
+    let file = export_items(connection, &user, group_titles)?;
+    fs::write(apkg_path, &file).map_err(|_| "EXPORT_FAILED")?;
+
+    Ok(file.len())
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn test_kanji(id: i32, symbol: &str, meaning: &str, group_id: Option<i32>)-> Kanji{
+        Kanji{
+            id,
+            symbol: symbol.to_owned(),
+            meaning: meaning.to_owned(),
+            onyomi: vec![Some(String::from("スイ"))],
+            kunyomi: vec![Some(String::from("みず"))],
+            description: Some(String::from("<drop>\nof water")),
+            vocab_refs: vec![Some(String::from("水曜日"))],
+            user_id: 1,
+            group_id,
+            components: Vec::new(),
+            stroke_count: Some(4),
+            jlpt_level: Some(5),
+            grade: Some(1),
+            frequency_rank: Some(223),
+            learned_at: None,
+        }
+    }
+
+    fn test_vocab(id: i32, phrase: &str, meaning: &str, group_id: Option<i32>)-> Vocab{
+        Vocab{
+            id,
+            phrase: phrase.to_owned(),
+            meaning: meaning.to_owned(),
+            reading: vec![Some(String::from("すいようび"))],
+            description: None,
+            kanji_refs: vec![Some(String::from("水"))],
+            user_id: 1,
+            group_id,
+            exception: false,
+            learned_at: None,
+            parts_of_speech: Vec::new(),
+        }
+    }
+
+    // Collection rows of a package in a stable text form, one row per line
+    fn dump_apkg(apkg: &[u8])-> String{
+        let mut archive = zip::ZipArchive::new(Cursor::new(apkg)).unwrap();
+        let mut collection = Vec::new();
+        archive.by_name("collection.anki2").unwrap().read_to_end(&mut collection).unwrap();
+
+        let path = std::env::temp_dir().join(format!("kms-test-{}.anki2", std::process::id()));
+        fs::write(&path, collection).unwrap();
+        let sqlite = rusqlite::Connection::open(&path).unwrap();
+
+        let mut dump = Vec::new();
+        for (table, query) in [
+            ("col", "SELECT crt, mod, scm, ver, conf, models, decks, dconf FROM col"),
+            ("notes", "SELECT id, guid, mid, mod, flds, sfld, csum FROM notes ORDER BY id"),
+            ("cards", "SELECT id, nid, did, ord, due FROM cards ORDER BY id"),
+        ]{
+            let mut statement = sqlite.prepare(query).unwrap();
+            let columns = statement.column_count();
+            let rows = statement.query_map([], |row|{
+                (0..columns).map(|column| row.get::<_, rusqlite::types::Value>(column).map(|value| match value{
+                    rusqlite::types::Value::Integer(value) => value.to_string(),
+                    rusqlite::types::Value::Text(value) => value.replace('\x1f', "|"),
+                    value => format!("{value:?}"),
+                })).collect::<rusqlite::Result<Vec<String>>>()
+            }).unwrap();
+
+            for row in rows{
+                dump.push(format!("{table}\t{}", row.unwrap().join("\t")));
+            }
+        }
+        drop(sqlite);
+        fs::remove_file(&path).ok();
+
+        dump.join("\n") + "\n"
+    }
+
+    #[test]
+    fn export_matches_golden(){
+        let group_titles = HashMap::from([(1, String::from("JLPT::N5")), (2, String::from("Days"))]);
+        let user_kanji = [test_kanji(2, "水", "water", Some(1)), test_kanji(1, "火", "fire & flame", None)];
+        let user_vocab = [test_vocab(1, "水曜日", "Wednesday", Some(2))];
+
+        let apkg = write_apkg(&user_kanji, &user_vocab, &group_titles).unwrap();
+        assert_eq!(apkg, write_apkg(&user_kanji, &user_vocab, &group_titles).unwrap());
+
+        // Set KMS_UPDATE_GOLDEN to rewrite the expected rows after an intended format change
+        let golden = concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/anki_export.golden");
+        if std::env::var_os("KMS_UPDATE_GOLDEN").is_some(){
+            fs::write(golden, dump_apkg(&apkg)).unwrap();
+        }
+        assert_eq!(dump_apkg(&apkg), fs::read_to_string(golden).unwrap());
+    }
+
+    #[test]
+    fn export_reads_back(){
+        let apkg = write_apkg(&[test_kanji(1, "水", "water", None)], &[test_vocab(1, "水曜日", "Wednesday", None)], &HashMap::new()).unwrap();
+        let collection = read_apkg(&apkg).unwrap();
+
+        assert_eq!(collection.notes.len(), 2);
+        assert_eq!(collection.cards.len(), 3);
+    }
+}
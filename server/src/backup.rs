@@ -0,0 +1,626 @@
+use std::collections::{HashMap, HashSet};
+use chrono::{DateTime, Utc};
+use diesel::{
+    pg::PgConnection,
+    prelude::*,
+};
+use lib::schema::*;
+use lib::models::*;
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+use crate::commands::{Eval, establish_connection, refresh_vocab_readings};
+use crate::review::{sync_kanji_cards, sync_vocab_cards};
+
+// Steps that bring an older backup forward, BACKUP_MIGRATIONS[n] turns a version n + 1 document
+// into a version n + 2 one. Changing the layout means bumping the version by adding a step here
+const BACKUP_MIGRATIONS: &[fn(Value)-> Value] = &[add_parts_of_speech];
+const BACKUP_VERSION: usize = BACKUP_MIGRATIONS.len() + 1;
+
+// Version 2 gave vocab the parts of speech filled in from JMdict
+fn add_parts_of_speech(mut backup: Value)-> Value{
+    if let Some(vocab) = backup["vocab"].as_array_mut(){
+        for user_vocab in vocab{
+            user_vocab["parts_of_speech"] = json!([]);
+        }
+    }
+
+    backup
+}
+
+#[derive(Serialize, Deserialize)]
+struct AccountSettings{
+    algorithm: String,
+    desired_retention: f64,
+    fsrs_weights: Option<Vec<Option<f64>>>,
+    disabled_cards: Vec<Option<String>>,
+    leech_threshold: i32,
+    leech_action: String,
+    lessons_per_day: i32,
+    default_preset_id: Option<i32>,
+}
+
+// Every row of one user, ids are the ones they had when exported and only tie rows together.
+// Undo snapshots of suspends, buries and resets aren't kept
+#[derive(Serialize, Deserialize)]
+struct AccountBackup{
+    version: usize,
+    exported_at: DateTime<Utc>,
+    settings: AccountSettings,
+    presets: Vec<SrsPreset>,
+    groups: Vec<Group>,
+    kanji: Vec<Kanji>,
+    vocab: Vec<Vocab>,
+    examples: Vec<Example>,
+    vocab_examples: Vec<VocabExample>,
+    similar_kanji: Vec<SimilarKanji>,
+    vocab_relations: Vec<VocabRelation>,
+    #[serde(default)]
+    reviews: Vec<Review>,
+    #[serde(default)]
+    review_sessions: Vec<ReviewSession>,
+    #[serde(default)]
+    review_logs: Vec<ReviewLog>,
+}
+
+// What happened to the backup's rows of one kind, kept rows already existed and were left alone
+#[derive(Serialize, Default)]
+struct MergeCount{
+    created: usize,
+    updated: usize,
+    kept: usize,
+}
+
+impl MergeCount{
+    fn count(&mut self, existed: bool, overwrite: bool){
+        match (existed, overwrite){
+            (false, _) => self.created += 1,
+            (true, true) => self.updated += 1,
+            (true, false) => self.kept += 1,
+        }
+    }
+}
+
+pub fn export_account(user: &User)-> Eval<String>{
+    let connection = &mut establish_connection();
+
+    let user = users::table.find(user.id)
+        .first::<User>(connection)
+        .map_err(|_| "INVALID_USER")?;
+
+    let user_kanji = Kanji::belonging_to(&user)
+        .order(kanji::id)
+        .load::<Kanji>(connection)
+        .unwrap_or_default();
+    let user_vocab = Vocab::belonging_to(&user)
+        .order(vocab::id)
+        .load::<Vocab>(connection)
+        .unwrap_or_default();
+    let kanji_ids = user_kanji.iter().map(|user_kanji| user_kanji.id).collect::<Vec<i32>>();
+    let vocab_ids = user_vocab.iter().map(|user_vocab| user_vocab.id).collect::<Vec<i32>>();
+
+    let backup = AccountBackup{
+        version: BACKUP_VERSION,
+        exported_at: Utc::now(),
+        settings: AccountSettings{
+            algorithm: user.algorithm.to_owned(),
+            desired_retention: user.desired_retention,
+            fsrs_weights: user.fsrs_weights.clone(),
+            disabled_cards: user.disabled_cards.clone(),
+            leech_threshold: user.leech_threshold,
+            leech_action: user.leech_action.to_owned(),
+            lessons_per_day: user.lessons_per_day,
+            default_preset_id: user.default_preset_id,
+        },
+        presets: SrsPreset::belonging_to(&user)
+            .order(srs_presets::id)
+            .load::<SrsPreset>(connection)
+            .unwrap_or_default(),
+        groups: Group::belonging_to(&user)
+            .order(groups::id)
+            .load::<Group>(connection)
+            .unwrap_or_default(),
+        examples: Example::belonging_to(&user)
+            .order(examples::id)
+            .load::<Example>(connection)
+            .unwrap_or_default(),
+        vocab_examples: vocab_examples::table.filter(vocab_examples::vocab_id.eq_any(&vocab_ids))
+            .order(vocab_examples::id)
+            .load::<VocabExample>(connection)
+            .unwrap_or_default(),
+        similar_kanji: similar_kanji::table.filter(similar_kanji::kanji_id.eq_any(&kanji_ids))
+            .order(similar_kanji::id)
+            .load::<SimilarKanji>(connection)
+            .unwrap_or_default(),
+        vocab_relations: vocab_relations::table.filter(vocab_relations::vocab_id.eq_any(&vocab_ids))
+            .order(vocab_relations::id)
+            .load::<VocabRelation>(connection)
+            .unwrap_or_default(),
+        reviews: Review::belonging_to(&user)
+            .order(reviews::id)
+            .load::<Review>(connection)
+            .unwrap_or_default(),
+        review_sessions: ReviewSession::belonging_to(&user)
+            .order(review_sessions::id)
+            .load::<ReviewSession>(connection)
+            .unwrap_or_default(),
+        review_logs: ReviewLog::belonging_to(&user)
+            .order(review_logs::id)
+            .load::<ReviewLog>(connection)
+            .unwrap_or_default(),
+        kanji: user_kanji,
+        vocab: user_vocab,
+    };
+
+    serde_json::to_string(&backup).map_err(|_| "INVALID_USER")
+}
+
+// Brings a backup of any earlier version up to the current layout
+fn migrate_backup(mut backup: Value)-> Eval<AccountBackup>{
+    let version = backup["version"].as_u64()
+        .and_then(|version| usize::try_from(version).ok())
+        .filter(|version| (1..=BACKUP_VERSION).contains(version))
+        .ok_or("UNSUPPORTED_VERSION")?;
+
+    for migration in &BACKUP_MIGRATIONS[version - 1..]{
+        backup = migration(backup);
+    }
+    backup["version"] = json!(BACKUP_VERSION);
+
+    serde_json::from_value::<AccountBackup>(backup).map_err(|_| "INVALID_BACKUP")
+}
+
+// Adds the kanji and vocab links create_kanji and create_vocab would have made between restored
+// items and the ones already in the account, existing links are never dropped
+fn relink_refs(connection: &mut PgConnection, user: &User)-> QueryResult<()>{
+    let mut user_kanji = Kanji::belonging_to(user).load::<Kanji>(connection)?;
+    let user_vocab = Vocab::belonging_to(user).load::<Vocab>(connection)?;
+    let mut kanji_changed = HashSet::new();
+
+    for mut user_vocab in user_vocab{
+        let mut vocab_changed = false;
+
+        for user_kanji in user_kanji.iter_mut().filter(|user_kanji| user_vocab.phrase.contains(&user_kanji.symbol)){
+            if !user_vocab.kanji_refs.contains(&Some(user_kanji.symbol.to_owned())){
+                user_vocab.kanji_refs.push(Some(user_kanji.symbol.to_owned()));
+                vocab_changed = true;
+            }
+            if !user_kanji.vocab_refs.contains(&Some(user_vocab.phrase.to_owned())){
+                user_kanji.vocab_refs.push(Some(user_vocab.phrase.to_owned()));
+                kanji_changed.insert(user_kanji.id);
+            }
+        }
+
+        if vocab_changed{
+            diesel::update(&user_vocab)
+                .set(vocab::kanji_refs.eq(&user_vocab.kanji_refs))
+                .execute(connection)?;
+        }
+    }
+
+    for user_kanji in user_kanji.iter().filter(|user_kanji| kanji_changed.contains(&user_kanji.id)){
+        diesel::update(user_kanji)
+            .set(kanji::vocab_refs.eq(&user_kanji.vocab_refs))
+            .execute(connection)?;
+    }
+
+    Ok(())
+}
+
+// Restores a backup into the account. Rows matching one already there by title, symbol, phrase or
+// sentence are left alone when merging, or take the backup's values and progress when overwriting.
+// An empty account takes the backup's settings either way
+fn restore_backup(connection: &mut PgConnection, user: &User, backup: &AccountBackup, overwrite: bool)-> QueryResult<Value>{
+    let empty = Kanji::belonging_to(user).count().get_result::<i64>(connection)? == 0
+        && Vocab::belonging_to(user).count().get_result::<i64>(connection)? == 0
+        && Group::belonging_to(user).count().get_result::<i64>(connection)? == 0;
+
+    let mut preset_ids = HashMap::new();
+    let mut preset_count = MergeCount::default();
+    for preset in &backup.presets{
+        let new_preset = NewSrsPreset{
+            title: preset.title.to_owned(),
+            new_per_day: preset.new_per_day,
+            max_interval: preset.max_interval,
+            learning_steps: preset.learning_steps.clone(),
+            ease_bonus: preset.ease_bonus,
+            desired_retention: preset.desired_retention,
+            user_id: user.id,
+        };
+
+        let existing = SrsPreset::belonging_to(user)
+            .filter(srs_presets::title.eq(&preset.title))
+            .select(srs_presets::id)
+            .first::<i32>(connection)
+            .optional()?;
+        preset_count.count(existing.is_some(), overwrite);
+
+        let preset_id = match existing{
+            Some(preset_id) =>{
+                if overwrite{
+                    diesel::update(srs_presets::table.find(preset_id))
+                        .set(&new_preset)
+                        .execute(connection)?;
+                }
+                preset_id
+            }
+            None => diesel::insert_into(srs_presets::table)
+                .values(&new_preset)
+                .returning(srs_presets::id)
+                .get_result::<i32>(connection)?,
+        };
+        preset_ids.insert(preset.id, preset_id);
+    }
+
+    if overwrite || empty{
+        let settings = &backup.settings;
+        diesel::update(users::table.find(user.id))
+            .set((
+                users::algorithm.eq(&settings.algorithm),
+                users::desired_retention.eq(settings.desired_retention),
+                users::fsrs_weights.eq(&settings.fsrs_weights),
+                users::disabled_cards.eq(&settings.disabled_cards),
+                users::leech_threshold.eq(settings.leech_threshold),
+                users::leech_action.eq(&settings.leech_action),
+                users::lessons_per_day.eq(settings.lessons_per_day),
+                users::default_preset_id.eq(settings.default_preset_id.and_then(|preset_id| preset_ids.get(&preset_id).copied())),
+            ))
+            .execute(connection)?;
+    }
+
+    let mut group_ids = HashMap::new();
+    let mut group_count = MergeCount::default();
+    for group in &backup.groups{
+        let preset_id = group.preset_id.and_then(|preset_id| preset_ids.get(&preset_id).copied());
+        let existing = Group::belonging_to(user)
+            .filter(groups::title.eq(&group.title))
+            .filter(groups::vocab.eq(group.vocab))
+            .select(groups::id)
+            .first::<i32>(connection)
+            .optional()?;
+        group_count.count(existing.is_some(), overwrite);
+
+        let group_id = match existing{
+            Some(group_id) =>{
+                if overwrite{
+                    diesel::update(groups::table.find(group_id))
+                        .set((groups::colour.eq(&group.colour), groups::preset_id.eq(preset_id)))
+                        .execute(connection)?;
+                }
+                group_id
+            }
+            None => diesel::insert_into(groups::table)
+                .values((
+                    &NewGroup{ title: group.title.to_owned(), colour: group.colour.clone(), vocab: group.vocab, user_id: user.id },
+                    groups::preset_id.eq(preset_id),
+                ))
+                .returning(groups::id)
+                .get_result::<i32>(connection)?,
+        };
+        group_ids.insert(group.id, group_id);
+    }
+    let group_id = |group_id: Option<i32>| group_id.and_then(|group_id| group_ids.get(&group_id).copied());
+
+    // Items whose progress comes from the backup, everything created plus everything overwritten
+    let mut kanji_ids = HashMap::new();
+    let mut restored_kanji = HashSet::new();
+    let mut kanji_count = MergeCount::default();
+    for backup_kanji in &backup.kanji{
+        let existing = Kanji::belonging_to(user)
+            .filter(kanji::symbol.eq(&backup_kanji.symbol))
+            .select(kanji::id)
+            .first::<i32>(connection)
+            .optional()?;
+        kanji_count.count(existing.is_some(), overwrite);
+
+        let fields = (
+            kanji::meaning.eq(&backup_kanji.meaning),
+            kanji::onyomi.eq(&backup_kanji.onyomi),
+            kanji::kunyomi.eq(&backup_kanji.kunyomi),
+            kanji::description.eq(&backup_kanji.description),
+            kanji::group_id.eq(group_id(backup_kanji.group_id)),
+            kanji::components.eq(&backup_kanji.components),
+            kanji::stroke_count.eq(backup_kanji.stroke_count),
+            kanji::jlpt_level.eq(backup_kanji.jlpt_level),
+            kanji::grade.eq(backup_kanji.grade),
+            kanji::frequency_rank.eq(backup_kanji.frequency_rank),
+            kanji::learned_at.eq(backup_kanji.learned_at),
+        );
+        let kanji_id = match existing{
+            Some(kanji_id) if !overwrite =>{
+                kanji_ids.insert(backup_kanji.id, kanji_id);
+                continue;
+            }
+            Some(kanji_id) =>{
+                diesel::update(kanji::table.find(kanji_id))
+                    .set(fields)
+                    .execute(connection)?;
+                diesel::delete(reviews::table.filter(reviews::kanji_id.eq(kanji_id)))
+                    .execute(connection)?;
+                kanji_id
+            }
+            None => diesel::insert_into(kanji::table)
+                .values((
+                    kanji::symbol.eq(&backup_kanji.symbol),
+                    kanji::vocab_refs.eq(&backup_kanji.vocab_refs),
+                    kanji::user_id.eq(user.id),
+                    fields,
+                ))
+                .returning(kanji::id)
+                .get_result::<i32>(connection)?,
+        };
+        kanji_ids.insert(backup_kanji.id, kanji_id);
+        restored_kanji.insert(backup_kanji.id);
+    }
+
+    let mut vocab_ids = HashMap::new();
+    let mut restored_vocab = HashSet::new();
+    let mut vocab_count = MergeCount::default();
+    for backup_vocab in &backup.vocab{
+        let existing = Vocab::belonging_to(user)
+            .filter(vocab::phrase.eq(&backup_vocab.phrase))
+            .select(vocab::id)
+            .first::<i32>(connection)
+            .optional()?;
+        vocab_count.count(existing.is_some(), overwrite);
+
+        let fields = (
+            vocab::meaning.eq(&backup_vocab.meaning),
+            vocab::reading.eq(&backup_vocab.reading),
+            vocab::description.eq(&backup_vocab.description),
+            vocab::group_id.eq(group_id(backup_vocab.group_id)),
+            vocab::exception.eq(backup_vocab.exception),
+            vocab::learned_at.eq(backup_vocab.learned_at),
+            vocab::parts_of_speech.eq(&backup_vocab.parts_of_speech),
+        );
+        let vocab_id = match existing{
+            Some(vocab_id) if !overwrite =>{
+                vocab_ids.insert(backup_vocab.id, vocab_id);
+                continue;
+            }
+            Some(vocab_id) =>{
+                diesel::update(vocab::table.find(vocab_id))
+                    .set(fields)
+                    .execute(connection)?;
+                diesel::delete(reviews::table.filter(reviews::vocab_id.eq(vocab_id)))
+                    .execute(connection)?;
+                vocab_id
+            }
+            None => diesel::insert_into(vocab::table)
+                .values((
+                    vocab::phrase.eq(&backup_vocab.phrase),
+                    vocab::kanji_refs.eq(&backup_vocab.kanji_refs),
+                    vocab::user_id.eq(user.id),
+                    fields,
+                ))
+                .returning(vocab::id)
+                .get_result::<i32>(connection)?,
+        };
+        vocab_ids.insert(backup_vocab.id, vocab_id);
+        restored_vocab.insert(backup_vocab.id);
+    }
+
+    let mut example_ids = HashMap::new();
+    let mut example_count = MergeCount::default();
+    for example in &backup.examples{
+        let existing = Example::belonging_to(user)
+            .filter(examples::sentence.eq(&example.sentence))
+            .select(examples::id)
+            .first::<i32>(connection)
+            .optional()?;
+        example_count.count(existing.is_some(), overwrite);
+
+        let example_id = match existing{
+            Some(example_id) =>{
+                if overwrite{
+                    diesel::update(examples::table.find(example_id))
+                        .set((examples::translation.eq(&example.translation), examples::reading.eq(&example.reading)))
+                        .execute(connection)?;
+                }
+                example_id
+            }
+            None => diesel::insert_into(examples::table)
+                .values(&NewExample{
+                    sentence: example.sentence.to_owned(),
+                    translation: example.translation.to_owned(),
+                    reading: example.reading.clone(),
+                    user_id: user.id,
+                })
+                .returning(examples::id)
+                .get_result::<i32>(connection)?,
+        };
+        example_ids.insert(example.id, example_id);
+    }
+
+    // Links between rows that made it across, skipping ones the account already has
+    let mut links = 0;
+    for link in &backup.vocab_examples{
+        if let (Some(vocab_id), Some(example_id)) = (vocab_ids.get(&link.vocab_id), example_ids.get(&link.example_id)){
+            if vocab_examples::table.filter(vocab_examples::vocab_id.eq(vocab_id))
+                .filter(vocab_examples::example_id.eq(example_id))
+                .first::<VocabExample>(connection)
+                .optional()?
+                .is_none(){
+                diesel::insert_into(vocab_examples::table)
+                    .values(&NewVocabExample{ vocab_id: *vocab_id, example_id: *example_id })
+                    .execute(connection)?;
+                links += 1;
+            }
+        }
+    }
+    for link in &backup.similar_kanji{
+        if let (Some(kanji_id), Some(similar_id)) = (kanji_ids.get(&link.kanji_id), kanji_ids.get(&link.similar_id)){
+            if similar_kanji::table.filter(similar_kanji::kanji_id.eq(kanji_id))
+                .filter(similar_kanji::similar_id.eq(similar_id))
+                .first::<SimilarKanji>(connection)
+                .optional()?
+                .is_none(){
+                diesel::insert_into(similar_kanji::table)
+                    .values(&NewSimilarKanji{ kanji_id: *kanji_id, similar_id: *similar_id })
+                    .execute(connection)?;
+                links += 1;
+            }
+        }
+    }
+    for link in &backup.vocab_relations{
+        if let (Some(vocab_id), Some(related_id)) = (vocab_ids.get(&link.vocab_id), vocab_ids.get(&link.related_id)){
+            if vocab_relations::table.filter(vocab_relations::vocab_id.eq(vocab_id))
+                .filter(vocab_relations::related_id.eq(related_id))
+                .filter(vocab_relations::kind.eq(&link.kind))
+                .first::<VocabRelation>(connection)
+                .optional()?
+                .is_none(){
+                diesel::insert_into(vocab_relations::table)
+                    .values(&NewVocabRelation{ kind: link.kind.to_owned(), vocab_id: *vocab_id, related_id: *related_id })
+                    .execute(connection)?;
+                links += 1;
+            }
+        }
+    }
+
+    let mut review_ids = HashMap::new();
+    for review in &backup.reviews{
+        let (kanji_id, vocab_id) = match (review.kanji_id, review.vocab_id){
+            (Some(kanji_id), _) if restored_kanji.contains(&kanji_id) => (kanji_ids.get(&kanji_id).copied(), None),
+            (_, Some(vocab_id)) if restored_vocab.contains(&vocab_id) => (None, vocab_ids.get(&vocab_id).copied()),
+            _ => continue,
+        };
+
+        let review_id = diesel::insert_into(reviews::table)
+            .values((
+                &NewReview{
+                    ease: review.ease,
+                    interval_days: review.interval_days,
+                    due: review.due,
+                    repetitions: review.repetitions,
+                    lapses: review.lapses,
+                    user_id: user.id,
+                    kanji_id,
+                    vocab_id,
+                    stability: review.stability,
+                    difficulty: review.difficulty,
+                    last_review: review.last_review,
+                    card: review.card.to_owned(),
+                },
+                reviews::leech.eq(review.leech),
+                reviews::suspended.eq(review.suspended),
+                reviews::buried_until.eq(review.buried_until),
+            ))
+            .returning(reviews::id)
+            .get_result::<i32>(connection)?;
+        review_ids.insert(review.id, review_id);
+    }
+
+    // Only sessions some restored answer was given in, reusing ones an earlier restore brought back
+    let used_sessions = backup.review_logs.iter()
+        .filter(|log| review_ids.contains_key(&log.review_id))
+        .filter_map(|log| log.session_id)
+        .collect::<HashSet<i32>>();
+    let mut session_ids = HashMap::new();
+    for session in backup.review_sessions.iter().filter(|session| used_sessions.contains(&session.id)){
+        let existing = ReviewSession::belonging_to(user)
+            .filter(review_sessions::started_at.eq(session.started_at))
+            .select(review_sessions::id)
+            .first::<i32>(connection)
+            .optional()?;
+        let session_id = match existing{
+            Some(session_id) => session_id,
+            None => diesel::insert_into(review_sessions::table)
+                .values(&NewReviewSession{
+                    started_at: session.started_at,
+                    group_title: session.group_title.clone(),
+                    user_id: user.id,
+                })
+                .returning(review_sessions::id)
+                .get_result::<i32>(connection)?,
+        };
+        session_ids.insert(session.id, session_id);
+    }
+
+    let mut review_logs = 0;
+    for log in &backup.review_logs{
+        let Some(review_id) = review_ids.get(&log.review_id) else{
+            continue;
+        };
+
+        diesel::insert_into(review_logs::table)
+            .values((
+                &NewReviewLog{
+                    grade: log.grade.to_owned(),
+                    reviewed_at: log.reviewed_at,
+                    elapsed_days: log.elapsed_days,
+                    user_id: user.id,
+                    review_id: *review_id,
+                    card: log.card.to_owned(),
+                    response_ms: log.response_ms,
+                    prior_ease: log.prior_ease,
+                    prior_interval_days: log.prior_interval_days,
+                    prior_due: log.prior_due,
+                    prior_repetitions: log.prior_repetitions,
+                    prior_lapses: log.prior_lapses,
+                    prior_stability: log.prior_stability,
+                    prior_difficulty: log.prior_difficulty,
+                    prior_last_review: log.prior_last_review,
+                    session_id: log.session_id.and_then(|session_id| session_ids.get(&session_id).copied()),
+                    prior_leech: log.prior_leech,
+                    prior_suspended: log.prior_suspended,
+                },
+                review_logs::undone.eq(log.undone),
+                review_logs::suspended_reviews.eq(log.suspended_reviews.iter()
+                    .flatten()
+                    .filter_map(|suspended_id| review_ids.get(suspended_id).copied().map(Some))
+                    .collect::<Vec<Option<i32>>>()),
+            ))
+            .execute(connection)?;
+        review_logs += 1;
+    }
+
+    // Restored items get whatever cards they're missing and their vocab reading breakdowns back
+    relink_refs(connection, user)?;
+    let restored_kanji_ids = restored_kanji.iter().filter_map(|kanji_id| kanji_ids.get(kanji_id)).collect::<Vec<&i32>>();
+    for user_kanji in kanji::table.filter(kanji::id.eq_any(restored_kanji_ids)).load::<Kanji>(connection)?{
+        sync_kanji_cards(connection, &user_kanji);
+    }
+    let restored_vocab_ids = restored_vocab.iter().filter_map(|vocab_id| vocab_ids.get(vocab_id)).collect::<Vec<&i32>>();
+    for user_vocab in vocab::table.filter(vocab::id.eq_any(restored_vocab_ids)).load::<Vocab>(connection)?{
+        refresh_vocab_readings(connection, user, &user_vocab);
+        sync_vocab_cards(connection, &user_vocab);
+    }
+
+    Ok(json!({
+        "presets": preset_count,
+        "groups": group_count,
+        "kanji": kanji_count,
+        "vocab": vocab_count,
+        "examples": example_count,
+        "links": links,
+        "reviews": review_ids.len(),
+        "review_logs": review_logs,
+    }))
+}
+
+pub fn import_account(user: &User, payload: String)-> Eval<String>{
+    let connection = &mut establish_connection();
+
+    let user = users::table.find(user.id)
+        .first::<User>(connection)
+        .map_err(|_| "INVALID_USER")?;
+
+    if let Ok(payload) = serde_json::from_str::<Value>(&payload){
+        let overwrite = match payload["mode"].as_str(){
+            None | Some("merge") => false,
+            Some("overwrite") => true,
+            Some(_) => return Err("INVALID_MODE"),
+        };
+        let version = payload["backup"]["version"].as_u64();
+        let backup = migrate_backup(payload["backup"].clone())?;
+
+        let mut report = connection.transaction::<_, diesel::result::Error, _>(|connection|{
+            restore_backup(connection, &user, &backup, overwrite)
+        }).map_err(|_| "IMPORT_FAILED")?;
+        report["version"] = json!(version);
+
+        return Ok(report.to_string());
+    }
+
+    Err("INVALID_FORMAT")
+}
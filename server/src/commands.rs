@@ -14,6 +14,11 @@ use regex::Regex;
 use tokio::{net::TcpStream, io::{AsyncReadExt, AsyncWriteExt}};
 use std::io::{Error as IoError, ErrorKind as IoErrorKind};
 use std::error::Error;
+use std::collections::{HashMap, HashSet};
+use lib::furigana::{align, is_exception, KanjiReadings, RubySegment};
+use lib::srs::Preset;
+use crate::dictionary::{autofill_kanji, autofill_vocab, find_components};
+use crate::review::{migrate_reviews, sync_kanji_cards, sync_vocab_cards, user_scheduler};
 
 pub type Eval<T> = Result<T, &'static str>;
 
@@ -101,6 +106,26 @@ pub fn validate_key(payload: String)-> Eval<User>{
     Err("INVALID_FORMAT")
 }
 
+// Optional integer field of a request payload, ill-formed if present with another type
+fn payload_i32(payload: &Value, field: &str)-> Eval<Option<i32>>{
+    match &payload[field]{
+        Value::Null => Ok(None),
+        value => value.as_i64()
+            .and_then(|value| i32::try_from(value).ok())
+            .map(Some)
+            .ok_or("INVALID_FORMAT"),
+    }
+}
+
+// Stroke count, JLPT level, grade and frequency rank, in that order
+fn valid_metadata(metadata: &[Option<i32>; 4])-> bool{
+    let ranges = [1..=84, 1..=5, 1..=10, 1..=i32::MAX];
+
+    metadata.iter()
+        .zip(ranges)
+        .all(|(value, range)| value.iter().all(|value| range.contains(value)))
+}
+
 pub fn create_kanji(user: &User, payload: String)-> Eval<()>{
     let connection = &mut establish_connection();
 
@@ -109,42 +134,735 @@ pub fn create_kanji(user: &User, payload: String)-> Eval<()>{
         return Err("INVALID_USER")
     }
 
-    if let Ok(mut payload) = serde_json::from_str::<NewKanji>(&payload){
-        if kanji::table.filter(kanji::symbol.eq(&payload.symbol))
-            .filter(kanji::user_id.eq(user.id))
-            .first::<Kanji>(connection).is_err(){
-            payload.user_id = user.id;
+    if let Ok(mut payload) = serde_json::from_str::<Value>(&payload){
+        // With autofill the meaning and readings may be left out and come from KANJIDIC2 instead
+        let autofill = payload["autofill"].as_bool().unwrap_or(false);
+        if autofill{
+            for (field, empty) in [("meaning", json!("")), ("onyomi", json!([])), ("kunyomi", json!([]))]{
+                if payload[field].is_null(){
+                    payload[field] = empty;
+                }
+            }
+        }
+
+        if let Ok(mut new_kanji) = serde_json::from_value::<NewKanji>(payload){
+            if autofill{
+                autofill_kanji(connection, &mut new_kanji);
+                if new_kanji.meaning.trim().is_empty(){
+                    return Err("UNKNOWN_KANJI");
+                }
+            }
+
+            return insert_kanji(connection, user, new_kanji).map(|_| ());
+        }
+    }
+
+    Err("INVALID_FORMAT")
+}
+
+// Adds a kanji to the user's collection, filling in its metadata and linking the vocab that use it
+pub fn insert_kanji(connection: &mut PgConnection, user: &User, mut payload: NewKanji)-> Eval<Kanji>{
+    if kanji::table.filter(kanji::symbol.eq(&payload.symbol))
+        .filter(kanji::user_id.eq(user.id))
+        .first::<Kanji>(connection).is_ok(){
+        return Err("KANJI_EXISTS");
+    }
+
+    payload.user_id = user.id;
+    payload.components = find_components(connection, &payload.symbol);
+
+    // KANJIDIC2's jlpt is the old four level one, so the N level only ever comes from the user
+    if let Ok(entry) = kanjidic::table.filter(kanjidic::symbol.eq(&payload.symbol))
+        .first::<KanjidicEntry>(connection){
+        payload.stroke_count = payload.stroke_count.or(Some(entry.stroke_count));
+        payload.grade = payload.grade.or(entry.grade);
+        payload.frequency_rank = payload.frequency_rank.or(entry.frequency_rank);
+    }
+
+    if !valid_metadata(&[payload.stroke_count, payload.jlpt_level, payload.grade, payload.frequency_rank]){
+        return Err("INVALID_METADATA");
+    }
+
+    let mut linked_vocab = Vec::new();
+    for mut vocab in Vocab::belonging_to(&user)
+        .load::<Vocab>(connection)
+        .unwrap(){
+        if vocab.phrase.contains(&payload.symbol){
+            vocab.kanji_refs.push(Some(payload.symbol.to_owned()));
+
+            diesel::update(&vocab)
+                .set(vocab::kanji_refs.eq(&vocab.kanji_refs))
+                .execute(connection)
+                .ok();
+
+            payload.vocab_refs.push(Some(vocab.phrase.to_owned()));
+            linked_vocab.push(vocab);
+        }
+    }
+
+    let user_kanji = diesel::insert_into(kanji::table)
+        .values(&payload)
+        .get_result::<Kanji>(connection)
+        .map_err(|_| "INVALID_FORMAT")?;
+    sync_kanji_cards(connection, &user_kanji);
+
+    for vocab in linked_vocab{
+        refresh_vocab_readings(connection, user, &vocab);
+    }
+
+    Ok(user_kanji)
+}
+
+// Kanji as sent to the client, with the symbols of its look-alikes
+fn kanji_response(connection: &mut PgConnection, user_kanji: Kanji)-> Value{
+    let similar_ids = SimilarKanji::belonging_to(&user_kanji)
+        .select(similar_kanji::similar_id)
+        .load::<i32>(connection)
+        .unwrap();
+
+    let similar = kanji::table.filter(kanji::id.eq_any(similar_ids))
+        .select(kanji::symbol)
+        .order(kanji::symbol)
+        .load::<String>(connection)
+        .unwrap();
+
+    let mut response = json!(user_kanji);
+    response["similar"] = json!(similar);
+    response
+}
+
+pub fn get_kanji(user: &User, payload: String)-> Eval<String>{
+    let connection = &mut establish_connection();
+
+    if users::table.find(user.id)
+        .first::<User>(connection).is_err(){
+        return Err("INVALID_USER")
+    }
+
+    let payload = if payload.is_empty(){
+        Value::Null
+    }
+    else if let Ok(payload) = serde_json::from_str::<Value>(&payload){
+        payload
+    }
+    else{
+        return Err("INVALID_FORMAT");
+    };
+
+    let mut query = kanji::table.filter(kanji::user_id.eq(user.id))
+        .into_boxed();
+
+    if let Some(kanji_symbol) = payload["kanji_symbol"].as_str(){
+        query = query.filter(kanji::symbol.eq(kanji_symbol));
+    }
+    if let Some(jlpt_level) = payload_i32(&payload, "jlpt_level")?{
+        query = query.filter(kanji::jlpt_level.eq(jlpt_level));
+    }
+    if let Some(grade) = payload_i32(&payload, "grade")?{
+        query = query.filter(kanji::grade.eq(grade));
+    }
+    if let Some(min_stroke_count) = payload_i32(&payload, "min_stroke_count")?{
+        query = query.filter(kanji::stroke_count.ge(min_stroke_count));
+    }
+    if let Some(max_stroke_count) = payload_i32(&payload, "max_stroke_count")?{
+        query = query.filter(kanji::stroke_count.le(max_stroke_count));
+    }
+    if let Some(max_frequency_rank) = payload_i32(&payload, "max_frequency_rank")?{
+        query = query.filter(kanji::frequency_rank.le(max_frequency_rank));
+    }
+
+    let descending = payload["descending"].as_bool().unwrap_or(false);
+    query = match (payload["sort_by"].as_str().unwrap_or("id"), descending){
+        ("id", false) => query.order_by(kanji::id.asc()),
+        ("id", true) => query.order_by(kanji::id.desc()),
+        ("symbol", false) => query.order_by(kanji::symbol.asc()),
+        ("symbol", true) => query.order_by(kanji::symbol.desc()),
+        ("stroke_count", false) => query.order_by(kanji::stroke_count.asc().nulls_last()),
+        ("stroke_count", true) => query.order_by(kanji::stroke_count.desc().nulls_last()),
+        ("jlpt_level", false) => query.order_by(kanji::jlpt_level.asc().nulls_last()),
+        ("jlpt_level", true) => query.order_by(kanji::jlpt_level.desc().nulls_last()),
+        ("grade", false) => query.order_by(kanji::grade.asc().nulls_last()),
+        ("grade", true) => query.order_by(kanji::grade.desc().nulls_last()),
+        ("frequency_rank", false) => query.order_by(kanji::frequency_rank.asc().nulls_last()),
+        ("frequency_rank", true) => query.order_by(kanji::frequency_rank.desc().nulls_last()),
+        _ => return Err("INVALID_FORMAT"),
+    };
+
+    let user_kanji = query.then_order_by(kanji::id)
+        .load::<Kanji>(connection)
+        .unwrap()
+        .into_iter()
+        .map(|user_kanji| kanji_response(connection, user_kanji))
+        .collect::<Vec<Value>>();
+
+    Ok(json!({ "kanji": user_kanji }).to_string())
+}
+
+pub fn edit_kanji(user: &User, payload: String)-> Eval<()>{
+    let connection = &mut establish_connection();
+
+    if users::table.find(user.id)
+        .first::<User>(connection).is_err(){
+        return Err("INVALID_USER")
+    }
+
+    if let Ok(payload) = serde_json::from_str::<Value>(&payload){
+        if let Some(kanji_symbol) = payload["kanji_symbol"].as_str(){
+            if let Ok(user_kanji) = kanji::table.filter(kanji::symbol.eq(kanji_symbol))
+                .filter(kanji::user_id.eq(user.id))
+                .first::<Kanji>(connection){
+                let edit = |field: &str| -> Eval<Option<Option<i32>>>{
+                    if payload.get(field).is_some(){
+                        return payload_i32(&payload, field).map(Some);
+                    }
+
+                    Ok(None)
+                };
+
+                let metadata = KanjiMetadata{
+                    stroke_count: edit("stroke_count")?,
+                    jlpt_level: edit("jlpt_level")?,
+                    grade: edit("grade")?,
+                    frequency_rank: edit("frequency_rank")?,
+                };
+
+                if !valid_metadata(&[
+                    metadata.stroke_count.unwrap_or(user_kanji.stroke_count),
+                    metadata.jlpt_level.unwrap_or(user_kanji.jlpt_level),
+                    metadata.grade.unwrap_or(user_kanji.grade),
+                    metadata.frequency_rank.unwrap_or(user_kanji.frequency_rank),
+                ]){
+                    return Err("INVALID_METADATA");
+                }
+
+                let readings = |field: &str| -> Eval<Option<Vec<Option<String>>>>{
+                    match &payload[field]{
+                        Value::Null => Ok(None),
+                        value => serde_json::from_value(value.clone())
+                            .map(Some)
+                            .map_err(|_| "INVALID_FORMAT"),
+                    }
+                };
+                let onyomi = readings("onyomi")?;
+                let kunyomi = readings("kunyomi")?;
+
+                diesel::update(&user_kanji)
+                    .set(&metadata)
+                    .execute(connection)
+                    .ok();
+
+                if onyomi.is_some() || kunyomi.is_some(){
+                    if let Ok(user_kanji) = diesel::update(&user_kanji)
+                        .set((
+                            kanji::onyomi.eq(onyomi.unwrap_or(user_kanji.onyomi.to_owned())),
+                            kanji::kunyomi.eq(kunyomi.unwrap_or(user_kanji.kunyomi.to_owned())),
+                        ))
+                        .get_result::<Kanji>(connection){
+                        sync_kanji_cards(connection, &user_kanji);
+
+                        for vocab in Vocab::belonging_to(user)
+                            .filter(vocab::phrase.eq_any(user_kanji.vocab_refs.iter().flatten()))
+                            .load::<Vocab>(connection)
+                            .unwrap(){
+                            refresh_vocab_readings(connection, user, &vocab);
+                        }
+                    }
+                }
+
+                return Ok(());
+            }
+
+            return Err("INVALID_KANJI");
+        }
+    }
+
+    Err("INVALID_FORMAT")
+}
+
+// Looks up the two kanji of a similar kanji request, both must belong to the user
+fn similar_pair(connection: &mut PgConnection, user: &User, payload: &str)-> Eval<(Kanji, Kanji)>{
+    if let Ok(payload) = serde_json::from_str::<Value>(payload){
+        if let Some(kanji_symbol) = payload["kanji_symbol"].as_str(){
+            if let Some(similar_symbol) = payload["similar_symbol"].as_str(){
+                if kanji_symbol == similar_symbol{
+                    return Err("SAME_KANJI");
+                }
+
+                if let Ok(user_kanji) = kanji::table.filter(kanji::symbol.eq(kanji_symbol))
+                    .filter(kanji::user_id.eq(user.id))
+                    .first::<Kanji>(connection){
+                    if let Ok(similar_kanji) = kanji::table.filter(kanji::symbol.eq(similar_symbol))
+                        .filter(kanji::user_id.eq(user.id))
+                        .first::<Kanji>(connection){
+                        return Ok((user_kanji, similar_kanji));
+                    }
+                }
+
+                return Err("INVALID_KANJI");
+            }
+        }
+    }
+
+    Err("INVALID_FORMAT")
+}
+
+pub fn add_similar(user: &User, payload: String)-> Eval<()>{
+    let connection = &mut establish_connection();
+
+    if users::table.find(user.id)
+        .first::<User>(connection).is_err(){
+        return Err("INVALID_USER")
+    }
+
+    let (user_kanji, other_kanji) = similar_pair(connection, user, &payload)?;
+
+    if SimilarKanji::belonging_to(&user_kanji)
+        .filter(similar_kanji::similar_id.eq(other_kanji.id))
+        .first::<SimilarKanji>(connection).is_ok(){
+        return Err("ALREADY_ADDED");
+    }
+
+    diesel::insert_into(similar_kanji::table)
+        .values(&vec![
+            NewSimilarKanji{ kanji_id: user_kanji.id, similar_id: other_kanji.id },
+            NewSimilarKanji{ kanji_id: other_kanji.id, similar_id: user_kanji.id },
+        ])
+        .execute(connection)
+        .ok();
+
+    Ok(())
+}
+
+pub fn remove_similar(user: &User, payload: String)-> Eval<()>{
+    let connection = &mut establish_connection();
+
+    if users::table.find(user.id)
+        .first::<User>(connection).is_err(){
+        return Err("INVALID_USER")
+    }
+
+    let (user_kanji, other_kanji) = similar_pair(connection, user, &payload)?;
 
-            for mut vocab in Vocab::belonging_to(&user)
-                .load::<Vocab>(connection)
+    let removed = diesel::delete(similar_kanji::table.filter(
+            similar_kanji::kanji_id.eq(user_kanji.id).and(similar_kanji::similar_id.eq(other_kanji.id))
+            .or(similar_kanji::kanji_id.eq(other_kanji.id).and(similar_kanji::similar_id.eq(user_kanji.id)))))
+        .execute(connection)
+        .unwrap_or(0);
+
+    if removed == 0{
+        return Err("ALREADY_REMOVED");
+    }
+
+    Ok(())
+}
+
+pub fn suggest_similar(user: &User, payload: String)-> Eval<String>{
+    let connection = &mut establish_connection();
+
+    if users::table.find(user.id)
+        .first::<User>(connection).is_err(){
+        return Err("INVALID_USER")
+    }
+
+    if let Ok(payload) = serde_json::from_str::<Value>(&payload){
+        if let Some(kanji_symbol) = payload["kanji_symbol"].as_str(){
+            if let Ok(user_kanji) = kanji::table.filter(kanji::symbol.eq(kanji_symbol))
+                .filter(kanji::user_id.eq(user.id))
+                .first::<Kanji>(connection){
+                let linked = SimilarKanji::belonging_to(&user_kanji)
+                    .select(similar_kanji::similar_id)
+                    .load::<i32>(connection)
+                    .unwrap();
+
+                let dataset = kanji_similarities::table.filter(kanji_similarities::kanji_symbol.eq(kanji_symbol))
+                    .select(kanji_similarities::similar_symbol)
+                    .load::<String>(connection)
+                    .unwrap()
+                    .into_iter()
+                    .collect::<HashSet<String>>();
+
+                let components = user_kanji.components.iter()
+                    .flatten()
+                    .collect::<HashSet<&String>>();
+
+                let mut suggestions = kanji::table.filter(kanji::user_id.eq(user.id))
+                    .filter(kanji::id.ne(user_kanji.id))
+                    .filter(kanji::id.ne_all(linked))
+                    .load::<Kanji>(connection)
+                    .unwrap()
+                    .into_iter()
+                    .filter_map(|other_kanji|{
+                        let shared = other_kanji.components.iter()
+                            .flatten()
+                            .filter(|component| components.contains(component))
+                            .cloned()
+                            .collect::<Vec<String>>();
+                        let smaller = components.len().min(other_kanji.components.len());
+                        let in_dataset = dataset.contains(&other_kanji.symbol);
+
+                        if in_dataset || (shared.len() >= 2 && shared.len() * 2 >= smaller){
+                            return Some((in_dataset, shared, other_kanji.symbol));
+                        }
+
+                        None
+                    })
+                    .collect::<Vec<(bool, Vec<String>, String)>>();
+
+                suggestions.sort_by(|a, b|{
+                    b.0.cmp(&a.0)
+                        .then(b.1.len().cmp(&a.1.len()))
+                        .then(a.2.cmp(&b.2))
+                });
+
+                let suggestions = suggestions.into_iter()
+                    .map(|(in_dataset, shared, symbol)|{
+                        json!({ "symbol": symbol, "shared_components": shared, "dataset": in_dataset })
+                    })
+                    .collect::<Vec<Value>>();
+
+                return Ok(json!({ "suggestions": suggestions }).to_string());
+            }
+
+            return Err("INVALID_KANJI");
+        }
+    }
+
+    Err("INVALID_FORMAT")
+}
+
+pub fn list_by_component(user: &User, payload: String)-> Eval<String>{
+    let connection = &mut establish_connection();
+
+    if users::table.find(user.id)
+        .first::<User>(connection).is_err(){
+        return Err("INVALID_USER")
+    }
+
+    if let Ok(payload) = serde_json::from_str::<Value>(&payload){
+        if let Some(radical_symbol) = payload["radical_symbol"].as_str(){
+            if radicals::table.filter(radicals::symbol.eq(radical_symbol))
+                .first::<Radical>(connection).is_ok(){
+                let user_kanji = kanji::table.filter(kanji::user_id.eq(user.id))
+                    .filter(kanji::components.contains(vec![Some(radical_symbol)]))
+                    .load::<Kanji>(connection)
+                    .unwrap();
+
+                return Ok(json!({ "kanji": user_kanji }).to_string());
+            }
+
+            return Err("INVALID_RADICAL");
+        }
+    }
+
+    Err("INVALID_FORMAT")
+}
+
+pub fn lookup_by_radicals(user: &User, payload: String)-> Eval<String>{
+    let connection = &mut establish_connection();
+
+    if users::table.find(user.id)
+        .first::<User>(connection).is_err(){
+        return Err("INVALID_USER")
+    }
+
+    if let Ok(payload) = serde_json::from_str::<Value>(&payload){
+        if let Some(radical_symbols) = payload["radical_symbols"].as_array(){
+            let radical_symbols = radical_symbols.iter()
+                .filter_map(|radical_symbol| radical_symbol.as_str())
+                .collect::<HashSet<&str>>();
+            let stroke_count = payload["stroke_count"].as_i64();
+
+            if radical_symbols.is_empty() || (!payload["stroke_count"].is_null() && stroke_count.is_none()){
+                return Err("INVALID_FORMAT");
+            }
+
+            let radical_ids = radicals::table.filter(radicals::symbol.eq_any(&radical_symbols))
+                .select(radicals::id)
+                .load::<i32>(connection)
+                .unwrap();
+            if radical_ids.len() != radical_symbols.len(){
+                return Err("INVALID_RADICAL");
+            }
+
+            let mut matches = HashMap::<String, usize>::new();
+            for kanji_symbol in kanji_components::table.filter(kanji_components::radical_id.eq_any(&radical_ids))
+                .select(kanji_components::kanji_symbol)
+                .load::<String>(connection)
                 .unwrap(){
-                if vocab.phrase.contains(&payload.symbol){
-                    vocab.kanji_refs.push(Some(payload.symbol.to_owned()));
+                *matches.entry(kanji_symbol).or_default() += 1;
+            }
 
-                    diesel::update(&vocab)
-                        .set(vocab::kanji_refs.eq(&vocab.kanji_refs))
-                        .execute(connection)
-                        .is_ok();
+            let candidates = matches.into_iter()
+                .filter(|(_, count)| *count == radical_ids.len())
+                .map(|(kanji_symbol, _)| kanji_symbol)
+                .collect::<Vec<String>>();
+
+            let stroke_counts = kanjidic::table.filter(kanjidic::symbol.eq_any(&candidates))
+                .select((kanjidic::symbol, kanjidic::stroke_count))
+                .load::<(String, i32)>(connection)
+                .unwrap()
+                .into_iter()
+                .collect::<HashMap<String, i32>>();
+
+            let candidates = candidates.into_iter()
+                .filter(|kanji_symbol|{
+                    stroke_count.is_none() || stroke_counts.get(kanji_symbol)
+                        .map(|count| i64::from(*count)) == stroke_count
+                })
+                .collect::<Vec<String>>();
+
+            let known = kanji::table.filter(kanji::user_id.eq(user.id))
+                .filter(kanji::symbol.eq_any(&candidates))
+                .select(kanji::symbol)
+                .load::<String>(connection)
+                .unwrap()
+                .into_iter()
+                .collect::<HashSet<String>>();
+
+            let mut remaining_radicals = kanji_components::table.inner_join(radicals::table)
+                .filter(kanji_components::kanji_symbol.eq_any(&candidates))
+                .select(radicals::symbol)
+                .distinct()
+                .load::<String>(connection)
+                .unwrap();
+            remaining_radicals.retain(|radical_symbol| !radical_symbols.contains(radical_symbol.as_str()));
+
+            let mut kanji_matches = candidates.iter()
+                .map(|kanji_symbol|{
+                    (stroke_counts.get(kanji_symbol).copied(), kanji_symbol)
+                })
+                .collect::<Vec<(Option<i32>, &String)>>();
+            kanji_matches.sort();
+
+            let kanji_matches = kanji_matches.into_iter()
+                .map(|(stroke_count, kanji_symbol)|{
+                    json!({
+                        "symbol": kanji_symbol,
+                        "stroke_count": stroke_count,
+                        "known": known.contains(kanji_symbol)
+                    })
+                })
+                .collect::<Vec<Value>>();
+
+            return Ok(json!({ "kanji": kanji_matches, "radicals": remaining_radicals }).to_string());
+        }
+    }
 
-                    payload.vocab_refs.push(Some(vocab.phrase));
+    Err("INVALID_FORMAT")
+}
+
+pub fn create_vocab(user: &User, payload: String)-> Eval<()>{
+    let connection = &mut establish_connection();
+
+    if users::table.find(user.id)
+        .first::<User>(connection).is_err(){
+        return Err("INVALID_USER")
+    }
+
+    if let Ok(mut payload) = serde_json::from_str::<Value>(&payload){
+        // With autofill the meaning and readings may be left out and come from JMdict instead,
+        // entry and sense pick among several matches as listed by LOOKUP_VOCAB
+        let autofill = payload["autofill"].as_bool().unwrap_or(false);
+        let entry_id = payload_i32(&payload, "entry")?;
+        let sense = match &payload["sense"]{
+            Value::Null => None,
+            sense => Some(sense.as_u64().ok_or("INVALID_FORMAT")? as usize),
+        };
+        if autofill{
+            for (field, empty) in [("meaning", json!("")), ("reading", json!([]))]{
+                if payload[field].is_null(){
+                    payload[field] = empty;
                 }
             }
+        }
+
+        if let Ok(mut new_vocab) = serde_json::from_value::<NewVocab>(payload){
+            if autofill{
+                autofill_vocab(connection, &mut new_vocab, entry_id, sense)?;
+            }
 
-            diesel::insert_into(kanji::table)
-                .values(&payload)
+            return insert_vocab(connection, user, new_vocab).map(|_| ());
+        }
+    }
+
+    Err("INVALID_FORMAT")
+}
+
+// Adds a vocab to the user's collection, linked both ways to the kanji of its phrase
+pub fn insert_vocab(connection: &mut PgConnection, user: &User, mut payload: NewVocab)-> Eval<Vocab>{
+    if vocab::table.filter(vocab::phrase.eq(&payload.phrase))
+        .filter(vocab::user_id.eq(user.id))
+        .first::<Vocab>(connection).is_ok(){
+        return Err("VOCAB_EXISTS");
+    }
+
+    payload.user_id = user.id;
+
+    for kanji in payload.phrase.chars(){
+       if let Ok(mut kanji) = kanji::table.filter(kanji::symbol.eq(kanji.to_string())) 
+           .filter(kanji::user_id.eq(user.id))
+           .first::<Kanji>(connection){
+            kanji.vocab_refs.push(Some(payload.phrase.to_owned()));
+
+            diesel::update(&kanji)
+                .set(kanji::vocab_refs.eq(&kanji.vocab_refs))
                 .execute(connection)
-                .is_ok();
+                .ok();
 
-            return Ok(());
+            payload.kanji_refs.push(Some(kanji.symbol));
+       }
+    }
+
+    let user_vocab = diesel::insert_into(vocab::table)
+        .values(&payload)
+        .get_result::<Vocab>(connection)
+        .map_err(|_| "INVALID_FORMAT")?;
+    refresh_vocab_readings(connection, user, &user_vocab);
+    link_vocab_examples(connection, &user_vocab);
+    sync_vocab_cards(connection, &user_vocab);
+
+    Ok(user_vocab)
+}
+
+pub fn edit_vocab(user: &User, payload: String)-> Eval<()>{
+    let connection = &mut establish_connection();
+
+    if users::table.find(user.id)
+        .first::<User>(connection).is_err(){
+        return Err("INVALID_USER")
+    }
+
+    if let Ok(payload) = serde_json::from_str::<Value>(&payload){
+        if let Some(vocab_phrase) = payload["vocab_phrase"].as_str(){
+            if let Ok(user_vocab) = vocab::table.filter(vocab::phrase.eq(vocab_phrase))
+                .filter(vocab::user_id.eq(user.id))
+                .first::<Vocab>(connection){
+                let meaning = match &payload["meaning"]{
+                    Value::Null => user_vocab.meaning.to_owned(),
+                    meaning => meaning.as_str().ok_or("INVALID_FORMAT")?.to_owned(),
+                };
+                let reading = match &payload["reading"]{
+                    Value::Null => user_vocab.reading.to_owned(),
+                    reading => serde_json::from_value::<Vec<Option<String>>>(reading.clone())
+                        .map_err(|_| "INVALID_FORMAT")?,
+                };
+
+                if let Ok(user_vocab) = diesel::update(&user_vocab)
+                    .set((vocab::meaning.eq(meaning), vocab::reading.eq(reading)))
+                    .get_result::<Vocab>(connection){
+                    refresh_vocab_readings(connection, user, &user_vocab);
+                    sync_vocab_cards(connection, &user_vocab);
+                }
+
+                return Ok(());
+            }
+
+            return Err("INVALID_VOCAB");
         }
-        
-        return Err("KANJI_EXISTS");
     }
 
     Err("INVALID_FORMAT")
 }
 
-pub fn create_vocab(user: &User, payload: String)-> Eval<()>{
+// The user's own readings for every kanji appearing in a phrase
+fn phrase_readings(connection: &mut PgConnection, user: &User, phrase: &str)-> HashMap<char, KanjiReadings>{
+    let symbols = phrase.chars()
+        .map(|c| c.to_string())
+        .collect::<Vec<String>>();
+
+    kanji::table.filter(kanji::user_id.eq(user.id))
+        .filter(kanji::symbol.eq_any(symbols))
+        .load::<Kanji>(connection)
+        .unwrap()
+        .into_iter()
+        .filter_map(|user_kanji|{
+            let symbol = user_kanji.symbol.chars().next()?;
+
+            Some((symbol, KanjiReadings{
+                onyomi: user_kanji.onyomi.into_iter().flatten().collect(),
+                kunyomi: user_kanji.kunyomi.into_iter().flatten().collect(),
+            }))
+        })
+        .collect()
+}
+
+// Recomputes a vocab's exception flag and which kanji readings each of its readings is built from
+pub fn refresh_vocab_readings(connection: &mut PgConnection, user: &User, user_vocab: &Vocab){
+    let readings = phrase_readings(connection, user, &user_vocab.phrase);
+
+    let mut exception = false;
+    let mut new_readings = Vec::new();
+    for reading in user_vocab.reading.iter().flatten(){
+        let segments = align(&user_vocab.phrase, reading, &readings);
+        exception |= is_exception(&segments, &readings);
+
+        for (position, segment) in segments.into_iter().flatten().enumerate(){
+            if let Some(source) = segment.source{
+                new_readings.push(NewVocabReading{
+                    reading: reading.to_owned(),
+                    position: position as i32,
+                    kanji_symbol: segment.text,
+                    kind: source.kind.as_str().to_owned(),
+                    kanji_reading: source.reading,
+                    vocab_id: user_vocab.id,
+                });
+            }
+        }
+    }
+
+    diesel::delete(VocabReading::belonging_to(user_vocab))
+        .execute(connection)
+        .ok();
+
+    diesel::insert_into(vocab_readings::table)
+        .values(&new_readings)
+        .execute(connection)
+        .ok();
+
+    diesel::update(user_vocab)
+        .set(vocab::exception.eq(exception))
+        .execute(connection)
+        .ok();
+}
+
+// Vocab as sent to the client, with furigana segments for each of its readings
+fn vocab_response(connection: &mut PgConnection, user: &User, user_vocab: Vocab)-> Value{
+    let readings = phrase_readings(connection, user, &user_vocab.phrase);
+    let ruby = user_vocab.reading.iter()
+        .flatten()
+        .map(|reading|{
+            align(&user_vocab.phrase, reading, &readings).unwrap_or_else(|| vec![RubySegment{
+                text: user_vocab.phrase.to_owned(),
+                ruby: Some(reading.to_owned()),
+                source: None,
+            }])
+        })
+        .collect::<Vec<Vec<RubySegment>>>();
+
+    let vocab_examples = VocabExample::belonging_to(&user_vocab)
+        .inner_join(examples::table)
+        .select(examples::all_columns)
+        .load::<Example>(connection)
+        .unwrap();
+
+    let relations = VocabRelation::belonging_to(&user_vocab)
+        .inner_join(vocab::table.on(vocab::id.eq(vocab_relations::related_id)))
+        .select((vocab_relations::kind, vocab::phrase))
+        .order(vocab_relations::id)
+        .load::<(String, String)>(connection)
+        .unwrap()
+        .into_iter()
+        .map(|(kind, phrase)| json!({ "relation": kind, "phrase": phrase }))
+        .collect::<Vec<Value>>();
+
+    let mut response = json!(user_vocab);
+    response["ruby"] = json!(ruby);
+    response["examples"] = json!(vocab_examples);
+    response["relations"] = json!(relations);
+    response
+}
+
+pub fn get_vocab(user: &User, payload: String)-> Eval<String>{
     let connection = &mut establish_connection();
 
     if users::table.find(user.id)
@@ -152,36 +870,347 @@ pub fn create_vocab(user: &User, payload: String)-> Eval<()>{
         return Err("INVALID_USER")
     }
 
-    if let Ok(mut payload) = serde_json::from_str::<NewVocab>(&payload){
-        if vocab::table.filter(vocab::phrase.eq(&payload.phrase))
+    let payload = if payload.is_empty(){
+        Value::Null
+    }
+    else if let Ok(payload) = serde_json::from_str::<Value>(&payload){
+        payload
+    }
+    else{
+        return Err("INVALID_FORMAT");
+    };
+
+    let user_vocab = if let Some(vocab_phrase) = payload["vocab_phrase"].as_str(){
+        if let Ok(user_vocab) = vocab::table.filter(vocab::phrase.eq(vocab_phrase))
             .filter(vocab::user_id.eq(user.id))
-            .first::<Vocab>(connection).is_err(){
+            .first::<Vocab>(connection){
+            vec![user_vocab]
+        }
+        else{
+            return Err("INVALID_VOCAB");
+        }
+    }
+    else{
+        Vocab::belonging_to(user)
+            .order(vocab::id)
+            .load::<Vocab>(connection)
+            .unwrap()
+    };
+
+    let user_vocab = user_vocab.into_iter()
+        .map(|user_vocab| vocab_response(connection, user, user_vocab))
+        .collect::<Vec<Value>>();
+
+    Ok(json!({ "vocab": user_vocab }).to_string())
+}
+
+// Links a new vocab to every example sentence of its owner containing the phrase
+fn link_vocab_examples(connection: &mut PgConnection, user_vocab: &Vocab){
+    let new_links = examples::table.filter(examples::user_id.eq(user_vocab.user_id))
+        .load::<Example>(connection)
+        .unwrap()
+        .into_iter()
+        .filter(|user_example| user_example.sentence.contains(&user_vocab.phrase))
+        .map(|user_example| NewVocabExample{ vocab_id: user_vocab.id, example_id: user_example.id })
+        .collect::<Vec<NewVocabExample>>();
+
+    diesel::insert_into(vocab_examples::table)
+        .values(&new_links)
+        .execute(connection)
+        .ok();
+}
+
+// Relinks an example sentence to every vocab of its owner it contains
+fn link_example_vocab(connection: &mut PgConnection, user_example: &Example){
+    diesel::delete(VocabExample::belonging_to(user_example))
+        .execute(connection)
+        .ok();
+
+    let new_links = vocab::table.filter(vocab::user_id.eq(user_example.user_id))
+        .load::<Vocab>(connection)
+        .unwrap()
+        .into_iter()
+        .filter(|user_vocab| user_example.sentence.contains(&user_vocab.phrase))
+        .map(|user_vocab| NewVocabExample{ vocab_id: user_vocab.id, example_id: user_example.id })
+        .collect::<Vec<NewVocabExample>>();
+
+    diesel::insert_into(vocab_examples::table)
+        .values(&new_links)
+        .execute(connection)
+        .ok();
+}
+
+pub fn create_example(user: &User, payload: String)-> Eval<()>{
+    let connection = &mut establish_connection();
+
+    if users::table.find(user.id)
+        .first::<User>(connection).is_err(){
+        return Err("INVALID_USER")
+    }
+
+    if let Ok(mut payload) = serde_json::from_str::<NewExample>(&payload){
+        if examples::table.filter(examples::sentence.eq(&payload.sentence))
+            .filter(examples::user_id.eq(user.id))
+            .first::<Example>(connection).is_err(){
             payload.user_id = user.id;
 
-            for kanji in payload.phrase.chars(){
-               if let Ok(mut kanji) = kanji::table.filter(kanji::symbol.eq(kanji.to_string())) 
-                   .filter(kanji::user_id.eq(user.id))
-                   .first::<Kanji>(connection){
-                    kanji.vocab_refs.push(Some(payload.phrase.to_owned()));
+            if let Ok(user_example) = diesel::insert_into(examples::table)
+                .values(&payload)
+                .get_result::<Example>(connection){
+                link_example_vocab(connection, &user_example);
+            }
 
-                    diesel::update(&kanji)
-                        .set(kanji::vocab_refs.eq(&kanji.vocab_refs))
-                        .execute(connection)
-                        .is_ok();
+            return Ok(());
+        }
+
+        return Err("EXAMPLE_EXISTS");
+    }
+
+    Err("INVALID_FORMAT")
+}
+
+pub fn get_examples(user: &User)-> Eval<String>{
+    let connection = &mut establish_connection();
+
+    if users::table.find(user.id)
+        .first::<User>(connection).is_err(){
+        return Err("INVALID_USER")
+    }
+
+    let user_examples = Example::belonging_to(user)
+        .order(examples::id)
+        .load::<Example>(connection)
+        .unwrap()
+        .into_iter()
+        .map(|user_example|{
+            let vocab_phrases = VocabExample::belonging_to(&user_example)
+                .inner_join(vocab::table)
+                .select(vocab::phrase)
+                .load::<String>(connection)
+                .unwrap();
+
+            let mut response = json!(user_example);
+            response["vocab"] = json!(vocab_phrases);
+            response
+        })
+        .collect::<Vec<Value>>();
+
+    Ok(json!({ "examples": user_examples }).to_string())
+}
+
+pub fn edit_example(user: &User, payload: String)-> Eval<()>{
+    let connection = &mut establish_connection();
+
+    if users::table.find(user.id)
+        .first::<User>(connection).is_err(){
+        return Err("INVALID_USER")
+    }
+
+    if let Ok(payload) = serde_json::from_str::<Value>(&payload){
+        if let Some(example_sentence) = payload["example_sentence"].as_str(){
+            if let Ok(user_example) = examples::table.filter(examples::sentence.eq(example_sentence))
+                .filter(examples::user_id.eq(user.id))
+                .first::<Example>(connection){
+                let mut changes = ExampleChanges::default();
+
+                if let Some(sentence) = payload.get("sentence"){
+                    let sentence = sentence.as_str().ok_or("INVALID_FORMAT")?;
+
+                    if sentence != user_example.sentence && examples::table.filter(examples::sentence.eq(sentence))
+                        .filter(examples::user_id.eq(user.id))
+                        .first::<Example>(connection).is_ok(){
+                        return Err("EXAMPLE_EXISTS");
+                    }
+
+                    changes.sentence = Some(sentence.to_owned());
+                }
+                if let Some(translation) = payload.get("translation"){
+                    changes.translation = Some(translation.as_str().ok_or("INVALID_FORMAT")?.to_owned());
+                }
+                if let Some(reading) = payload.get("reading"){
+                    changes.reading = match reading{
+                        Value::Null => Some(None),
+                        reading => Some(Some(reading.as_str().ok_or("INVALID_FORMAT")?.to_owned())),
+                    };
+                }
+
+                if let Ok(user_example) = diesel::update(&user_example)
+                    .set(&changes)
+                    .get_result::<Example>(connection){
+                    link_example_vocab(connection, &user_example);
+                }
 
-                    payload.kanji_refs.push(Some(kanji.symbol));
-               }
+                return Ok(());
             }
 
-            diesel::insert_into(vocab::table)
-                .values(&payload)
-                .execute(connection)
-                .is_ok();
+            return Err("INVALID_EXAMPLE");
+        }
+    }
+
+    Err("INVALID_FORMAT")
+}
+
+pub fn delete_example(user: &User, payload: String)-> Eval<()>{
+    let connection = &mut establish_connection();
+
+    if users::table.find(user.id)
+        .first::<User>(connection).is_err(){
+        return Err("INVALID_USER")
+    }
+
+    if let Ok(payload) = serde_json::from_str::<Value>(&payload){
+        if let Some(example_sentence) = payload["example_sentence"].as_str(){
+            if let Ok(user_example) = examples::table.filter(examples::sentence.eq(example_sentence))
+                .filter(examples::user_id.eq(user.id))
+                .first::<Example>(connection){
+                diesel::delete(&user_example)
+                    .execute(connection)
+                    .ok();
+
+                return Ok(());
+            }
+
+            return Err("INVALID_EXAMPLE");
+        }
+    }
+
+    Err("INVALID_FORMAT")
+}
+
+// Looks up the two vocab and relation kind of a relation request, both vocab must belong to the user
+fn relation_pair(connection: &mut PgConnection, user: &User, payload: &str)-> Eval<(Vocab, Vocab, RelationKind)>{
+    if let Ok(payload) = serde_json::from_str::<Value>(payload){
+        if let Some(vocab_phrase) = payload["vocab_phrase"].as_str(){
+            if let Some(related_phrase) = payload["related_phrase"].as_str(){
+                if let Some(relation) = payload["relation"].as_str(){
+                    let relation = RelationKind::parse(relation).ok_or("INVALID_RELATION")?;
+
+                    if vocab_phrase == related_phrase{
+                        return Err("SAME_VOCAB");
+                    }
+
+                    if let Ok(user_vocab) = vocab::table.filter(vocab::phrase.eq(vocab_phrase))
+                        .filter(vocab::user_id.eq(user.id))
+                        .first::<Vocab>(connection){
+                        if let Ok(related_vocab) = vocab::table.filter(vocab::phrase.eq(related_phrase))
+                            .filter(vocab::user_id.eq(user.id))
+                            .first::<Vocab>(connection){
+                            return Ok((user_vocab, related_vocab, relation));
+                        }
+                    }
+
+                    return Err("INVALID_VOCAB");
+                }
+            }
+        }
+    }
+
+    Err("INVALID_FORMAT")
+}
+
+pub fn add_relation(user: &User, payload: String)-> Eval<()>{
+    let connection = &mut establish_connection();
+
+    if users::table.find(user.id)
+        .first::<User>(connection).is_err(){
+        return Err("INVALID_USER")
+    }
+
+    let (user_vocab, related_vocab, relation) = relation_pair(connection, user, &payload)?;
+
+    if VocabRelation::belonging_to(&user_vocab)
+        .filter(vocab_relations::related_id.eq(related_vocab.id))
+        .filter(vocab_relations::kind.eq(relation.as_str()))
+        .first::<VocabRelation>(connection).is_ok(){
+        return Err("ALREADY_ADDED");
+    }
+
+    diesel::insert_into(vocab_relations::table)
+        .values(&vec![
+            NewVocabRelation{
+                kind: relation.as_str().to_owned(),
+                vocab_id: user_vocab.id,
+                related_id: related_vocab.id,
+            },
+            NewVocabRelation{
+                kind: relation.reverse().as_str().to_owned(),
+                vocab_id: related_vocab.id,
+                related_id: user_vocab.id,
+            },
+        ])
+        .execute(connection)
+        .ok();
+
+    Ok(())
+}
+
+pub fn remove_relation(user: &User, payload: String)-> Eval<()>{
+    let connection = &mut establish_connection();
+
+    if users::table.find(user.id)
+        .first::<User>(connection).is_err(){
+        return Err("INVALID_USER")
+    }
+
+    let (user_vocab, related_vocab, relation) = relation_pair(connection, user, &payload)?;
+
+    let removed = diesel::delete(vocab_relations::table.filter(
+            vocab_relations::vocab_id.eq(user_vocab.id)
+                .and(vocab_relations::related_id.eq(related_vocab.id))
+                .and(vocab_relations::kind.eq(relation.as_str()))
+            .or(vocab_relations::vocab_id.eq(related_vocab.id)
+                .and(vocab_relations::related_id.eq(user_vocab.id))
+                .and(vocab_relations::kind.eq(relation.reverse().as_str())))))
+        .execute(connection)
+        .unwrap_or(0);
+
+    if removed == 0{
+        return Err("ALREADY_REMOVED");
+    }
+
+    Ok(())
+}
+
+pub fn set_scheduler(user: &User, payload: String)-> Eval<()>{
+    let connection = &mut establish_connection();
+
+    let user = users::table.find(user.id)
+        .first::<User>(connection)
+        .map_err(|_| "INVALID_USER")?;
+
+    if let Ok(payload) = serde_json::from_str::<Value>(&payload){
+        if let Some(algorithm) = payload["algorithm"].as_str(){
+            if !matches!(algorithm, "sm2" | "fsrs"){
+                return Err("INVALID_ALGORITHM");
+            }
+
+            let desired_retention = match &payload["desired_retention"]{
+                Value::Null => user.desired_retention,
+                desired_retention => desired_retention.as_f64()
+                    .filter(|desired_retention| (0.7..=0.99).contains(desired_retention))
+                    .ok_or("INVALID_RETENTION")?,
+            };
+
+            let updated_user = User{
+                algorithm: algorithm.to_owned(),
+                desired_retention,
+                ..user.clone()
+            };
+
+            connection.transaction::<_, diesel::result::Error, _>(|connection|{
+                diesel::update(&user)
+                    .set((users::algorithm.eq(algorithm), users::desired_retention.eq(desired_retention)))
+                    .execute(connection)?;
+
+                if user.algorithm != algorithm{
+                    migrate_reviews(connection, &user, user_scheduler(&updated_user, &Preset::default()).as_ref())?;
+                }
+
+                Ok(())
+            }).map_err(|_| "INVALID_USER")?;
 
             return Ok(());
         }
-        
-       return  Err("VOCAB_EXISTS");
     }
 
     Err("INVALID_FORMAT")
@@ -331,6 +1360,18 @@ pub fn delete_user(user: &User)-> Eval<()>{
             .is_ok();
     }
 
+    diesel::delete(Example::belonging_to(user))
+        .execute(connection)
+        .ok();
+
+    diesel::delete(ReviewSession::belonging_to(user))
+        .execute(connection)
+        .ok();
+
+    diesel::delete(ReviewAction::belonging_to(user))
+        .execute(connection)
+        .ok();
+
     for group in Group::belonging_to(user)
         .load::<Group>(connection)
         .unwrap(){
@@ -339,6 +1380,10 @@ pub fn delete_user(user: &User)-> Eval<()>{
             .is_ok();
     }
 
+    diesel::delete(SrsPreset::belonging_to(user))
+        .execute(connection)
+        .ok();
+
     diesel::delete(user)
         .execute(connection)
         .is_ok();
@@ -363,6 +1408,21 @@ pub fn delete_kanji(user: &User, payload: String)-> Eval<()>{
                     .execute(connection)
                     .is_ok();
 
+                // Vocab using it drop the link, and their readings are broken down again without it
+                for mut user_vocab in Vocab::belonging_to(user)
+                    .filter(vocab::phrase.eq_any(user_kanji.vocab_refs.iter().flatten()))
+                    .load::<Vocab>(connection)
+                    .unwrap_or_default(){
+                    user_vocab.kanji_refs.retain(|symbol| symbol.as_deref() != Some(kanji_symbol));
+
+                    diesel::update(&user_vocab)
+                        .set(vocab::kanji_refs.eq(&user_vocab.kanji_refs))
+                        .execute(connection)
+                        .ok();
+
+                    refresh_vocab_readings(connection, user, &user_vocab);
+                }
+
                 return Ok(());
             }
 
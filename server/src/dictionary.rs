@@ -0,0 +1,656 @@
+use std::{collections::{HashMap, HashSet}, fs};
+use diesel::{
+    pg::PgConnection,
+    prelude::*,
+};
+use lib::schema::*;
+use lib::models::*;
+use serde_json::{json, Value};
+use crate::commands::{Eval, establish_connection};
+
+// Rows per INSERT statement, keeps bulk imports under postgres' bind parameter limit
+pub(crate) const CHUNK_SIZE: usize = 1000;
+
+// Reads a dictionary file, the EDRDG files ship as EUC-JP but UTF-8 conversions are common
+fn read_dictionary(path: &str)-> Eval<String>{
+    if let Ok(bytes) = fs::read(path){
+        return match String::from_utf8(bytes){
+            Ok(text) => Ok(text),
+            Err(err) =>{
+                let (text, _, malformed) = encoding_rs::EUC_JP.decode(err.as_bytes());
+                if malformed{
+                    return Err("INVALID_FILE");
+                }
+
+                Ok(text.into_owned())
+            }
+        };
+    }
+
+    Err("INVALID_FILE")
+}
+
+// Parses RADKFILE radical headers, e.g. "$ 言 7" or "$ 化 2 js01"
+pub fn parse_radkfile(text: &str)-> Vec<(String, i32)>{
+    text.lines()
+        .filter(|line| line.starts_with('$'))
+        .filter_map(|line|{
+            let mut fields = line[1..].split_whitespace();
+            let symbol = fields.next()?;
+            let stroke_count = fields.next()?.parse::<i32>().ok()?;
+
+            Some((symbol.to_owned(), stroke_count))
+        })
+        .collect()
+}
+
+// Parses KRADFILE decompositions, e.g. "語 : 言 五 口"
+pub fn parse_kradfile(text: &str)-> Vec<(String, Vec<String>)>{
+    text.lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line|{
+            let (symbol, components) = line.split_once(" : ")?;
+
+            Some((symbol.trim().to_owned(), components.split_whitespace()
+                .map(|component| component.to_owned())
+                .collect()))
+        })
+        .collect()
+}
+
+// First number found under a KANJIDIC2 element with the given tag
+fn child_number(node: roxmltree::Node, tag: &str)-> Option<i32>{
+    node.descendants()
+        .find(|child| child.has_tag_name(tag))?
+        .text()?
+        .trim()
+        .parse::<i32>()
+        .ok()
+}
+
+// Texts of the KANJIDIC2 elements with the given tag passing a filter on their attributes
+fn child_texts(node: roxmltree::Node, tag: &str, filter: &dyn Fn(roxmltree::Node)-> bool)-> Vec<Option<String>>{
+    node.descendants()
+        .filter(|child| child.has_tag_name(tag) && filter(*child))
+        .filter_map(|child| child.text())
+        .map(|text| Some(text.trim().to_owned()))
+        .collect()
+}
+
+// Parses KANJIDIC2 character entries, the first stroke count listed is the accepted one. Only
+// english meanings are kept, they're the ones without an m_lang attribute. Its jlpt levels are
+// the old four level test's, which don't map onto the N5 to N1 levels kanji are tagged with
+pub fn parse_kanjidic(text: &str)-> Eval<Vec<NewKanjidicEntry>>{
+    let options = roxmltree::ParsingOptions{ allow_dtd: true, ..Default::default() };
+    let document = roxmltree::Document::parse_with_options(text, options)
+        .map_err(|_| "INVALID_FILE")?;
+
+    Ok(document.descendants()
+        .filter(|node| node.has_tag_name("character"))
+        .filter_map(|character|{
+            let symbol = character.children()
+                .find(|node| node.has_tag_name("literal"))?
+                .text()?;
+
+            Some(NewKanjidicEntry{
+                symbol: symbol.to_owned(),
+                stroke_count: child_number(character, "stroke_count")?,
+                old_jlpt_level: child_number(character, "jlpt"),
+                grade: child_number(character, "grade"),
+                frequency_rank: child_number(character, "freq"),
+                meanings: child_texts(character, "meaning", &|meaning| meaning.attribute("m_lang").is_none()),
+                onyomi: child_texts(character, "reading", &|reading| reading.attribute("r_type") == Some("ja_on")),
+                kunyomi: child_texts(character, "reading", &|reading| reading.attribute("r_type") == Some("ja_kun")),
+            })
+        })
+        .collect())
+}
+
+// Priority tags JMdict itself counts as marking a common word
+const COMMON_PRIORITIES: [&str; 5] = ["news1", "ichi1", "spec1", "spec2", "gai1"];
+
+pub struct JmdictEntries{
+    pub entries: Vec<NewJmdictEntry>,
+    pub forms: Vec<NewJmdictForm>,
+    pub senses: Vec<NewJmdictSense>,
+}
+
+// Parses JMdict entries keyed by their sequence number. Senses without parts of speech take the
+// previous sense's as the format specifies, and only english glosses are kept
+pub fn parse_jmdict(text: &str)-> Eval<JmdictEntries>{
+    let options = roxmltree::ParsingOptions{ allow_dtd: true, ..Default::default() };
+    let document = roxmltree::Document::parse_with_options(text, options)
+        .map_err(|_| "INVALID_FILE")?;
+    let texts = |node: roxmltree::Node, tag: &str| node.children()
+        .filter(|child| child.has_tag_name(tag))
+        .filter_map(|child| child.text())
+        .map(|text| text.trim().to_owned())
+        .collect::<Vec<String>>();
+
+    let mut jmdict = JmdictEntries{ entries: Vec::new(), forms: Vec::new(), senses: Vec::new() };
+    for entry in document.descendants().filter(|node| node.has_tag_name("entry")){
+        let Some(entry_id) = child_number(entry, "ent_seq") else{
+            continue;
+        };
+
+        let mut common = false;
+        for (tag, text_tag, reading) in [("k_ele", "keb", false), ("r_ele", "reb", true)]{
+            for element in entry.children().filter(|child| child.has_tag_name(tag)){
+                let Some(text) = texts(element, text_tag).pop() else{
+                    continue;
+                };
+                let form_common = texts(element, if reading{ "re_pri" } else{ "ke_pri" }).iter()
+                    .any(|priority| COMMON_PRIORITIES.contains(&priority.as_str()));
+                common |= form_common;
+
+                jmdict.forms.push(NewJmdictForm{
+                    text,
+                    reading,
+                    common: form_common,
+                    restrictions: texts(element, "re_restr").into_iter().map(Some).collect(),
+                    entry_id,
+                });
+            }
+        }
+
+        let mut parts_of_speech = Vec::new();
+        for (position, sense) in entry.children().filter(|child| child.has_tag_name("sense")).enumerate(){
+            let sense_parts = texts(sense, "pos");
+            if !sense_parts.is_empty(){
+                parts_of_speech = sense_parts.into_iter().map(Some).collect();
+            }
+
+            let glosses = sense.children()
+                .filter(|child| child.has_tag_name("gloss"))
+                .filter(|gloss| gloss.attribute(("http://www.w3.org/XML/1998/namespace", "lang")).unwrap_or("eng") == "eng")
+                .filter_map(|gloss| gloss.text())
+                .map(|gloss| Some(gloss.trim().to_owned()))
+                .collect::<Vec<Option<String>>>();
+            if glosses.is_empty(){
+                continue;
+            }
+
+            jmdict.senses.push(NewJmdictSense{
+                position: position as i32,
+                glosses,
+                parts_of_speech: parts_of_speech.clone(),
+                entry_id,
+            });
+        }
+
+        jmdict.entries.push(NewJmdictEntry{ id: entry_id, common });
+    }
+
+    Ok(jmdict)
+}
+
+// Parses a look-alike dataset, one kanji per line followed by its look-alikes separated by whitespace or
+// commas, any numeric similarity scores in between are skipped. Pairs come back in both directions
+pub fn parse_similarities(text: &str)-> Vec<(String, String)>{
+    let mut pairs = HashSet::new();
+
+    for line in text.lines().filter(|line| !line.starts_with('#')){
+        let mut symbols = line.split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|token| !token.is_empty() && token.parse::<f64>().is_err());
+
+        if let Some(kanji_symbol) = symbols.next(){
+            for similar_symbol in symbols.filter(|symbol| *symbol != kanji_symbol){
+                pairs.insert((kanji_symbol.to_owned(), similar_symbol.to_owned()));
+                pairs.insert((similar_symbol.to_owned(), kanji_symbol.to_owned()));
+            }
+        }
+    }
+
+    pairs.into_iter().collect()
+}
+
+// Radical symbols making up a kanji, empty if the kanji is not in the component index
+pub fn find_components(connection: &mut PgConnection, kanji_symbol: &str)-> Vec<Option<String>>{
+    kanji_components::table.inner_join(radicals::table)
+        .filter(kanji_components::kanji_symbol.eq(kanji_symbol))
+        .select(radicals::symbol)
+        .load::<String>(connection)
+        .unwrap_or_default()
+        .into_iter()
+        .map(Some)
+        .collect()
+}
+
+// Fills in the meaning and readings a new kanji was sent without from its KANJIDIC2 entry
+pub fn autofill_kanji(connection: &mut PgConnection, new_kanji: &mut NewKanji){
+    if let Ok(entry) = kanjidic::table.filter(kanjidic::symbol.eq(&new_kanji.symbol))
+        .first::<KanjidicEntry>(connection){
+        if new_kanji.meaning.trim().is_empty(){
+            new_kanji.meaning = entry.meanings.into_iter()
+                .flatten()
+                .collect::<Vec<String>>()
+                .join(", ");
+        }
+        if new_kanji.onyomi.iter().flatten().next().is_none(){
+            new_kanji.onyomi = entry.onyomi;
+        }
+        if new_kanji.kunyomi.iter().flatten().next().is_none(){
+            new_kanji.kunyomi = entry.kunyomi;
+        }
+    }
+}
+
+// Suggested meaning, readings and metadata for a kanji from KANJIDIC2
+pub fn lookup_kanji(user: &User, payload: String)-> Eval<String>{
+    let connection = &mut establish_connection();
+
+    if users::table.find(user.id)
+        .first::<User>(connection).is_err(){
+        return Err("INVALID_USER")
+    }
+
+    if let Ok(payload) = serde_json::from_str::<Value>(&payload){
+        if let Some(kanji_symbol) = payload["kanji_symbol"].as_str(){
+            let entry = kanjidic::table.filter(kanjidic::symbol.eq(kanji_symbol))
+                .first::<KanjidicEntry>(connection)
+                .map_err(|_| "UNKNOWN_KANJI")?;
+            let meanings = entry.meanings.into_iter().flatten().collect::<Vec<String>>();
+
+            // KANJIDIC2 only knows the old four level jlpt, there's no N level to give
+            return Ok(json!({
+                "symbol": entry.symbol,
+                "meaning": meanings.join(", "),
+                "meanings": meanings,
+                "onyomi": entry.onyomi,
+                "kunyomi": entry.kunyomi,
+                "stroke_count": entry.stroke_count,
+                "old_jlpt_level": entry.old_jlpt_level,
+                "grade": entry.grade,
+                "frequency_rank": entry.frequency_rank,
+            }).to_string());
+        }
+    }
+
+    Err("INVALID_FORMAT")
+}
+
+// Entries with a written form or reading equal to the text, best match first. Entries where the
+// matching form itself is common come before ones only common under another form
+fn find_entries(connection: &mut PgConnection, text: &str)-> Vec<JmdictEntry>{
+    let forms = jmdict_forms::table.filter(jmdict_forms::text.eq(text))
+        .load::<JmdictForm>(connection)
+        .unwrap_or_default();
+    let entry_ids = forms.iter().map(|form| form.entry_id).collect::<Vec<i32>>();
+
+    let mut entries = jmdict_entries::table.filter(jmdict_entries::id.eq_any(entry_ids))
+        .load::<JmdictEntry>(connection)
+        .unwrap_or_default();
+    entries.sort_by_key(|entry|{
+        let form_common = forms.iter().any(|form| form.entry_id == entry.id && form.common);
+        (!form_common, !entry.common, entry.id)
+    });
+
+    entries
+}
+
+fn entry_senses(connection: &mut PgConnection, entry: &JmdictEntry)-> Vec<JmdictSense>{
+    JmdictSense::belonging_to(entry)
+        .order(jmdict_senses::position)
+        .load::<JmdictSense>(connection)
+        .unwrap_or_default()
+}
+
+// Readings of an entry that apply to the given written form
+fn entry_readings(connection: &mut PgConnection, entry: &JmdictEntry, phrase: &str)-> Vec<Option<String>>{
+    let forms = JmdictForm::belonging_to(entry)
+        .order(jmdict_forms::id)
+        .load::<JmdictForm>(connection)
+        .unwrap_or_default();
+
+    // Kana words are their own reading
+    if forms.iter().any(|form| form.reading && form.text == phrase){
+        return vec![Some(phrase.to_owned())];
+    }
+
+    forms.into_iter()
+        .filter(|form| form.reading)
+        .filter(|form| form.restrictions.is_empty() || form.restrictions.contains(&Some(phrase.to_owned())))
+        .map(|form| Some(form.text))
+        .collect()
+}
+
+// Fills in the meaning, readings and parts of speech a new vocab was sent without from its best
+// JMdict match, or from the given entry. The meaning comes from one sense, so entries with
+// several need the sense picked
+pub fn autofill_vocab(connection: &mut PgConnection, new_vocab: &mut NewVocab, entry_id: Option<i32>, sense: Option<usize>)-> Eval<()>{
+    let entries = find_entries(connection, &new_vocab.phrase);
+    let entry = match entry_id{
+        Some(entry_id) => entries.iter().find(|entry| entry.id == entry_id).ok_or("INVALID_ENTRY")?,
+        None => match entries.first(){
+            Some(entry) => entry,
+            None if new_vocab.meaning.trim().is_empty() => return Err("UNKNOWN_VOCAB"),
+            None => return Ok(()),
+        },
+    };
+
+    let senses = entry_senses(connection, entry);
+    let sense = match sense{
+        Some(sense) => Some(senses.get(sense).ok_or("INVALID_SENSE")?),
+        None if senses.len() == 1 => senses.first(),
+        None => None,
+    };
+
+    if new_vocab.meaning.trim().is_empty(){
+        new_vocab.meaning = sense.ok_or("AMBIGUOUS_SENSE")?
+            .glosses
+            .iter()
+            .flatten()
+            .map(|gloss| gloss.as_str())
+            .collect::<Vec<&str>>()
+            .join("; ");
+    }
+    if new_vocab.reading.iter().flatten().next().is_none(){
+        new_vocab.reading = entry_readings(connection, entry, &new_vocab.phrase);
+    }
+    if new_vocab.parts_of_speech.is_empty(){
+        for part in sense.map_or_else(|| senses.iter().collect(), |sense| vec![sense])
+            .into_iter()
+            .flat_map(|sense| sense.parts_of_speech.iter()){
+            if !new_vocab.parts_of_speech.contains(part){
+                new_vocab.parts_of_speech.push(part.clone());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// JMdict entries written or read as the query, best match first as used by vocab autofill
+pub fn lookup_vocab(user: &User, payload: String)-> Eval<String>{
+    let connection = &mut establish_connection();
+
+    if users::table.find(user.id)
+        .first::<User>(connection).is_err(){
+        return Err("INVALID_USER")
+    }
+
+    if let Ok(payload) = serde_json::from_str::<Value>(&payload){
+        if let Some(query) = payload["query"].as_str(){
+            let entries = find_entries(connection, query);
+            if entries.is_empty(){
+                return Err("UNKNOWN_VOCAB");
+            }
+
+            let entries = entries.into_iter()
+                .map(|entry|{
+                    let forms = JmdictForm::belonging_to(&entry)
+                        .order(jmdict_forms::id)
+                        .load::<JmdictForm>(connection)
+                        .unwrap_or_default();
+                    let form_list = |reading: bool| forms.iter()
+                        .filter(|form| form.reading == reading)
+                        .map(|form| json!({ "text": form.text, "common": form.common }))
+                        .collect::<Vec<Value>>();
+                    let senses = entry_senses(connection, &entry).into_iter()
+                        .map(|sense| json!({ "glosses": sense.glosses, "parts_of_speech": sense.parts_of_speech }))
+                        .collect::<Vec<Value>>();
+
+                    json!({
+                        "sequence": entry.id,
+                        "common": entry.common,
+                        "written": form_list(false),
+                        "readings": form_list(true),
+                        "senses": senses,
+                    })
+                })
+                .collect::<Vec<Value>>();
+
+            return Ok(json!({ "entries": entries }).to_string());
+        }
+    }
+
+    Err("INVALID_FORMAT")
+}
+
+pub fn import_radicals(kradfile_path: &str, radkfile_path: &str)-> Eval<(usize, usize)>{
+    let connection = &mut establish_connection();
+
+    let radkfile = parse_radkfile(&read_dictionary(radkfile_path)?);
+    let kradfile = parse_kradfile(&read_dictionary(kradfile_path)?);
+    if radkfile.is_empty() || kradfile.is_empty(){
+        return Err("INVALID_FILE");
+    }
+
+    connection.transaction::<_, diesel::result::Error, _>(|connection|{
+        diesel::delete(kanji_components::table).execute(connection)?;
+        diesel::delete(radicals::table).execute(connection)?;
+
+        let new_radicals = radkfile.iter()
+            .map(|(symbol, stroke_count)| NewRadical{
+                symbol: symbol.to_owned(),
+                stroke_count: *stroke_count
+            })
+            .collect::<Vec<NewRadical>>();
+
+        for chunk in new_radicals.chunks(CHUNK_SIZE){
+            diesel::insert_into(radicals::table)
+                .values(chunk)
+                .execute(connection)?;
+        }
+
+        let radical_ids = radicals::table.load::<Radical>(connection)?
+            .into_iter()
+            .map(|radical| (radical.symbol, radical.id))
+            .collect::<HashMap<String, i32>>();
+
+        let new_components = kradfile.iter()
+            .flat_map(|(symbol, components)|{
+                components.iter()
+                    .filter_map(|component| radical_ids.get(component))
+                    .map(|radical_id| NewKanjiComponent{
+                        kanji_symbol: symbol.to_owned(),
+                        radical_id: *radical_id
+                    })
+            })
+            .collect::<Vec<NewKanjiComponent>>();
+
+        for chunk in new_components.chunks(CHUNK_SIZE){
+            diesel::insert_into(kanji_components::table)
+                .values(chunk)
+                .execute(connection)?;
+        }
+
+        for user_kanji in kanji::table.load::<Kanji>(connection)?{
+            let components = find_components(connection, &user_kanji.symbol);
+
+            diesel::update(&user_kanji)
+                .set(kanji::components.eq(components))
+                .execute(connection)?;
+        }
+
+        Ok((new_radicals.len(), new_components.len()))
+    }).map_err(|_| "IMPORT_FAILED")
+}
+
+pub fn import_kanjidic(kanjidic_path: &str)-> Eval<usize>{
+    let connection = &mut establish_connection();
+
+    let entries = parse_kanjidic(&read_dictionary(kanjidic_path)?)?;
+    if entries.is_empty(){
+        return Err("INVALID_FILE");
+    }
+
+    connection.transaction::<_, diesel::result::Error, _>(|connection|{
+        diesel::delete(kanjidic::table).execute(connection)?;
+
+        for chunk in entries.chunks(CHUNK_SIZE){
+            diesel::insert_into(kanjidic::table)
+                .values(chunk)
+                .execute(connection)?;
+        }
+
+        Ok(entries.len())
+    }).map_err(|_| "IMPORT_FAILED")
+}
+
+pub fn import_jmdict(jmdict_path: &str)-> Eval<usize>{
+    let connection = &mut establish_connection();
+
+    let jmdict = parse_jmdict(&read_dictionary(jmdict_path)?)?;
+    if jmdict.entries.is_empty(){
+        return Err("INVALID_FILE");
+    }
+
+    connection.transaction::<_, diesel::result::Error, _>(|connection|{
+        diesel::delete(jmdict_entries::table).execute(connection)?;
+
+        for chunk in jmdict.entries.chunks(CHUNK_SIZE){
+            diesel::insert_into(jmdict_entries::table)
+                .values(chunk)
+                .execute(connection)?;
+        }
+        for chunk in jmdict.forms.chunks(CHUNK_SIZE){
+            diesel::insert_into(jmdict_forms::table)
+                .values(chunk)
+                .execute(connection)?;
+        }
+        for chunk in jmdict.senses.chunks(CHUNK_SIZE){
+            diesel::insert_into(jmdict_senses::table)
+                .values(chunk)
+                .execute(connection)?;
+        }
+
+        Ok(jmdict.entries.len())
+    }).map_err(|_| "IMPORT_FAILED")
+}
+
+pub fn import_similarities(similarities_path: &str)-> Eval<usize>{
+    let connection = &mut establish_connection();
+
+    let pairs = parse_similarities(&read_dictionary(similarities_path)?);
+    if pairs.is_empty(){
+        return Err("INVALID_FILE");
+    }
+
+    connection.transaction::<_, diesel::result::Error, _>(|connection|{
+        diesel::delete(kanji_similarities::table).execute(connection)?;
+
+        let new_similarities = pairs.into_iter()
+            .map(|(kanji_symbol, similar_symbol)| NewKanjiSimilarity{ kanji_symbol, similar_symbol })
+            .collect::<Vec<NewKanjiSimilarity>>();
+
+        for chunk in new_similarities.chunks(CHUNK_SIZE){
+            diesel::insert_into(kanji_similarities::table)
+                .values(chunk)
+                .execute(connection)?;
+        }
+
+        Ok(new_similarities.len())
+    }).map_err(|_| "IMPORT_FAILED")
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn radkfile_headers(){
+        let radicals = parse_radkfile(include_str!("../testdata/radkfile"));
+
+        assert_eq!(radicals, [
+            ("一".to_owned(), 1),
+            ("化".to_owned(), 2),
+            ("日".to_owned(), 4),
+            ("言".to_owned(), 7),
+        ]);
+    }
+
+    #[test]
+    fn kradfile_decompositions(){
+        let components = parse_kradfile(include_str!("../testdata/kradfile"));
+
+        assert_eq!(components.len(), 3);
+        assert_eq!(components[0], ("語".to_owned(), vec!["言".to_owned(), "五".to_owned(), "口".to_owned()]));
+        assert_eq!(components[1].1, ["化", "木"]);
+    }
+
+    #[test]
+    fn kanjidic_characters(){
+        let entries = parse_kanjidic(include_str!("../testdata/kanjidic2.xml")).unwrap();
+
+        // Characters without a stroke count are skipped
+        assert_eq!(entries.iter().map(|entry| entry.symbol.as_str()).collect::<Vec<&str>>(), ["上", "亜", "鬱"]);
+
+        let up = &entries[0];
+        assert_eq!((up.stroke_count, up.grade, up.frequency_rank), (3, Some(1), Some(35)));
+        assert_eq!(up.meanings, [Some("above".to_owned()), Some("up".to_owned())]);
+        assert_eq!(up.onyomi, [Some("ジョウ".to_owned()), Some("ショウ".to_owned())]);
+        assert_eq!(up.kunyomi, [Some("うえ".to_owned()), Some("あ.がる".to_owned())]);
+
+        // The first stroke count is the accepted one
+        assert_eq!(entries[1].stroke_count, 7);
+
+        let rare = &entries[2];
+        assert_eq!((rare.grade, rare.frequency_rank, rare.old_jlpt_level), (None, None, None));
+        assert!(rare.meanings.is_empty() && rare.onyomi.is_empty() && rare.kunyomi.is_empty());
+    }
+
+    #[test]
+    fn kanjidic_jlpt_is_the_old_scale(){
+        let entries = parse_kanjidic(include_str!("../testdata/kanjidic2.xml")).unwrap();
+
+        assert_eq!(entries[0].old_jlpt_level, Some(4));
+        assert_eq!(entries[1].old_jlpt_level, Some(1));
+    }
+
+    #[test]
+    fn kanjidic_rejects_malformed_xml(){
+        assert_eq!(parse_kanjidic("<kanjidic2><character>").err(), Some("INVALID_FILE"));
+    }
+
+    #[test]
+    fn jmdict_entries_and_forms(){
+        let jmdict = parse_jmdict(include_str!("../testdata/jmdict.xml")).unwrap();
+
+        // Entries without a sequence number are skipped, common comes from any form's priority
+        let entries = jmdict.entries.iter().map(|entry| (entry.id, entry.common)).collect::<Vec<(i32, bool)>>();
+        assert_eq!(entries, [(1358280, true), (1582710, false)]);
+
+        let forms = jmdict.forms.iter()
+            .map(|form| (form.text.as_str(), form.reading, form.common, form.entry_id))
+            .collect::<Vec<(&str, bool, bool, i32)>>();
+        assert_eq!(forms, [
+            ("食べる", false, true, 1358280),
+            ("喰べる", false, false, 1358280),
+            ("たべる", true, true, 1358280),
+            ("綺麗", false, false, 1582710),
+            ("奇麗", false, false, 1582710),
+            ("きれい", true, false, 1582710),
+            ("キレー", true, false, 1582710),
+        ]);
+        assert_eq!(jmdict.forms[6].restrictions, [Some("綺麗".to_owned())]);
+        assert!(jmdict.forms[5].restrictions.is_empty());
+    }
+
+    #[test]
+    fn jmdict_senses(){
+        let jmdict = parse_jmdict(include_str!("../testdata/jmdict.xml")).unwrap();
+        let senses = |entry_id| jmdict.senses.iter()
+            .filter(|sense| sense.entry_id == entry_id)
+            .collect::<Vec<&NewJmdictSense>>();
+
+        let eat = senses(1358280);
+        assert_eq!(eat.len(), 2);
+        // Only english glosses, and parts of speech expanded from their entities
+        assert_eq!(eat[0].glosses, [Some("to eat".to_owned())]);
+        assert_eq!(eat[0].parts_of_speech, [Some("Ichidan verb".to_owned()), Some("transitive verb".to_owned())]);
+        // A sense without parts of speech carries on the previous sense's
+        assert_eq!(eat[1].parts_of_speech, eat[0].parts_of_speech);
+        assert_eq!(eat[1].glosses.len(), 2);
+
+        // Senses with no english gloss are left out but keep their place in the numbering
+        let pretty = senses(1582710);
+        assert_eq!(pretty.iter().map(|sense| sense.position).collect::<Vec<i32>>(), [0, 2]);
+        assert_eq!(pretty[1].parts_of_speech, [Some("noun (common) (futsuumeishi)".to_owned())]);
+    }
+
+    #[test]
+    fn jmdict_rejects_malformed_xml(){
+        assert!(parse_jmdict("<JMdict><entry>").is_err());
+    }
+}
@@ -0,0 +1,256 @@
+use std::collections::{HashMap, HashSet};
+use chrono::{DateTime, Utc};
+use diesel::{
+    pg::PgConnection,
+    prelude::*,
+};
+use lib::schema::*;
+use lib::models::*;
+use serde_json::{json, Value};
+use crate::commands::{Eval, establish_connection};
+use crate::presets::PresetIndex;
+
+// Start of the current UTC day, when the daily lesson limit resets
+fn today_start()-> DateTime<Utc>{
+    Utc::now().date_naive()
+        .and_hms_opt(0, 0, 0)
+        .unwrap_or_default()
+        .and_utc()
+}
+
+// Lessons the user can still take today
+fn remaining_lessons(connection: &mut PgConnection, user: &User)-> i64{
+    let learned_kanji = Kanji::belonging_to(user)
+        .filter(kanji::learned_at.ge(today_start()))
+        .count()
+        .get_result::<i64>(connection)
+        .unwrap_or(0);
+    let learned_vocab = Vocab::belonging_to(user)
+        .filter(vocab::learned_at.ge(today_start()))
+        .count()
+        .get_result::<i64>(connection)
+        .unwrap_or(0);
+
+    (i64::from(user.lessons_per_day) - learned_kanji - learned_vocab).max(0)
+}
+
+// Lessons learned today under each preset, None being the built in defaults
+fn learned_per_preset(connection: &mut PgConnection, user: &User, index: &PresetIndex)-> HashMap<Option<i32>, i64>{
+    let mut group_ids = Kanji::belonging_to(user)
+        .filter(kanji::learned_at.ge(today_start()))
+        .select(kanji::group_id)
+        .load::<Option<i32>>(connection)
+        .unwrap_or_default();
+    group_ids.extend(Vocab::belonging_to(user)
+        .filter(vocab::learned_at.ge(today_start()))
+        .select(vocab::group_id)
+        .load::<Option<i32>>(connection)
+        .unwrap_or_default());
+
+    let mut learned = HashMap::new();
+    for group_id in group_ids{
+        *learned.entry(index.preset_id(group_id)).or_insert(0) += 1;
+    }
+
+    learned
+}
+
+// Counts a lesson from the group against its preset's daily limit, false once that's used up
+fn take_preset_lesson(index: &PresetIndex, learned: &mut HashMap<Option<i32>, i64>, group_id: Option<i32>)-> bool{
+    let preset_id = index.preset_id(group_id);
+    let taken = learned.entry(preset_id).or_insert(0);
+    if *taken >= i64::from(index.preset(preset_id).new_per_day){
+        return false;
+    }
+
+    *taken += 1;
+    true
+}
+
+fn learned_symbols(connection: &mut PgConnection, user: &User)-> HashSet<String>{
+    Kanji::belonging_to(user)
+        .filter(kanji::learned_at.is_not_null())
+        .select(kanji::symbol)
+        .load::<String>(connection)
+        .unwrap_or_default()
+        .into_iter()
+        .collect()
+}
+
+// Unlearned kanji and vocab in the order they're introduced, kanji first and a vocab only
+// once every kanji it references has been learned
+fn lesson_queue(connection: &mut PgConnection, user: &User)-> (Vec<Kanji>, Vec<Vocab>){
+    let learned = learned_symbols(connection, user);
+
+    let new_kanji = Kanji::belonging_to(user)
+        .filter(kanji::learned_at.is_null())
+        .order(kanji::id)
+        .load::<Kanji>(connection)
+        .unwrap_or_default();
+
+    let new_vocab = Vocab::belonging_to(user)
+        .filter(vocab::learned_at.is_null())
+        .order(vocab::id)
+        .load::<Vocab>(connection)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|user_vocab| user_vocab.kanji_refs.iter().flatten().all(|symbol| learned.contains(symbol)))
+        .collect();
+
+    (new_kanji, new_vocab)
+}
+
+pub fn get_lessons(user: &User, payload: String)-> Eval<String>{
+    let connection = &mut establish_connection();
+
+    let user = users::table.find(user.id)
+        .first::<User>(connection)
+        .map_err(|_| "INVALID_USER")?;
+
+    let payload = if payload.is_empty(){
+        Value::Null
+    }
+    else if let Ok(payload) = serde_json::from_str::<Value>(&payload){
+        payload
+    }
+    else{
+        return Err("INVALID_FORMAT");
+    };
+
+    let remaining = remaining_lessons(connection, &user);
+    let batch_size = match &payload["limit"]{
+        Value::Null => remaining,
+        limit => remaining.min(limit.as_i64().filter(|limit| *limit >= 0).ok_or("INVALID_FORMAT")?),
+    };
+
+    let index = PresetIndex::load(connection, &user);
+    let mut learned = learned_per_preset(connection, &user, &index);
+
+    let (new_kanji, new_vocab) = lesson_queue(connection, &user);
+    let lessons = new_kanji.into_iter()
+        .map(|user_kanji|{
+            let mut lesson = json!(user_kanji);
+            lesson["kind"] = json!("kanji");
+            (user_kanji.group_id, lesson)
+        })
+        .chain(new_vocab.into_iter().map(|user_vocab|{
+            let mut lesson = json!(user_vocab);
+            lesson["kind"] = json!("vocab");
+            (user_vocab.group_id, lesson)
+        }))
+        .filter(|(group_id, _)| take_preset_lesson(&index, &mut learned, *group_id))
+        .map(|(_, lesson)| lesson)
+        .take(batch_size as usize)
+        .collect::<Vec<Value>>();
+
+    Ok(json!({
+        "remaining_today": remaining,
+        "lessons": lessons,
+    }).to_string())
+}
+
+// Introduces a batch of lessons, their cards join the review queue from now on
+pub fn mark_learned(user: &User, payload: String)-> Eval<()>{
+    let connection = &mut establish_connection();
+
+    let user = users::table.find(user.id)
+        .first::<User>(connection)
+        .map_err(|_| "INVALID_USER")?;
+
+    if let Ok(payload) = serde_json::from_str::<Value>(&payload){
+        if let Some(items) = payload["items"].as_array(){
+            let mut learned = learned_symbols(connection, &user);
+            let mut new_kanji = Vec::new();
+            let mut new_vocab = Vec::new();
+
+            for item in items{
+                match (item["kind"].as_str(), item["item"].as_str()){
+                    (Some("kanji"), Some(symbol)) => new_kanji.push(Kanji::belonging_to(&user)
+                        .filter(kanji::symbol.eq(symbol))
+                        .first::<Kanji>(connection)
+                        .map_err(|_| "INVALID_ITEM")?),
+                    (Some("vocab"), Some(phrase)) => new_vocab.push(Vocab::belonging_to(&user)
+                        .filter(vocab::phrase.eq(phrase))
+                        .first::<Vocab>(connection)
+                        .map_err(|_| "INVALID_ITEM")?),
+                    (Some(_), Some(_)) => return Err("INVALID_KIND"),
+                    _ => return Err("INVALID_FORMAT"),
+                }
+            }
+
+            new_kanji.retain(|user_kanji| user_kanji.learned_at.is_none());
+            new_vocab.retain(|user_vocab| user_vocab.learned_at.is_none());
+            if (new_kanji.len() + new_vocab.len()) as i64 > remaining_lessons(connection, &user){
+                return Err("LESSON_LIMIT");
+            }
+
+            let index = PresetIndex::load(connection, &user);
+            let mut learned_today = learned_per_preset(connection, &user, &index);
+            let group_ids = new_kanji.iter()
+                .map(|user_kanji| user_kanji.group_id)
+                .chain(new_vocab.iter().map(|user_vocab| user_vocab.group_id))
+                .collect::<Vec<Option<i32>>>();
+            if !group_ids.into_iter().all(|group_id| take_preset_lesson(&index, &mut learned_today, group_id)){
+                return Err("LESSON_LIMIT");
+            }
+
+            // Kanji learned in the same batch count towards the vocab built from them
+            learned.extend(new_kanji.iter().map(|user_kanji| user_kanji.symbol.to_owned()));
+            if new_vocab.iter().any(|user_vocab| user_vocab.kanji_refs.iter().flatten().any(|symbol| !learned.contains(symbol))){
+                return Err("KANJI_NOT_LEARNED");
+            }
+
+            let now = Utc::now();
+            connection.transaction::<_, diesel::result::Error, _>(|connection|{
+                for user_kanji in &new_kanji{
+                    diesel::update(user_kanji)
+                        .set(kanji::learned_at.eq(now))
+                        .execute(connection)?;
+                    diesel::update(Review::belonging_to(user_kanji))
+                        .set(reviews::due.eq(now))
+                        .execute(connection)?;
+                }
+                for user_vocab in &new_vocab{
+                    diesel::update(user_vocab)
+                        .set(vocab::learned_at.eq(now))
+                        .execute(connection)?;
+                    diesel::update(Review::belonging_to(user_vocab))
+                        .set(reviews::due.eq(now))
+                        .execute(connection)?;
+                }
+
+                Ok(())
+            }).map_err(|_| "INVALID_USER")?;
+
+            return Ok(());
+        }
+    }
+
+    Err("INVALID_FORMAT")
+}
+
+pub fn set_lessons_per_day(user: &User, payload: String)-> Eval<()>{
+    let connection = &mut establish_connection();
+
+    if users::table.find(user.id)
+        .first::<User>(connection).is_err(){
+        return Err("INVALID_USER")
+    }
+
+    if let Ok(payload) = serde_json::from_str::<Value>(&payload){
+        if let Some(lessons_per_day) = payload["lessons_per_day"].as_i64(){
+            if !(0..=500).contains(&lessons_per_day){
+                return Err("INVALID_LIMIT");
+            }
+
+            diesel::update(users::table.find(user.id))
+                .set(users::lessons_per_day.eq(lessons_per_day as i32))
+                .execute(connection)
+                .ok();
+
+            return Ok(());
+        }
+    }
+
+    Err("INVALID_FORMAT")
+}
@@ -5,16 +5,38 @@ use std::{
 };
 use serde_json::json;
 use commands::*;
+use dictionary::*;
+use review::*;
+use stats::*;
+use lessons::*;
+use actions::*;
+use presets::*;
+use anki::*;
+use spreadsheet::*;
+use backup::*;
+use analysis::*;
 use lib::models::User;
 use lib::Package;
 use chrono::Local;
 use threadpool::ThreadPool;
-use tokio::{net::{TcpStream, TcpListener}, io::{AsyncReadExt, AsyncWriteExt}};
+use tokio::{net::{TcpStream, TcpListener}, io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader as AsyncBufReader}};
 
 mod commands;
+mod dictionary;
+mod review;
+mod stats;
+mod lessons;
+mod actions;
+mod presets;
+mod anki;
+mod spreadsheet;
+mod backup;
+mod analysis;
 
 // const SOCKET: &str = "192.168.2.6:7878";
 const SOCKET: &str = "127.0.0.1:7878";
+// Largest request line accepted, room for an Anki package or account backup sent as base64
+const MAX_REQUEST_SIZE: u64 = 64 * 1024 * 1024;
 
 fn log_activity(file: &Arc<Mutex<File>>, msg: String){
     // let time = Local::now().format("[%Y-%m-%d %H:%M:%S]");
@@ -77,6 +99,1002 @@ fn handle_connection(user: &mut Option<User>, request: Package)-> Package{
                         header = String::from("BAD");
                         json!({ "error": "Kanji already exists in database!" }).to_string()
                     }
+                    Err("UNKNOWN_KANJI") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Kanji isn't in the dictionary! Fill in its meaning and readings by hand..." }).to_string()
+                    }
+                    Err("INVALID_METADATA") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Kanji metadata is out of range! Check the stroke count, JLPT level, grade and frequency rank..." }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "GET_KANJI" =>{
+            if let Some(user) = user{
+                match get_kanji(user, request.payload){
+                    Ok(kanji) => kanji,
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "EDIT_KANJI" =>{
+            if let Some(user) = user{
+                match edit_kanji(user, request.payload){
+                    Err("INVALID_KANJI") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Kanji selected does not exist! Pick a valid kanji..." }).to_string()
+                    }
+                    Err("INVALID_METADATA") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Kanji metadata is out of range! Check the stroke count, JLPT level, grade and frequency rank..." }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "ADD_SIMILAR" =>{
+            if let Some(user) = user{
+                match add_similar(user, request.payload){
+                    Err("INVALID_KANJI") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Kanji selected does not exist! Pick a valid kanji..." }).to_string()
+                    }
+                    Err("SAME_KANJI") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "A kanji can't be similar to itself! Pick two different kanji..." }).to_string()
+                    }
+                    Err("ALREADY_ADDED") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Kanji already marked as similar!" }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "REMOVE_SIMILAR" =>{
+            if let Some(user) = user{
+                match remove_similar(user, request.payload){
+                    Err("INVALID_KANJI") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Kanji selected does not exist! Pick a valid kanji..." }).to_string()
+                    }
+                    Err("SAME_KANJI") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "A kanji can't be similar to itself! Pick two different kanji..." }).to_string()
+                    }
+                    Err("ALREADY_REMOVED") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Kanji already unmarked as similar!" }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "SUGGEST_SIMILAR" =>{
+            if let Some(user) = user{
+                match suggest_similar(user, request.payload){
+                    Ok(suggestions) => suggestions,
+                    Err("INVALID_KANJI") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Kanji selected does not exist! Pick a valid kanji..." }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "LIST_BY_COMPONENT" =>{
+            if let Some(user) = user{
+                match list_by_component(user, request.payload){
+                    Ok(kanji) => kanji,
+                    Err("INVALID_RADICAL") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Radical selected does not exist! Pick a valid radical..." }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "LOOKUP_BY_RADICALS" =>{
+            if let Some(user) = user{
+                match lookup_by_radicals(user, request.payload){
+                    Ok(kanji) => kanji,
+                    Err("INVALID_RADICAL") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Radical selected does not exist! Pick a valid radical..." }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "CREATE_VOCAB" =>{
+            if let Some(user) = user{
+                match create_vocab(&user, request.payload){
+                    Err("VOCAB_EXISTS") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Vocab already exists in database!" }).to_string()
+                    }
+                    Err("UNKNOWN_VOCAB") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Vocab isn't in the dictionary! Fill in its meaning and readings by hand..." }).to_string()
+                    }
+                    Err("AMBIGUOUS_SENSE") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Vocab has several senses! Pick one to fill its meaning from..." }).to_string()
+                    }
+                    Err("INVALID_SENSE") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Sense doesn't exist in the dictionary entry!" }).to_string()
+                    }
+                    Err("INVALID_ENTRY") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Dictionary entry doesn't match the vocab!" }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "GET_VOCAB" =>{
+            if let Some(user) = user{
+                match get_vocab(user, request.payload){
+                    Ok(vocab) => vocab,
+                    Err("INVALID_VOCAB") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Vocab selected does not exist! Pick a valid vocab..." }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "EDIT_VOCAB" =>{
+            if let Some(user) = user{
+                match edit_vocab(user, request.payload){
+                    Err("INVALID_VOCAB") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Vocab selected does not exist! Pick a valid vocab..." }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "CREATE_EXAMPLE" =>{
+            if let Some(user) = user{
+                match create_example(user, request.payload){
+                    Err("EXAMPLE_EXISTS") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Example already exists in database!" }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "GET_EXAMPLES" =>{
+            if let Some(user) = user{
+                match get_examples(user){
+                    Ok(examples) => examples,
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "EDIT_EXAMPLE" =>{
+            if let Some(user) = user{
+                match edit_example(user, request.payload){
+                    Err("INVALID_EXAMPLE") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Example selected does not exist! Pick a valid example..." }).to_string()
+                    }
+                    Err("EXAMPLE_EXISTS") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Example already exists in database!" }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "DELETE_EXAMPLE" =>{
+            if let Some(user) = user{
+                match delete_example(user, request.payload){
+                    Err("INVALID_EXAMPLE") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Example selected does not exist! Pick a valid example..." }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "GET_LESSONS" =>{
+            if let Some(user) = user{
+                match get_lessons(user, request.payload){
+                    Ok(lessons) => lessons,
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "MARK_LEARNED" =>{
+            if let Some(user) = user{
+                match mark_learned(user, request.payload){
+                    Err("INVALID_ITEM") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Item selected does not exist! Pick a valid item..." }).to_string()
+                    }
+                    Err("INVALID_KIND") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Invalid item kind! Pick one of kanji or vocab..." }).to_string()
+                    }
+                    Err("LESSON_LIMIT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Daily lesson limit reached! Come back tomorrow for more lessons..." }).to_string()
+                    }
+                    Err("KANJI_NOT_LEARNED") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Learn the kanji of this vocab before the vocab itself..." }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "SET_LESSONS_PER_DAY" =>{
+            if let Some(user) = user{
+                match set_lessons_per_day(user, request.payload){
+                    Err("INVALID_LIMIT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Daily lesson limit must be between 0 and 500..." }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "CREATE_PRESET" =>{
+            if let Some(user) = user{
+                match save_preset(user, request.payload){
+                    Err("INVALID_SETTINGS") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Preset settings are out of range! Check the daily limit, interval cap, learning steps, easy bonus and retention..." }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "GET_PRESETS" =>{
+            if let Some(user) = user{
+                match get_presets(user){
+                    Ok(presets) => presets,
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "DELETE_PRESET" =>{
+            if let Some(user) = user{
+                match delete_preset(user, request.payload){
+                    Err("INVALID_PRESET") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Preset selected does not exist! Pick a valid preset..." }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "SET_GROUP_PRESET" =>{
+            if let Some(user) = user{
+                match set_group_preset(user, request.payload){
+                    Err("INVALID_GROUP") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Group selected does not exist! Pick a valid group..." }).to_string()
+                    }
+                    Err("INVALID_PRESET") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Preset selected does not exist! Pick a valid preset..." }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "SET_DEFAULT_PRESET" =>{
+            if let Some(user) = user{
+                match set_default_preset(user, request.payload){
+                    Err("INVALID_PRESET") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Preset selected does not exist! Pick a valid preset..." }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "IMPORT_ANKI" =>{
+            if let Some(user) = user{
+                match import_anki(user, request.payload){
+                    Ok(report) => report,
+                    Err("INVALID_FILE") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "File is not a valid Anki package!" }).to_string()
+                    }
+                    Err("UNSUPPORTED_COLLECTION") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Anki package uses the newer collection format! Export it with legacy support enabled..." }).to_string()
+                    }
+                    Err("INVALID_MAPPING") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Field mapping is invalid! Map each note type to kanji or vocab fields and cards..." }).to_string()
+                    }
+                    Err("IMPORT_FAILED") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Anki package could not be imported!" }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "EXPORT_ANKI" =>{
+            if let Some(user) = user{
+                match export_anki(user, request.payload){
+                    Ok(package) => package,
+                    Err("INVALID_GROUP") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Group selected does not exist! Pick a valid group..." }).to_string()
+                    }
+                    Err("EXPORT_FAILED") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Anki package could not be generated!" }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "IMPORT_CSV" =>{
+            if let Some(user) = user{
+                match import_csv(user, request.payload){
+                    Ok(report) => report,
+                    Err("INVALID_KIND") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Invalid item kind! Pick one of kanji or vocab..." }).to_string()
+                    }
+                    Err("INVALID_DELIMITER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Delimiter and in-cell separator must be different single characters..." }).to_string()
+                    }
+                    Err("INVALID_COLUMNS") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Columns don't match the item's fields! Map each header to a kanji or vocab field..." }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "EXPORT_CSV" =>{
+            if let Some(user) = user{
+                match export_csv(user, request.payload){
+                    Ok(sheet) => sheet,
+                    Err("INVALID_KIND") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Invalid item kind! Pick one of kanji or vocab..." }).to_string()
+                    }
+                    Err("INVALID_DELIMITER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Delimiter and in-cell separator must be different single characters..." }).to_string()
+                    }
+                    Err("INVALID_GROUP") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Group selected does not exist! Pick a valid group..." }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "EXPORT_ACCOUNT" =>{
+            if let Some(user) = user{
+                match export_account(user){
+                    Ok(backup) => backup,
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "IMPORT_ACCOUNT" =>{
+            if let Some(user) = user{
+                match import_account(user, request.payload){
+                    Ok(report) => report,
+                    Err("INVALID_MODE") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Invalid import mode! Pick one of merge or overwrite..." }).to_string()
+                    }
+                    Err("UNSUPPORTED_VERSION") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Backup version isn't supported! Update the app to restore backups made by newer versions..." }).to_string()
+                    }
+                    Err("INVALID_BACKUP") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Backup is corrupted or incomplete!" }).to_string()
+                    }
+                    Err("IMPORT_FAILED") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Backup couldn't be restored! No changes were made..." }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "LOOKUP_KANJI" =>{
+            if let Some(user) = user{
+                match lookup_kanji(user, request.payload){
+                    Ok(entry) => entry,
+                    Err("UNKNOWN_KANJI") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Kanji isn't in the dictionary! Import KANJIDIC2 or check the symbol..." }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "LOOKUP_VOCAB" =>{
+            if let Some(user) = user{
+                match lookup_vocab(user, request.payload){
+                    Ok(entries) => entries,
+                    Err("UNKNOWN_VOCAB") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Vocab isn't in the dictionary! Import JMdict or check the spelling..." }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "ANALYZE_TEXT" =>{
+            if let Some(user) = user{
+                match analyze_text(user, request.payload){
+                    Ok(analysis) => analysis,
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "GET_DUE" =>{
+            if let Some(user) = user{
+                match get_due(user, request.payload){
+                    Ok(due) => due,
+                    Err("INVALID_GROUP") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Group selected does not exist! Pick a valid group..." }).to_string()
+                    }
+                    Err("INVALID_KIND") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Invalid item kind! Pick one of kanji or vocab..." }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "SUBMIT_REVIEW" =>{
+            if let Some(user) = user{
+                match submit_review(user, request.payload){
+                    Ok(next) => next,
+                    Err("INVALID_SESSION") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Review session does not exist! Fetch due reviews to start a new session..." }).to_string()
+                    }
+                    Err("INVALID_KIND") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Invalid item kind! Pick one of kanji or vocab..." }).to_string()
+                    }
+                    Err("INVALID_ITEM") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Item selected has no such card to review! Pick a valid item..." }).to_string()
+                    }
+                    Err("NOT_DUE") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Card is not due for review yet! Fetch due reviews to see what can be answered..." }).to_string()
+                    }
+                    Err("INVALID_GRADE") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Invalid grade! Pick one of again, hard, good or easy..." }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "CHECK_ANSWER" =>{
+            if let Some(user) = user{
+                match check_answer(user, request.payload){
+                    Ok(verdict) => verdict,
+                    Err("INVALID_CARD") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Invalid card direction! Kanji have meaning, onyomi and kunyomi cards, vocab have reading, meaning and recall cards..." }).to_string()
+                    }
+                    Err("INVALID_ITEM") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Item selected does not exist! Pick a valid item..." }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "UNDO_REVIEW" =>{
+            if let Some(user) = user{
+                match undo_review(user, request.payload){
+                    Ok(undone) => undone,
+                    Err("INVALID_SESSION") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Review session does not exist! Fetch due reviews to start a new session..." }).to_string()
+                    }
+                    Err("NOTHING_TO_UNDO") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "No answer left to undo in this session!" }).to_string()
+                    }
+                    Err("STALE_UNDO") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Card has been answered again since! Only its latest answer can be undone..." }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "SET_CARD_DIRECTION" =>{
+            if let Some(user) = user{
+                match set_card_direction(user, request.payload){
+                    Err("INVALID_CARD") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Invalid card direction! Kanji have meaning, onyomi and kunyomi cards, vocab have reading, meaning and recall cards..." }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "SET_LEECH_SETTINGS" =>{
+            if let Some(user) = user{
+                match set_leech_settings(user, request.payload){
+                    Err("INVALID_THRESHOLD") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Leech threshold must be between 1 and 99 lapses..." }).to_string()
+                    }
+                    Err("INVALID_ACTION") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Invalid leech action! Pick one of suspend or tag..." }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "LIST_LEECHES" =>{
+            if let Some(user) = user{
+                match list_leeches(user){
+                    Ok(leeches) => leeches,
                     Err("INVALID_USER") =>{
                         header = String::from("BAD");
                         json!({ "error": "User has been invalidated!" }).to_string()
@@ -89,12 +1107,294 @@ fn handle_connection(user: &mut Option<User>, request: Package)-> Package{
                 json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
             }
         }
-        "CREATE_VOCAB" =>{
+        "GET_STATS" =>{
             if let Some(user) = user{
-                match create_vocab(&user, request.payload){
-                    Err("VOCAB_EXISTS") =>{
+                match get_stats(user, request.payload){
+                    Ok(stats) => stats,
+                    Err("INVALID_GROUP") =>{
                         header = String::from("BAD");
-                        json!({ "error": "Vocab already exists in database!" }).to_string()
+                        json!({ "error": "Group selected does not exist! Pick a valid group..." }).to_string()
+                    }
+                    Err("INVALID_KIND") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Invalid item kind! Pick one of kanji or vocab..." }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("STATS_FAILED") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Statistics could not be computed! Try again later..." }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "SUSPEND" =>{
+            if let Some(user) = user{
+                match apply_review_action(user, request.payload, ReviewActionKind::Suspend){
+                    Ok(action) => action,
+                    Err("INVALID_ITEM") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Item selected does not exist! Pick a valid item..." }).to_string()
+                    }
+                    Err("INVALID_GROUP") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Group selected does not exist! Pick a valid group..." }).to_string()
+                    }
+                    Err("INVALID_KIND") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Invalid item kind! Pick one of kanji or vocab..." }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("ACTION_FAILED") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Action could not be saved! Nothing was changed..." }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "UNSUSPEND" =>{
+            if let Some(user) = user{
+                match apply_review_action(user, request.payload, ReviewActionKind::Unsuspend){
+                    Ok(action) => action,
+                    Err("INVALID_ITEM") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Item selected does not exist! Pick a valid item..." }).to_string()
+                    }
+                    Err("INVALID_GROUP") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Group selected does not exist! Pick a valid group..." }).to_string()
+                    }
+                    Err("INVALID_KIND") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Invalid item kind! Pick one of kanji or vocab..." }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("ACTION_FAILED") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Action could not be saved! Nothing was changed..." }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "BURY" =>{
+            if let Some(user) = user{
+                match apply_review_action(user, request.payload, ReviewActionKind::Bury){
+                    Ok(action) => action,
+                    Err("INVALID_ITEM") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Item selected does not exist! Pick a valid item..." }).to_string()
+                    }
+                    Err("INVALID_GROUP") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Group selected does not exist! Pick a valid group..." }).to_string()
+                    }
+                    Err("INVALID_KIND") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Invalid item kind! Pick one of kanji or vocab..." }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("ACTION_FAILED") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Action could not be saved! Nothing was changed..." }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "RESET_PROGRESS" =>{
+            if let Some(user) = user{
+                match apply_review_action(user, request.payload, ReviewActionKind::Reset){
+                    Ok(action) => action,
+                    Err("INVALID_ITEM") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Item selected does not exist! Pick a valid item..." }).to_string()
+                    }
+                    Err("INVALID_GROUP") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Group selected does not exist! Pick a valid group..." }).to_string()
+                    }
+                    Err("INVALID_KIND") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Invalid item kind! Pick one of kanji or vocab..." }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("ACTION_FAILED") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Action could not be saved! Nothing was changed..." }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "UNDO_ACTION" =>{
+            if let Some(user) = user{
+                match undo_action(user, request.payload){
+                    Ok(undone) => undone,
+                    Err("NOTHING_TO_UNDO") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "No suspend, bury or reset left to undo!" }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("ACTION_FAILED") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Action could not be saved! Nothing was changed..." }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "SET_SCHEDULER" =>{
+            if let Some(user) = user{
+                match set_scheduler(user, request.payload){
+                    Err("INVALID_ALGORITHM") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Invalid scheduling algorithm! Pick one of sm2 or fsrs..." }).to_string()
+                    }
+                    Err("INVALID_RETENTION") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Desired retention must be between 0.7 and 0.99..." }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "ADD_RELATION" =>{
+            if let Some(user) = user{
+                match add_relation(user, request.payload){
+                    Err("INVALID_VOCAB") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Vocab selected does not exist! Pick a valid vocab..." }).to_string()
+                    }
+                    Err("INVALID_RELATION") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Invalid relation! Pick one of synonym, antonym, transitive, intransitive or see_also..." }).to_string()
+                    }
+                    Err("SAME_VOCAB") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "A vocab can't be related to itself! Pick two different vocab..." }).to_string()
+                    }
+                    Err("ALREADY_ADDED") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Vocab relation already added!" }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
+                    }
+                    Err("INVALID_USER") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "User has been invalidated!" }).to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+            else{
+                header = String::from("BAD");
+                json!({ "error": "Unverified request! Login to a valid account to make this request..." }).to_string()
+            }
+        }
+        "REMOVE_RELATION" =>{
+            if let Some(user) = user{
+                match remove_relation(user, request.payload){
+                    Err("INVALID_VOCAB") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Vocab selected does not exist! Pick a valid vocab..." }).to_string()
+                    }
+                    Err("INVALID_RELATION") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Invalid relation! Pick one of synonym, antonym, transitive, intransitive or see_also..." }).to_string()
+                    }
+                    Err("SAME_VOCAB") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "A vocab can't be related to itself! Pick two different vocab..." }).to_string()
+                    }
+                    Err("ALREADY_REMOVED") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Vocab relation already removed!" }).to_string()
+                    }
+                    Err("INVALID_FORMAT") =>{
+                        header = String::from("BAD");
+                        json!({ "error": "Request body format is ill-formed!" }).to_string()
                     }
                     Err("INVALID_USER") =>{
                         header = String::from("BAD");
@@ -352,12 +1652,16 @@ fn handle_connection(user: &mut Option<User>, request: Package)-> Package{
     Package{ id: request.id, header, payload }
 }
 
-async fn check_connection(mut stream: TcpStream, addr: SocketAddr, file_handle: Arc<Mutex<File>>){
-    let mut buf = [0_u8; 4096];
+async fn check_connection(stream: TcpStream, addr: SocketAddr, file_handle: Arc<Mutex<File>>){
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = AsyncBufReader::new(reader);
+    let mut buf = Vec::new();
     let mut user = None::<User>;
 
     loop{
-        match stream.read(&mut buf).await{
+        // Requests are one line of JSON each, read whole however many reads they span
+        buf.clear();
+        match (&mut reader).take(MAX_REQUEST_SIZE).read_until(b'\n', &mut buf).await{
             Ok(0) =>{
                 log_activity(&file_handle, format!("CONNECTION TERMINATED NORMALLY || With Address: {}, User: {:?};", 
                     addr.to_string(),
@@ -371,34 +1675,45 @@ async fn check_connection(mut stream: TcpStream, addr: SocketAddr, file_handle:
                     payload: json!({ "error": "Request body format is ill-formed!" }).to_string(),
                 };
 
-                if let Some(package_end) = buf.iter().position(|x| *x == b'\n'){
-                    if let Ok(request) = serde_json::from_slice::<Package>(&buf[..package_end]){
-                        log_activity(&file_handle, format!("INCOMING REQUEST || From Address: {}, User: {:?}, Header: {}, Payload: {:?};", 
-                            addr.to_string(),
-                            user, 
-                            request.header, 
-                            request.payload));
-                        response = handle_connection(&mut user, request);
-                    }
+                // Without its newline the request hit the size limit, the rest of it can't be told apart
+                // from the next request so the connection is dropped after answering
+                let oversized = buf.last() != Some(&b'\n') && buf.len() as u64 == MAX_REQUEST_SIZE;
+                if oversized{
+                    response.payload = json!({ "error": "Request is too large!" }).to_string();
+                }
+                else if let Ok(request) = serde_json::from_slice::<Package>(buf.strip_suffix(b"\n").unwrap_or(&buf)){
+                    log_activity(&file_handle, format!("INCOMING REQUEST || From Address: {}, User: {:?}, Header: {}, Payload: {:?};", 
+                        addr,
+                        user, 
+                        request.header, 
+                        request.payload));
+                    response = handle_connection(&mut user, request);
                 }
 
                 let mut response_bytes = serde_json::to_vec(&response).unwrap();
                 response_bytes.push(b'\n');
 
-                if stream.write_all(&response_bytes).await.is_ok(){
+                if writer.write_all(&response_bytes).await.is_ok(){
                     log_activity(&file_handle, format!("OUTGOING RESPONSE SENT || To Address: {}, User: {:?}, Header: {}, Payload: {:?};", 
-                        addr.to_string(),
+                        addr,
                         user,
                         response.header, 
                         response.payload));
                 }
                 else{
                     log_activity(&file_handle, format!("OUTGOING RESPONSE FAILED || To Address: {}, User: {:?}, Header: {}, Payload: {:?};", 
-                        addr.to_string(),
+                        addr,
                         user,
                         response.header, 
                         response.payload));
                 }
+
+                if oversized{
+                    log_activity(&file_handle, format!("CONNECTION TERMINATED ABNORMALLY || With Address: {}, User: {:?};", 
+                        addr,
+                        user));
+                    return;
+                }
             }
             Err(_) =>{
                 log_activity(&file_handle, format!("CONNECTION TERMINATED ABNORMALLY || With Address: {}, User: {:?};", 
@@ -410,8 +1725,83 @@ async fn check_connection(mut stream: TcpStream, addr: SocketAddr, file_handle:
     }
 }
 
+fn handle_cli(args: &[String]){
+    match args.iter().map(|arg| arg.as_str()).collect::<Vec<&str>>().as_slice(){
+        ["import-radicals", kradfile_path, radkfile_path] =>{
+            match import_radicals(kradfile_path, radkfile_path){
+                Ok((radicals, components)) => println!("IMPORTED {radicals} RADICALS AND {components} KANJI COMPONENTS"),
+                Err(err) => println!("FAILED TO IMPORT RADICALS: {err}"),
+            }
+        }
+        ["import-kanjidic", kanjidic_path] =>{
+            match import_kanjidic(kanjidic_path){
+                Ok(entries) => println!("IMPORTED {entries} KANJIDIC ENTRIES"),
+                Err(err) => println!("FAILED TO IMPORT KANJIDIC: {err}"),
+            }
+        }
+        ["import-jmdict", jmdict_path] =>{
+            match import_jmdict(jmdict_path){
+                Ok(entries) => println!("IMPORTED {entries} JMDICT ENTRIES"),
+                Err(err) => println!("FAILED TO IMPORT JMDICT: {err}"),
+            }
+        }
+        ["import-similarities", similarities_path] =>{
+            match import_similarities(similarities_path){
+                Ok(pairs) => println!("IMPORTED {pairs} SIMILAR KANJI PAIRS"),
+                Err(err) => println!("FAILED TO IMPORT SIMILAR KANJI: {err}"),
+            }
+        }
+        ["optimize-fsrs", user_username] =>{
+            match optimize_user_weights(user_username){
+                Ok((answers, weights)) => println!("FIT FSRS WEIGHTS TO {answers} REVIEWS: {weights:?}"),
+                Err(err) => println!("FAILED TO OPTIMIZE FSRS WEIGHTS: {err}"),
+            }
+        }
+        ["import-anki", user_username, apkg_path, mapping_path, flags @ ..] =>{
+            let dry_run = flags.contains(&"--dry-run");
+            match import_anki_file(user_username, apkg_path, mapping_path, flags.contains(&"--history"), dry_run){
+                Ok(report) =>{
+                    let verb = if dry_run{ "WOULD IMPORT" } else{ "IMPORTED" };
+                    println!("{verb} {} KANJI, {} VOCAB, {} GROUPS AND {} REVIEWS",
+                        report["kanji"]["created"].as_array().map_or(0, |kanji| kanji.len()),
+                        report["vocab"]["created"].as_array().map_or(0, |vocab| vocab.len()),
+                        report["groups"].as_array().map_or(0, |groups| groups.len()),
+                        report["reviews"]);
+                    println!("SKIPPED {} DUPLICATE KANJI, {} DUPLICATE VOCAB AND {} UNMAPPED OR INCOMPLETE NOTES",
+                        report["kanji"]["duplicates"].as_array().map_or(0, |kanji| kanji.len()),
+                        report["vocab"]["duplicates"].as_array().map_or(0, |vocab| vocab.len()),
+                        report["skipped"].as_array().map_or(0, |notes| notes.len()));
+                }
+                Err(err) => println!("FAILED TO IMPORT ANKI PACKAGE: {err}"),
+            }
+        }
+        ["export-anki", user_username, apkg_path, group_titles @ ..] =>{
+            let group_titles = group_titles.iter().map(|title| title.to_string()).collect::<Vec<String>>();
+            match export_anki_file(user_username, apkg_path, &group_titles){
+                Ok(size) => println!("EXPORTED {size} BYTE ANKI PACKAGE TO {apkg_path}"),
+                Err(err) => println!("FAILED TO EXPORT ANKI PACKAGE: {err}"),
+            }
+        }
+        _ =>{
+            println!("USAGE: kms import-radicals <kradfile> <radkfile>");
+            println!("       kms import-kanjidic <kanjidic2.xml>");
+            println!("       kms import-jmdict <JMdict.xml>");
+            println!("       kms import-similarities <similarities file>");
+            println!("       kms optimize-fsrs <username>");
+            println!("       kms import-anki <username> <deck.apkg> <mapping.json> [--history] [--dry-run]");
+            println!("       kms export-anki <username> <deck.apkg> [group titles...]");
+        }
+    }
+}
+
 #[tokio::main]
 async fn main(){
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    if !args.is_empty(){
+        handle_cli(&args);
+        return;
+    }
+
     let file = Arc::new(Mutex::new(OpenOptions::new()
         .create(true)
         .append(true)
@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+use diesel::{
+    pg::PgConnection,
+    prelude::*,
+};
+use lib::schema::*;
+use lib::models::*;
+use lib::srs::Preset;
+use serde_json::{json, Value};
+use crate::commands::{Eval, establish_connection};
+
+fn to_preset(srs_preset: &SrsPreset)-> Preset{
+    Preset{
+        new_per_day: srs_preset.new_per_day,
+        max_interval: srs_preset.max_interval,
+        learning_steps: srs_preset.learning_steps.iter().flatten().copied().collect(),
+        ease_bonus: srs_preset.ease_bonus,
+        desired_retention: srs_preset.desired_retention,
+    }
+}
+
+// Which preset each of the user's groups runs on, items outside a group or in a group without
+// one fall back to the user's default preset, and then to the built in defaults
+pub struct PresetIndex{
+    group_presets: HashMap<i32, i32>,
+    presets: HashMap<i32, Preset>,
+    default_preset_id: Option<i32>,
+}
+
+impl PresetIndex{
+    pub fn load(connection: &mut PgConnection, user: &User)-> PresetIndex{
+        let presets = SrsPreset::belonging_to(user)
+            .load::<SrsPreset>(connection)
+            .unwrap_or_default()
+            .iter()
+            .map(|srs_preset| (srs_preset.id, to_preset(srs_preset)))
+            .collect();
+
+        let group_presets = Group::belonging_to(user)
+            .filter(groups::preset_id.is_not_null())
+            .select((groups::id, groups::preset_id))
+            .load::<(i32, Option<i32>)>(connection)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(group_id, preset_id)| Some((group_id, preset_id?)))
+            .collect();
+
+        PresetIndex{ group_presets, presets, default_preset_id: user.default_preset_id }
+    }
+
+    // Preset an item of the given group is scheduled with, None for the built in defaults
+    pub fn preset_id(&self, group_id: Option<i32>)-> Option<i32>{
+        group_id.and_then(|group_id| self.group_presets.get(&group_id).copied())
+            .or(self.default_preset_id)
+    }
+
+    pub fn preset(&self, preset_id: Option<i32>)-> Preset{
+        preset_id.and_then(|preset_id| self.presets.get(&preset_id).cloned())
+            .unwrap_or_default()
+    }
+}
+
+// Preset the kanji or vocab a review belongs to is scheduled with
+pub fn review_preset(connection: &mut PgConnection, user: &User, review: &Review)-> Preset{
+    let group_id = match (review.kanji_id, review.vocab_id){
+        (Some(kanji_id), _) => kanji::table.find(kanji_id)
+            .select(kanji::group_id)
+            .first::<Option<i32>>(connection),
+        (_, vocab_id) => vocab::table.find(vocab_id.unwrap_or_default())
+            .select(vocab::group_id)
+            .first::<Option<i32>>(connection),
+    }.ok().flatten();
+
+    let index = PresetIndex::load(connection, user);
+    index.preset(index.preset_id(group_id))
+}
+
+fn valid_preset(preset: &NewSrsPreset)-> bool{
+    (0..=500).contains(&preset.new_per_day)
+        && (1..=36500).contains(&preset.max_interval)
+        && preset.learning_steps.iter().all(|step| matches!(step, Some(step) if (1..=365).contains(step)))
+        && (1.0..=3.0).contains(&preset.ease_bonus)
+        && preset.desired_retention.iter().all(|retention| (0.7..=0.99).contains(retention))
+}
+
+fn find_preset(connection: &mut PgConnection, user: &User, preset_title: &str)-> Eval<SrsPreset>{
+    SrsPreset::belonging_to(user)
+        .filter(srs_presets::title.eq(preset_title))
+        .first::<SrsPreset>(connection)
+        .map_err(|_| "INVALID_PRESET")
+}
+
+// Optional preset title of a payload, null clears the preset
+fn payload_preset(connection: &mut PgConnection, user: &User, payload: &Value)-> Eval<Option<i32>>{
+    match &payload["preset_title"]{
+        Value::Null => Ok(None),
+        Value::String(preset_title) => Ok(Some(find_preset(connection, user, preset_title)?.id)),
+        _ => Err("INVALID_FORMAT"),
+    }
+}
+
+// Creates a preset, or replaces the settings of the user's preset with the same title
+pub fn save_preset(user: &User, payload: String)-> Eval<()>{
+    let connection = &mut establish_connection();
+
+    if users::table.find(user.id)
+        .first::<User>(connection).is_err(){
+        return Err("INVALID_USER")
+    }
+
+    if let Ok(mut payload) = serde_json::from_str::<NewSrsPreset>(&payload){
+        if !valid_preset(&payload){
+            return Err("INVALID_SETTINGS");
+        }
+        payload.user_id = user.id;
+
+        match find_preset(connection, user, &payload.title){
+            Ok(srs_preset) => diesel::update(&srs_preset)
+                .set(&payload)
+                .execute(connection)
+                .ok(),
+            Err(_) => diesel::insert_into(srs_presets::table)
+                .values(&payload)
+                .execute(connection)
+                .ok(),
+        };
+
+        return Ok(());
+    }
+
+    Err("INVALID_FORMAT")
+}
+
+pub fn get_presets(user: &User)-> Eval<String>{
+    let connection = &mut establish_connection();
+
+    let user = users::table.find(user.id)
+        .first::<User>(connection)
+        .map_err(|_| "INVALID_USER")?;
+
+    let presets = SrsPreset::belonging_to(&user)
+        .order(srs_presets::title)
+        .load::<SrsPreset>(connection)
+        .unwrap_or_default();
+    let default_title = presets.iter()
+        .find(|srs_preset| Some(srs_preset.id) == user.default_preset_id)
+        .map(|srs_preset| srs_preset.title.to_owned());
+
+    Ok(json!({ "presets": presets, "default": default_title }).to_string())
+}
+
+pub fn delete_preset(user: &User, payload: String)-> Eval<()>{
+    let connection = &mut establish_connection();
+
+    if users::table.find(user.id)
+        .first::<User>(connection).is_err(){
+        return Err("INVALID_USER")
+    }
+
+    if let Ok(payload) = serde_json::from_str::<Value>(&payload){
+        if let Some(preset_title) = payload["preset_title"].as_str(){
+            let srs_preset = find_preset(connection, user, preset_title)?;
+
+            diesel::delete(&srs_preset)
+                .execute(connection)
+                .ok();
+
+            return Ok(());
+        }
+    }
+
+    Err("INVALID_FORMAT")
+}
+
+// Assigns a preset to every group of the user with the given title, null puts them back on the default
+pub fn set_group_preset(user: &User, payload: String)-> Eval<()>{
+    let connection = &mut establish_connection();
+
+    if users::table.find(user.id)
+        .first::<User>(connection).is_err(){
+        return Err("INVALID_USER")
+    }
+
+    if let Ok(payload) = serde_json::from_str::<Value>(&payload){
+        if let Some(group_title) = payload["group_title"].as_str(){
+            let preset_id = payload_preset(connection, user, &payload)?;
+
+            let updated = diesel::update(Group::belonging_to(user)
+                .filter(groups::title.eq(group_title)))
+                .set(groups::preset_id.eq(preset_id))
+                .execute(connection)
+                .unwrap_or(0);
+            if updated == 0{
+                return Err("INVALID_GROUP");
+            }
+
+            return Ok(());
+        }
+    }
+
+    Err("INVALID_FORMAT")
+}
+
+pub fn set_default_preset(user: &User, payload: String)-> Eval<()>{
+    let connection = &mut establish_connection();
+
+    if users::table.find(user.id)
+        .first::<User>(connection).is_err(){
+        return Err("INVALID_USER")
+    }
+
+    if let Ok(payload) = serde_json::from_str::<Value>(&payload){
+        let preset_id = payload_preset(connection, user, &payload)?;
+
+        diesel::update(users::table.find(user.id))
+            .set(users::default_preset_id.eq(preset_id))
+            .execute(connection)
+            .ok();
+
+        return Ok(());
+    }
+
+    Err("INVALID_FORMAT")
+}
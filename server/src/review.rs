@@ -0,0 +1,729 @@
+use diesel::{
+    pg::PgConnection,
+    prelude::*,
+};
+use lib::schema::*;
+use lib::models::*;
+use lib::srs::*;
+use lib::answer::*;
+use rand::seq::SliceRandom;
+use serde_json::{json, Value};
+use chrono::{DateTime, Utc};
+use crate::commands::{Eval, establish_connection};
+use crate::presets::review_preset;
+
+// Fewest logged answers, past each item's first, worth fitting FSRS weights to
+const MINIMUM_OPTIMIZER_REVIEWS: usize = 100;
+const OPTIMIZER_EPOCHS: usize = 100;
+
+// Queues a new review for every card the item should have. Cards it no longer has keep their
+// review and answer history, get_due leaves them out until the item has them again
+fn sync_cards(connection: &mut PgConnection, user_id: i32, kanji_id: Option<i32>, vocab_id: Option<i32>, cards: Vec<Card>){
+    let existing = reviews::table.filter(reviews::kanji_id.is_not_distinct_from(kanji_id))
+        .filter(reviews::vocab_id.is_not_distinct_from(vocab_id))
+        .select(reviews::card)
+        .load::<String>(connection)
+        .unwrap_or_default();
+    let cards = cards.into_iter()
+        .map(|card| card.as_str().to_owned())
+        .collect::<Vec<String>>();
+
+    let state = ReviewState::new(&SystemClock);
+    let new_reviews = cards.into_iter()
+        .filter(|card| !existing.contains(card))
+        .map(|card| NewReview{
+            ease: state.ease,
+            interval_days: state.interval_days,
+            due: state.due,
+            repetitions: state.repetitions,
+            lapses: state.lapses,
+            user_id,
+            kanji_id,
+            vocab_id,
+            stability: state.stability,
+            difficulty: state.difficulty,
+            last_review: state.last_review,
+            card,
+        })
+        .collect::<Vec<NewReview>>();
+
+    diesel::insert_into(reviews::table)
+        .values(&new_reviews)
+        .execute(connection)
+        .ok();
+}
+
+pub fn sync_kanji_cards(connection: &mut PgConnection, user_kanji: &Kanji){
+    sync_cards(connection, user_kanji.user_id, Some(user_kanji.id), None, user_kanji.cards());
+}
+
+pub fn sync_vocab_cards(connection: &mut PgConnection, user_vocab: &Vocab){
+    sync_cards(connection, user_vocab.user_id, None, Some(user_vocab.id), user_vocab.cards());
+}
+
+pub fn review_state(review: &Review)-> ReviewState{
+    ReviewState{
+        ease: review.ease,
+        interval_days: review.interval_days,
+        due: review.due,
+        repetitions: review.repetitions,
+        lapses: review.lapses,
+        stability: review.stability,
+        difficulty: review.difficulty,
+        last_review: review.last_review,
+    }
+}
+
+pub fn review_changes(state: &ReviewState)-> ReviewChanges{
+    ReviewChanges{
+        ease: state.ease,
+        interval_days: state.interval_days,
+        due: state.due,
+        repetitions: state.repetitions,
+        lapses: state.lapses,
+        stability: state.stability,
+        difficulty: state.difficulty,
+        last_review: state.last_review,
+    }
+}
+
+// Scheduler the user picked, FSRS runs on their fitted weights once they have some and aims for
+// the preset's retention when it sets one
+pub fn user_scheduler(user: &User, preset: &Preset)-> Box<dyn Scheduler>{
+    match user.algorithm.as_str(){
+        "fsrs" =>{
+            let weights = user.fsrs_weights.iter()
+                .flatten()
+                .copied()
+                .collect::<Option<Vec<f64>>>()
+                .filter(|weights| weights.len() == DEFAULT_WEIGHTS.len())
+                .unwrap_or_else(|| DEFAULT_WEIGHTS.to_vec());
+
+            Box::new(Fsrs{ weights, desired_retention: preset.desired_retention.unwrap_or(user.desired_retention) })
+        }
+        _ => Box::new(Sm2),
+    }
+}
+
+// Carries every review of the user over to a newly picked scheduler
+pub fn migrate_reviews(connection: &mut PgConnection, user: &User, scheduler: &dyn Scheduler)-> QueryResult<()>{
+    for review in Review::belonging_to(user).load::<Review>(connection)?{
+        diesel::update(&review)
+            .set(&review_changes(&scheduler.migrate(&review_state(&review))))
+            .execute(connection)?;
+    }
+
+    Ok(())
+}
+
+// Fits FSRS weights to the user's review log and stores them, returns how many answers they were fit to
+pub fn optimize_user_weights(user_username: &str)-> Eval<(usize, Vec<f64>)>{
+    let connection = &mut establish_connection();
+
+    let user = users::table.filter(users::username.eq(user_username))
+        .first::<User>(connection)
+        .map_err(|_| "INVALID_USER")?;
+
+    let logs = ReviewLog::belonging_to(&user)
+        .filter(review_logs::undone.eq(false))
+        .order((review_logs::review_id, review_logs::reviewed_at, review_logs::id))
+        .load::<ReviewLog>(connection)
+        .map_err(|_| "INVALID_USER")?;
+
+    let mut histories = Vec::<(i32, ReviewHistory)>::new();
+    for log in &logs{
+        if let Some(grade) = Grade::parse(&log.grade){
+            match histories.last_mut(){
+                Some((review_id, history)) if *review_id == log.review_id => history.push((grade, log.elapsed_days)),
+                _ => histories.push((log.review_id, vec![(grade, log.elapsed_days)])),
+            }
+        }
+    }
+    let histories = histories.into_iter()
+        .map(|(_, history)| history)
+        .collect::<Vec<ReviewHistory>>();
+
+    let answers = histories.iter()
+        .map(|history| history.len() - 1)
+        .sum::<usize>();
+    if answers < MINIMUM_OPTIMIZER_REVIEWS{
+        return Err("NOT_ENOUGH_REVIEWS");
+    }
+
+    let weights = optimize_fsrs(&histories, &DEFAULT_WEIGHTS, OPTIMIZER_EPOCHS);
+
+    diesel::update(&user)
+        .set(users::fsrs_weights.eq(weights.iter().copied().map(Some).collect::<Vec<Option<f64>>>()))
+        .execute(connection)
+        .map_err(|_| "INVALID_USER")?;
+
+    Ok((answers, weights))
+}
+
+// Review of one card of the user's kanji or vocab, named by symbol or phrase
+fn find_review(connection: &mut PgConnection, user: &User, kind: &str, item: &str, card: &str)-> Eval<Review>{
+    let review = match kind{
+        "kanji" => reviews::table.inner_join(kanji::table)
+            .filter(kanji::user_id.eq(user.id))
+            .filter(kanji::symbol.eq(item))
+            .filter(reviews::card.eq(card))
+            .select(reviews::all_columns)
+            .first::<Review>(connection),
+        "vocab" => reviews::table.inner_join(vocab::table)
+            .filter(vocab::user_id.eq(user.id))
+            .filter(vocab::phrase.eq(item))
+            .filter(reviews::card.eq(card))
+            .select(reviews::all_columns)
+            .first::<Review>(connection),
+        _ => return Err("INVALID_KIND"),
+    };
+
+    review.map_err(|_| "INVALID_ITEM")
+}
+
+// Same filters get_due hands cards out with: the item is learned, the card is one it still has and
+// the user hasn't turned off, and it isn't suspended, buried or scheduled for later
+fn check_due(connection: &mut PgConnection, user: &User, kind: &str, review: &Review, now: DateTime<Utc>)-> Eval<()>{
+    let (learned, cards) = match kind{
+        "kanji" => kanji::table.find(review.kanji_id.unwrap_or_default())
+            .first::<Kanji>(connection)
+            .map(|user_kanji| (user_kanji.learned_at.is_some(), user_kanji.cards())),
+        _ => vocab::table.find(review.vocab_id.unwrap_or_default())
+            .first::<Vocab>(connection)
+            .map(|user_vocab| (user_vocab.learned_at.is_some(), user_vocab.cards())),
+    }.map_err(|_| "INVALID_ITEM")?;
+
+    let disabled = user.disabled_cards.contains(&Some(format!("{kind}_{}", review.card)));
+    if !learned || disabled || !cards.iter().any(|card| card.as_str() == review.card){
+        return Err("INVALID_ITEM");
+    }
+    if review.suspended || review.due > now || review.buried_until.is_some_and(|buried_until| buried_until > now){
+        return Err("NOT_DUE");
+    }
+
+    Ok(())
+}
+
+// Kind and symbol or phrase of the kanji or vocab a review belongs to
+pub fn review_item(connection: &mut PgConnection, review: &Review)-> (&'static str, String){
+    if let Some(kanji_id) = review.kanji_id{
+        let symbol = kanji::table.find(kanji_id)
+            .select(kanji::symbol)
+            .first::<String>(connection)
+            .unwrap_or_default();
+
+        return ("kanji", symbol);
+    }
+
+    let phrase = vocab::table.find(review.vocab_id.unwrap_or_default())
+        .select(vocab::phrase)
+        .first::<String>(connection)
+        .unwrap_or_default();
+
+    ("vocab", phrase)
+}
+
+fn find_session(connection: &mut PgConnection, user: &User, payload: &Value)-> Eval<ReviewSession>{
+    let session_id = payload["session_id"].as_i64()
+        .and_then(|session_id| i32::try_from(session_id).ok())
+        .ok_or("INVALID_FORMAT")?;
+
+    ReviewSession::belonging_to(user)
+        .filter(review_sessions::id.eq(session_id))
+        .first::<ReviewSession>(connection)
+        .map_err(|_| "INVALID_SESSION")
+}
+
+// Merges two shuffled queues so both kinds are spread evenly through the session
+fn interleave<T>(kanji: Vec<T>, vocab: Vec<T>)-> Vec<T>{
+    let (kanji_total, vocab_total) = (kanji.len(), vocab.len());
+    let mut kanji = kanji.into_iter().peekable();
+    let mut vocab = vocab.into_iter().peekable();
+    let mut queue = Vec::with_capacity(kanji_total + vocab_total);
+    let (mut kanji_taken, mut vocab_taken) = (0, 0);
+
+    while kanji.peek().is_some() || vocab.peek().is_some(){
+        if vocab.peek().is_none() || (kanji.peek().is_some() && kanji_taken * vocab_total <= vocab_taken * kanji_total){
+            queue.extend(kanji.next());
+            kanji_taken += 1;
+        }
+        else{
+            queue.extend(vocab.next());
+            vocab_taken += 1;
+        }
+    }
+
+    queue
+}
+
+pub fn get_due(user: &User, payload: String)-> Eval<String>{
+    let connection = &mut establish_connection();
+
+    if users::table.find(user.id)
+        .first::<User>(connection).is_err(){
+        return Err("INVALID_USER")
+    }
+
+    let payload = if payload.is_empty(){
+        Value::Null
+    }
+    else if let Ok(payload) = serde_json::from_str::<Value>(&payload){
+        payload
+    }
+    else{
+        return Err("INVALID_FORMAT");
+    };
+
+    let group_title = payload["group_title"].as_str();
+    let group_ids = match group_title{
+        Some(group_title) =>{
+            let group_ids = Group::belonging_to(user)
+                .filter(groups::title.eq(group_title))
+                .select(groups::id)
+                .load::<i32>(connection)
+                .unwrap_or_default();
+            if group_ids.is_empty(){
+                return Err("INVALID_GROUP");
+            }
+
+            Some(group_ids)
+        }
+        None => None,
+    };
+
+    let kind = payload["kind"].as_str();
+    if !matches!(kind, None | Some("kanji") | Some("vocab")){
+        return Err("INVALID_KIND");
+    }
+    let limit = match &payload["limit"]{
+        Value::Null => None,
+        limit => Some(limit.as_u64().ok_or("INVALID_FORMAT")? as usize),
+    };
+
+    let disabled_cards = users::table.find(user.id)
+        .select(users::disabled_cards)
+        .first::<Vec<Option<String>>>(connection)
+        .unwrap_or_default();
+
+    let now = Utc::now();
+    let mut due = Vec::<(&'static str, String, Review)>::new();
+
+    if kind != Some("vocab"){
+        let mut query = reviews::table.inner_join(kanji::table)
+            .filter(kanji::user_id.eq(user.id))
+            .filter(kanji::learned_at.is_not_null())
+            .filter(reviews::due.le(now))
+            .filter(reviews::suspended.eq(false))
+            .filter(reviews::buried_until.is_null().or(reviews::buried_until.le(now)))
+            .select((kanji::all_columns, reviews::all_columns))
+            .into_boxed();
+        if let Some(group_ids) = &group_ids{
+            query = query.filter(kanji::group_id.eq_any(group_ids));
+        }
+
+        due.extend(query.load::<(Kanji, Review)>(connection)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|(user_kanji, review)| user_kanji.cards().iter().any(|card| card.as_str() == review.card))
+            .map(|(user_kanji, review)| ("kanji", user_kanji.symbol, review)));
+    }
+    if kind != Some("kanji"){
+        let mut query = reviews::table.inner_join(vocab::table)
+            .filter(vocab::user_id.eq(user.id))
+            .filter(vocab::learned_at.is_not_null())
+            .filter(reviews::due.le(now))
+            .filter(reviews::suspended.eq(false))
+            .filter(reviews::buried_until.is_null().or(reviews::buried_until.le(now)))
+            .select((vocab::all_columns, reviews::all_columns))
+            .into_boxed();
+        if let Some(group_ids) = &group_ids{
+            query = query.filter(vocab::group_id.eq_any(group_ids));
+        }
+
+        due.extend(query.load::<(Vocab, Review)>(connection)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|(user_vocab, review)| user_vocab.cards().iter().any(|card| card.as_str() == review.card))
+            .map(|(user_vocab, review)| ("vocab", user_vocab.phrase, review)));
+    }
+
+    due.retain(|(kind, _, review)| !disabled_cards.contains(&Some(format!("{kind}_{}", review.card))));
+
+    // Most overdue first when the session is capped, the order within it is shuffled after
+    due.sort_by_key(|(_, _, review)| review.due);
+    if let Some(limit) = limit{
+        due.truncate(limit);
+    }
+
+    let (mut due_kanji, mut due_vocab): (Vec<_>, Vec<_>) = due.into_iter()
+        .map(|(kind, item, review)| json!({
+            "kind": kind,
+            "item": item,
+            "card": review.card,
+            "due": review.due,
+        }))
+        .partition(|item| item["kind"] == "kanji");
+    due_kanji.shuffle(&mut rand::thread_rng());
+    due_vocab.shuffle(&mut rand::thread_rng());
+
+    let session = diesel::insert_into(review_sessions::table)
+        .values(&NewReviewSession{
+            started_at: now,
+            group_title: group_title.map(|group_title| group_title.to_owned()),
+            user_id: user.id,
+        })
+        .get_result::<ReviewSession>(connection)
+        .map_err(|_| "INVALID_USER")?;
+
+    Ok(json!({
+        "session_id": session.id,
+        "items": interleave(due_kanji, due_vocab),
+    }).to_string())
+}
+
+pub fn submit_review(user: &User, payload: String)-> Eval<String>{
+    let connection = &mut establish_connection();
+
+    let user = users::table.find(user.id)
+        .first::<User>(connection)
+        .map_err(|_| "INVALID_USER")?;
+
+    if let Ok(payload) = serde_json::from_str::<Value>(&payload){
+        let session = find_session(connection, &user, &payload)?;
+
+        if let (Some(kind), Some(item), Some(grade)) =
+            (payload["kind"].as_str(), payload["item"].as_str(), payload["grade"].as_str()){
+            let card = payload["card"].as_str().unwrap_or("meaning");
+            let grade = Grade::parse(grade).ok_or("INVALID_GRADE")?;
+            let response_ms = match &payload["response_ms"]{
+                Value::Null => None,
+                response_ms => Some(response_ms.as_i64()
+                    .and_then(|response_ms| i32::try_from(response_ms).ok())
+                    .filter(|response_ms| *response_ms >= 0)
+                    .ok_or("INVALID_FORMAT")?),
+            };
+
+            let review = find_review(connection, &user, kind, item, card)?;
+            let now = Utc::now();
+            check_due(connection, &user, kind, &review, now)?;
+            let prior = review_state(&review);
+            let preset = review_preset(connection, &user, &review);
+            let state = apply_preset(user_scheduler(&user, &preset).schedule(&prior, grade, &FixedClock(now)),
+                grade, &preset, &FixedClock(now));
+            // Tagged once it lapses past the user's threshold. If they asked for that the whole item is
+            // suspended right then, the log keeps which of its other cards that took out
+            let leech = review.leech || state.lapses >= user.leech_threshold;
+            let suspend_item = leech && !review.leech && user.leech_action == "suspend";
+            let suspended = review.suspended || suspend_item;
+            let elapsed_days = prior.last_review
+                .map(|last_review| ((now - last_review).num_seconds() as f64 / 86400.0).max(0.0))
+                .unwrap_or(0.0);
+
+            connection.transaction::<_, diesel::result::Error, _>(|connection|{
+                diesel::update(&review)
+                    .set((&review_changes(&state), reviews::leech.eq(leech), reviews::suspended.eq(suspended)))
+                    .execute(connection)?;
+
+                let suspended_reviews = if suspend_item{
+                    diesel::update(reviews::table
+                        .filter(reviews::kanji_id.is_not_distinct_from(review.kanji_id))
+                        .filter(reviews::vocab_id.is_not_distinct_from(review.vocab_id))
+                        .filter(reviews::id.ne(review.id))
+                        .filter(reviews::suspended.eq(false)))
+                        .set(reviews::suspended.eq(true))
+                        .returning(reviews::id)
+                        .get_results::<i32>(connection)?
+                }
+                else{
+                    Vec::new()
+                };
+
+                diesel::insert_into(review_logs::table)
+                    .values((&NewReviewLog{
+                        grade: grade.as_str().to_owned(),
+                        reviewed_at: now,
+                        elapsed_days,
+                        user_id: user.id,
+                        review_id: review.id,
+                        card: review.card.to_owned(),
+                        response_ms,
+                        prior_ease: prior.ease,
+                        prior_interval_days: prior.interval_days,
+                        prior_due: prior.due,
+                        prior_repetitions: prior.repetitions,
+                        prior_lapses: prior.lapses,
+                        prior_stability: prior.stability,
+                        prior_difficulty: prior.difficulty,
+                        prior_last_review: prior.last_review,
+                        session_id: Some(session.id),
+                        prior_leech: review.leech,
+                        prior_suspended: review.suspended,
+                    }, review_logs::suspended_reviews.eq(suspended_reviews.into_iter().map(Some).collect::<Vec<Option<i32>>>())))
+                    .execute(connection)?;
+
+                Ok(())
+            }).map_err(|_| "INVALID_ITEM")?;
+
+            return Ok(json!({
+                "due": state.due,
+                "interval_days": state.interval_days,
+                "leech": leech,
+                "suspended": suspended,
+            }).to_string());
+        }
+    }
+
+    Err("INVALID_FORMAT")
+}
+
+// Reverts the latest answer of a session, its log entry stays but is marked as undone
+pub fn undo_review(user: &User, payload: String)-> Eval<String>{
+    let connection = &mut establish_connection();
+
+    if users::table.find(user.id)
+        .first::<User>(connection).is_err(){
+        return Err("INVALID_USER")
+    }
+
+    if let Ok(payload) = serde_json::from_str::<Value>(&payload){
+        let session = find_session(connection, user, &payload)?;
+
+        let log = ReviewLog::belonging_to(&session)
+            .filter(review_logs::undone.eq(false))
+            .order((review_logs::reviewed_at.desc(), review_logs::id.desc()))
+            .first::<ReviewLog>(connection)
+            .map_err(|_| "NOTHING_TO_UNDO")?;
+        let review = reviews::table.find(log.review_id)
+            .first::<Review>(connection)
+            .map_err(|_| "NOTHING_TO_UNDO")?;
+
+        // The card may have been answered again since, in this session or a newer one. Putting back
+        // the state before this answer would drop that later one while its log stays
+        let latest_id = ReviewLog::belonging_to(&review)
+            .filter(review_logs::undone.eq(false))
+            .order((review_logs::reviewed_at.desc(), review_logs::id.desc()))
+            .select(review_logs::id)
+            .first::<i32>(connection)
+            .map_err(|_| "NOTHING_TO_UNDO")?;
+        if latest_id != log.id{
+            return Err("STALE_UNDO");
+        }
+
+        connection.transaction::<_, diesel::result::Error, _>(|connection|{
+            diesel::update(&review)
+                .set(&ReviewChanges{
+                    ease: log.prior_ease,
+                    interval_days: log.prior_interval_days,
+                    due: log.prior_due,
+                    repetitions: log.prior_repetitions,
+                    lapses: log.prior_lapses,
+                    stability: log.prior_stability,
+                    difficulty: log.prior_difficulty,
+                    last_review: log.prior_last_review,
+                })
+                .execute(connection)?;
+
+            diesel::update(&review)
+                .set((reviews::leech.eq(log.prior_leech), reviews::suspended.eq(log.prior_suspended)))
+                .execute(connection)?;
+            diesel::update(reviews::table.filter(reviews::id.eq_any(log.suspended_reviews.iter().flatten())))
+                .set(reviews::suspended.eq(false))
+                .execute(connection)?;
+
+            diesel::update(&log)
+                .set(review_logs::undone.eq(true))
+                .execute(connection)?;
+
+            Ok(())
+        }).map_err(|_| "NOTHING_TO_UNDO")?;
+
+        let (kind, item) = review_item(connection, &review);
+
+        return Ok(json!({
+            "kind": kind,
+            "item": item,
+            "card": review.card,
+            "grade": log.grade,
+        }).to_string());
+    }
+
+    Err("INVALID_FORMAT")
+}
+
+// Turns one card direction on or off for all of the user's kanji or vocab, a disabled card keeps its progress
+pub fn set_card_direction(user: &User, payload: String)-> Eval<()>{
+    let connection = &mut establish_connection();
+
+    let user = users::table.find(user.id)
+        .first::<User>(connection)
+        .map_err(|_| "INVALID_USER")?;
+
+    if let Ok(payload) = serde_json::from_str::<Value>(&payload){
+        if let (Some(kind), Some(card), Some(active)) =
+            (payload["kind"].as_str(), payload["card"].as_str(), payload["active"].as_bool()){
+            let key = Card::parse(kind, card).ok_or("INVALID_CARD")?.key(kind);
+
+            let mut disabled_cards = user.disabled_cards.into_iter()
+                .flatten()
+                .filter(|disabled| *disabled != key)
+                .map(Some)
+                .collect::<Vec<Option<String>>>();
+            if !active{
+                disabled_cards.push(Some(key));
+            }
+
+            diesel::update(users::table.find(user.id))
+                .set(users::disabled_cards.eq(disabled_cards))
+                .execute(connection)
+                .ok();
+
+            return Ok(());
+        }
+    }
+
+    Err("INVALID_FORMAT")
+}
+
+// Grades a typed answer to a card, the client still submits the grade it settles on
+pub fn check_answer(user: &User, payload: String)-> Eval<String>{
+    let connection = &mut establish_connection();
+
+    if users::table.find(user.id)
+        .first::<User>(connection).is_err(){
+        return Err("INVALID_USER")
+    }
+
+    if let Ok(payload) = serde_json::from_str::<Value>(&payload){
+        if let (Some(kind), Some(item), Some(card), Some(answer)) =
+            (payload["kind"].as_str(), payload["item"].as_str(), payload["card"].as_str(), payload["answer"].as_str()){
+            let card = Card::parse(kind, card).ok_or("INVALID_CARD")?;
+            let strings = |values: &[Option<String>]| values.iter()
+                .flatten()
+                .filter(|value| !value.trim().is_empty())
+                .cloned()
+                .collect::<Vec<String>>();
+
+            let (verdict, expected) = if kind == "kanji"{
+                let user_kanji = kanji::table.filter(kanji::user_id.eq(user.id))
+                    .filter(kanji::symbol.eq(item))
+                    .first::<Kanji>(connection)
+                    .map_err(|_| "INVALID_ITEM")?;
+                let (onyomi, kunyomi) = (strings(&user_kanji.onyomi), strings(&user_kanji.kunyomi));
+
+                match card{
+                    Card::Onyomi => (check_reading(answer, &onyomi, &kunyomi), onyomi),
+                    Card::Kunyomi => (check_reading(answer, &kunyomi, &onyomi), kunyomi),
+                    _ => (check_meaning(answer, &accepted_meanings(&user_kanji.meaning)), vec![user_kanji.meaning]),
+                }
+            }
+            else{
+                let user_vocab = vocab::table.filter(vocab::user_id.eq(user.id))
+                    .filter(vocab::phrase.eq(item))
+                    .first::<Vocab>(connection)
+                    .map_err(|_| "INVALID_ITEM")?;
+                let reading = strings(&user_vocab.reading);
+
+                match card{
+                    Card::Reading => (check_reading(answer, &reading, &[]), reading),
+                    Card::Recall if answer.trim() == user_vocab.phrase => (Verdict::Correct, vec![user_vocab.phrase]),
+                    Card::Recall => (check_reading(answer, &reading, &[]), vec![user_vocab.phrase]),
+                    _ => (check_meaning(answer, &accepted_meanings(&user_vocab.meaning)), vec![user_vocab.meaning]),
+                }
+            };
+
+            let mut response = json!({ "result": verdict, "expected": expected });
+            if verdict == Verdict::WrongKind{
+                response["hint"] = json!(match card{
+                    Card::Onyomi => "That's the kun'yomi, we want the on'yomi",
+                    _ => "That's the on'yomi, we want the kun'yomi",
+                });
+            }
+
+            return Ok(response.to_string());
+        }
+    }
+
+    Err("INVALID_FORMAT")
+}
+
+pub fn set_leech_settings(user: &User, payload: String)-> Eval<()>{
+    let connection = &mut establish_connection();
+
+    if users::table.find(user.id)
+        .first::<User>(connection).is_err(){
+        return Err("INVALID_USER")
+    }
+
+    if let Ok(payload) = serde_json::from_str::<Value>(&payload){
+        if let (Some(threshold), Some(action)) = (payload["threshold"].as_i64(), payload["action"].as_str()){
+            if !(1..=99).contains(&threshold){
+                return Err("INVALID_THRESHOLD");
+            }
+            if !matches!(action, "suspend" | "tag"){
+                return Err("INVALID_ACTION");
+            }
+
+            diesel::update(users::table.find(user.id))
+                .set((users::leech_threshold.eq(threshold as i32), users::leech_action.eq(action)))
+                .execute(connection)
+                .ok();
+
+            return Ok(());
+        }
+    }
+
+    Err("INVALID_FORMAT")
+}
+
+// Cards tagged as leeches with every time they were failed, most lapsed first
+pub fn list_leeches(user: &User)-> Eval<String>{
+    let connection = &mut establish_connection();
+
+    if users::table.find(user.id)
+        .first::<User>(connection).is_err(){
+        return Err("INVALID_USER")
+    }
+
+    let leeches = Review::belonging_to(user)
+        .filter(reviews::leech.eq(true))
+        .order((reviews::lapses.desc(), reviews::id))
+        .load::<Review>(connection)
+        .unwrap_or_default();
+
+    let mut response = Vec::new();
+    for review in leeches{
+        let failures = ReviewLog::belonging_to(&review)
+            .filter(review_logs::undone.eq(false))
+            .filter(review_logs::grade.eq(Grade::Again.as_str()))
+            .order(review_logs::reviewed_at)
+            .select(review_logs::reviewed_at)
+            .load::<DateTime<Utc>>(connection)
+            .unwrap_or_default();
+
+        let description = match (review.kanji_id, review.vocab_id){
+            (Some(kanji_id), _) => kanji::table.find(kanji_id)
+                .select(kanji::description)
+                .first::<Option<String>>(connection),
+            (_, vocab_id) => vocab::table.find(vocab_id.unwrap_or_default())
+                .select(vocab::description)
+                .first::<Option<String>>(connection),
+        }.ok().flatten();
+        let (kind, item) = review_item(connection, &review);
+
+        let mut leech = json!({
+            "kind": kind,
+            "item": item,
+            "card": review.card,
+            "lapses": review.lapses,
+            "suspended": review.suspended,
+            "failures": failures,
+        });
+        if description.iter().all(|description| description.trim().is_empty()){
+            leech["suggestion"] = json!("Add a mnemonic to the description to help this one stick");
+        }
+
+        response.push(leech);
+    }
+
+    Ok(json!({ "leeches": response }).to_string())
+}
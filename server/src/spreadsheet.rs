@@ -0,0 +1,342 @@
+use std::collections::HashMap;
+use diesel::{
+    pg::PgConnection,
+    prelude::*,
+};
+use lib::schema::*;
+use lib::models::*;
+use regex::Regex;
+use serde_json::{json, Value};
+use crate::commands::{Eval, establish_connection, insert_kanji, insert_vocab};
+
+pub const KANJI_COLUMNS: [&str; 11] = ["symbol", "meaning", "onyomi", "kunyomi", "description", "group", "colour",
+    "stroke_count", "jlpt_level", "grade", "frequency_rank"];
+const VOCAB_COLUMNS: [&str; 8] = ["phrase", "meaning", "reading", "description", "group", "colour", "exception",
+    "parts_of_speech"];
+// In-cell separator sheets use unless told otherwise
+pub const DEFAULT_SEPARATOR: &str = ";";
+
+// Delimiter, in-cell separator for multi-value readings and parts of speech, and the kind of item
+// a sheet holds
+struct SheetOptions{
+    vocab: bool,
+    delimiter: u8,
+    separator: String,
+}
+
+fn sheet_options(payload: &Value)-> Eval<SheetOptions>{
+    let vocab = match payload["kind"].as_str(){
+        Some("kanji") => false,
+        Some("vocab") => true,
+        Some(_) => return Err("INVALID_KIND"),
+        None => return Err("INVALID_FORMAT"),
+    };
+
+    // Tabs are easier to send spelled out
+    let delimiter = match payload["delimiter"].as_str(){
+        None => b',',
+        Some("tab") => b'\t',
+        Some(delimiter) if delimiter.len() == 1 && delimiter != "\"" => delimiter.as_bytes()[0],
+        Some(_) => return Err("INVALID_DELIMITER"),
+    };
+
+    let separator = payload["separator"].as_str().unwrap_or(DEFAULT_SEPARATOR).to_owned();
+    if separator.is_empty() || separator.as_bytes() == [delimiter]{
+        return Err("INVALID_DELIMITER");
+    }
+
+    Ok(SheetOptions{ vocab, delimiter, separator })
+}
+
+fn split_cell(cell: &str, separator: &str)-> Vec<Option<String>>{
+    cell.split(separator)
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+        .map(|value| Some(value.to_owned()))
+        .collect()
+}
+
+pub fn join_cell(values: &[Option<String>], separator: &str)-> String{
+    values.iter()
+        .flatten()
+        .map(|value| value.as_str())
+        .collect::<Vec<&str>>()
+        .join(separator)
+}
+
+fn parse_metadata(cell: &str)-> Eval<Option<i32>>{
+    if cell.trim().is_empty(){
+        return Ok(None);
+    }
+
+    cell.trim().parse::<i32>()
+        .map(Some)
+        .map_err(|_| "INVALID_METADATA")
+}
+
+// Group a row belongs to, created with the row's colour when the import asks for missing groups
+fn row_group(connection: &mut PgConnection, user: &User, vocab: bool, title: &str, colour: &str, create_groups: bool)-> Eval<Option<i32>>{
+    if title.is_empty(){
+        return Ok(None);
+    }
+
+    if let Ok(group_id) = Group::belonging_to(user)
+        .filter(groups::title.eq(title))
+        .filter(groups::vocab.eq(vocab))
+        .select(groups::id)
+        .first::<i32>(connection){
+        return Ok(Some(group_id));
+    }
+
+    if !create_groups{
+        return Err("INVALID_GROUP");
+    }
+    if !colour.is_empty() && !Regex::new(r"^#([0-9A-Fa-f]{6})$").unwrap().is_match(colour){
+        return Err("INVALID_HEXCODE");
+    }
+
+    diesel::insert_into(groups::table)
+        .values(&NewGroup{
+            title: title.to_owned(),
+            colour: Some(colour.to_owned()).filter(|colour| !colour.is_empty()),
+            vocab,
+            user_id: user.id,
+        })
+        .returning(groups::id)
+        .get_result::<i32>(connection)
+        .map(Some)
+        .map_err(|_| "INVALID_GROUP")
+}
+
+fn import_row(connection: &mut PgConnection, user: &User, options: &SheetOptions, create_groups: bool, cell: &dyn Fn(&str)-> String)-> Eval<()>{
+    let group_id = row_group(connection, user, options.vocab, &cell("group"), &cell("colour"), create_groups)?;
+    let description = Some(cell("description")).filter(|description| !description.is_empty());
+
+    if options.vocab{
+        let phrase = cell("phrase");
+        let meaning = cell("meaning");
+        if phrase.is_empty() || meaning.is_empty(){
+            return Err("MISSING_FIELD");
+        }
+
+        let exception = match cell("exception").to_lowercase().as_str(){
+            "" | "false" | "0" => false,
+            "true" | "1" => true,
+            _ => return Err("INVALID_FORMAT"),
+        };
+
+        insert_vocab(connection, user, NewVocab{
+            phrase,
+            meaning,
+            reading: split_cell(&cell("reading"), &options.separator),
+            description,
+            kanji_refs: Vec::new(),
+            user_id: user.id,
+            group_id,
+            exception,
+            parts_of_speech: split_cell(&cell("parts_of_speech"), &options.separator),
+        })?;
+    }
+    else{
+        let symbol = cell("symbol");
+        let meaning = cell("meaning");
+        if symbol.is_empty() || meaning.is_empty(){
+            return Err("MISSING_FIELD");
+        }
+
+        insert_kanji(connection, user, NewKanji{
+            symbol,
+            meaning,
+            onyomi: split_cell(&cell("onyomi"), &options.separator),
+            kunyomi: split_cell(&cell("kunyomi"), &options.separator),
+            description,
+            vocab_refs: Vec::new(),
+            user_id: user.id,
+            group_id,
+            components: Vec::new(),
+            stroke_count: parse_metadata(&cell("stroke_count"))?,
+            jlpt_level: parse_metadata(&cell("jlpt_level"))?,
+            grade: parse_metadata(&cell("grade"))?,
+            frequency_rank: parse_metadata(&cell("frequency_rank"))?,
+        })?;
+    }
+
+    Ok(())
+}
+
+// Imports each row of a sheet as a kanji or vocab, rows that fail are reported by their line and
+// don't stop the rest. Headers name fields directly unless columns maps them to fields
+pub fn import_csv(user: &User, payload: String)-> Eval<String>{
+    let connection = &mut establish_connection();
+
+    if users::table.find(user.id)
+        .first::<User>(connection).is_err(){
+        return Err("INVALID_USER")
+    }
+
+    let payload = serde_json::from_str::<Value>(&payload)
+        .map_err(|_| "INVALID_FORMAT")?;
+    let options = sheet_options(&payload)?;
+    let text = payload["text"].as_str().ok_or("INVALID_FORMAT")?;
+    let columns = match &payload["columns"]{
+        Value::Null => HashMap::new(),
+        columns => serde_json::from_value::<HashMap<String, String>>(columns.clone())
+            .map_err(|_| "INVALID_FORMAT")?,
+    };
+    let create_groups = payload["create_groups"].as_bool().unwrap_or(false);
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(options.delimiter)
+        .flexible(true)
+        .from_reader(text.as_bytes());
+
+    let fields: &[&str] = if options.vocab{ &VOCAB_COLUMNS } else{ &KANJI_COLUMNS };
+    let headers = reader.headers()
+        .map_err(|_| "INVALID_FORMAT")?
+        .iter()
+        .map(|header| columns.get(header.trim()).map_or(header.trim(), |field| field.as_str()).to_owned())
+        .collect::<Vec<String>>();
+    if !headers.iter().all(|header| fields.contains(&header.as_str()))
+        || !fields[..2].iter().all(|field| headers.iter().any(|header| header == field)){
+        return Err("INVALID_COLUMNS");
+    }
+
+    let mut created = 0;
+    let mut errors = Vec::new();
+    for record in reader.records(){
+        let result = match &record{
+            Ok(record) =>{
+                let cell = |field: &str| headers.iter()
+                    .position(|header| header == field)
+                    .and_then(|position| record.get(position))
+                    .unwrap_or_default()
+                    .to_owned();
+
+                // A failed row leaves nothing behind, not even a group it created
+                let mut row_error = "INVALID_FORMAT";
+                connection.transaction::<_, diesel::result::Error, _>(|connection|{
+                    import_row(connection, user, &options, create_groups, &cell).map_err(|error|{
+                        row_error = error;
+                        diesel::result::Error::RollbackTransaction
+                    })
+                }).map_err(|_| row_error)
+            }
+            Err(_) => Err("INVALID_FORMAT"),
+        };
+
+        match result{
+            Ok(()) => created += 1,
+            Err(error) =>{
+                let line = match &record{
+                    Ok(record) => record.position().map(|position| position.line()),
+                    Err(err) => err.position().map(|position| position.line()),
+                };
+                errors.push(json!({ "line": line, "error": error }));
+            }
+        }
+    }
+
+    Ok(json!({ "created": created, "errors": errors }).to_string())
+}
+
+// Writes the user's kanji or vocab, optionally only those of some groups, as a sheet that imports
+// back into the same items and groups
+pub fn export_csv(user: &User, payload: String)-> Eval<String>{
+    let connection = &mut establish_connection();
+
+    if users::table.find(user.id)
+        .first::<User>(connection).is_err(){
+        return Err("INVALID_USER")
+    }
+
+    let payload = serde_json::from_str::<Value>(&payload)
+        .map_err(|_| "INVALID_FORMAT")?;
+    let options = sheet_options(&payload)?;
+
+    let groups = Group::belonging_to(user)
+        .filter(groups::vocab.eq(options.vocab))
+        .load::<Group>(connection)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|group| (group.id, group))
+        .collect::<HashMap<i32, Group>>();
+    let group_ids = match &payload["group_titles"]{
+        Value::Null => None,
+        group_titles =>{
+            let group_titles = serde_json::from_value::<Vec<String>>(group_titles.clone())
+                .map_err(|_| "INVALID_FORMAT")?;
+            if !group_titles.iter().all(|title| groups.values().any(|group| group.title == *title)){
+                return Err("INVALID_GROUP");
+            }
+
+            Some(groups.values()
+                .filter(|group| group_titles.contains(&group.title))
+                .map(|group| group.id)
+                .collect::<Vec<i32>>())
+        }
+    };
+    let group_cells = |group_id: Option<i32>|{
+        group_id.and_then(|group_id| groups.get(&group_id))
+            .map(|group| (group.title.to_owned(), group.colour.clone().unwrap_or_default()))
+            .unwrap_or_default()
+    };
+
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(options.delimiter)
+        .from_writer(Vec::new());
+    let write_error = |_| "INVALID_FORMAT";
+
+    if options.vocab{
+        let mut query = Vocab::belonging_to(user).into_boxed();
+        if let Some(group_ids) = group_ids{
+            query = query.filter(vocab::group_id.eq_any(group_ids));
+        }
+
+        writer.write_record(VOCAB_COLUMNS).map_err(write_error)?;
+        for user_vocab in query.order(vocab::id).load::<Vocab>(connection).unwrap_or_default(){
+            let (group, colour) = group_cells(user_vocab.group_id);
+            writer.write_record([
+                user_vocab.phrase,
+                user_vocab.meaning,
+                join_cell(&user_vocab.reading, &options.separator),
+                user_vocab.description.unwrap_or_default(),
+                group,
+                colour,
+                user_vocab.exception.to_string(),
+                join_cell(&user_vocab.parts_of_speech, &options.separator),
+            ]).map_err(write_error)?;
+        }
+    }
+    else{
+        let mut query = Kanji::belonging_to(user).into_boxed();
+        if let Some(group_ids) = group_ids{
+            query = query.filter(kanji::group_id.eq_any(group_ids));
+        }
+
+        let metadata_cell = |value: Option<i32>| value.map(|value| value.to_string()).unwrap_or_default();
+        writer.write_record(KANJI_COLUMNS).map_err(write_error)?;
+        for user_kanji in query.order(kanji::id).load::<Kanji>(connection).unwrap_or_default(){
+            let (group, colour) = group_cells(user_kanji.group_id);
+            writer.write_record([
+                user_kanji.symbol,
+                user_kanji.meaning,
+                join_cell(&user_kanji.onyomi, &options.separator),
+                join_cell(&user_kanji.kunyomi, &options.separator),
+                user_kanji.description.unwrap_or_default(),
+                group,
+                colour,
+                metadata_cell(user_kanji.stroke_count),
+                metadata_cell(user_kanji.jlpt_level),
+                metadata_cell(user_kanji.grade),
+                metadata_cell(user_kanji.frequency_rank),
+            ]).map_err(write_error)?;
+        }
+    }
+
+    let text = writer.into_inner()
+        .ok()
+        .and_then(|text| String::from_utf8(text).ok())
+        .ok_or("INVALID_FORMAT")?;
+
+    Ok(json!({ "text": text }).to_string())
+}
@@ -0,0 +1,228 @@
+use chrono::{Duration, NaiveDate, Utc};
+use diesel::{
+    pg::Pg,
+    prelude::*,
+    query_builder::{BoxedSqlQuery, SqlQuery},
+    sql_types::{BigInt, Integer, Nullable, Text},
+};
+use lib::schema::*;
+use lib::models::*;
+use serde_json::{json, Value};
+use crate::commands::{Eval, establish_connection};
+
+// Days ahead the due forecast covers, today included
+const FORECAST_DAYS: i64 = 30;
+
+// Reviews of the user narrowed down to an item kind and group, $1 user id, $2 kind, $3 group title
+const FILTERED_REVIEWS: &str = "
+    reviews r
+    LEFT JOIN kanji k ON k.id = r.kanji_id
+    LEFT JOIN vocab v ON v.id = r.vocab_id
+    LEFT JOIN groups g ON g.id = COALESCE(k.group_id, v.group_id)
+    WHERE r.user_id = $1
+      AND ($2::TEXT IS NULL OR ($2 = 'kanji') = (r.kanji_id IS NOT NULL))
+      AND ($3::TEXT IS NULL OR g.title = $3)";
+
+// Cards of items out of lessons that the user hasn't turned off and their item still has, the
+// stage counts and forecast only look at those. Reading cards of items left without readings keep
+// their review but aren't quizzed
+const ACTIVE_CARDS: &str = "
+      AND COALESCE(k.learned_at, v.learned_at) IS NOT NULL
+      AND NOT ((CASE WHEN r.kanji_id IS NULL THEN 'vocab_' ELSE 'kanji_' END) || r.card)
+        = ANY((SELECT disabled_cards FROM users WHERE id = $1)::TEXT[])
+      AND NOT (r.card = 'onyomi' AND NOT EXISTS (SELECT 1 FROM unnest(k.onyomi) reading WHERE btrim(reading) <> ''))
+      AND NOT (r.card = 'kunyomi' AND NOT EXISTS (SELECT 1 FROM unnest(k.kunyomi) reading WHERE btrim(reading) <> ''))
+      AND NOT (r.card = 'reading' AND NOT EXISTS (SELECT 1 FROM unnest(v.reading) reading WHERE btrim(reading) <> ''))";
+
+#[derive(QueryableByName)]
+struct DailyCount{
+    #[diesel(sql_type = Text)]
+    day: String,
+    #[diesel(sql_type = BigInt)]
+    reviews: i64,
+}
+
+#[derive(QueryableByName)]
+struct RetentionBucket{
+    #[diesel(sql_type = Text)]
+    bucket: String,
+    #[diesel(sql_type = BigInt)]
+    reviews: i64,
+    #[diesel(sql_type = BigInt)]
+    passed: i64,
+}
+
+#[derive(QueryableByName)]
+struct StageCount{
+    #[diesel(sql_type = Text)]
+    stage: String,
+    #[diesel(sql_type = BigInt)]
+    cards: i64,
+}
+
+#[derive(QueryableByName)]
+struct ForecastDay{
+    #[diesel(sql_type = Integer)]
+    day_offset: i32,
+    #[diesel(sql_type = BigInt)]
+    reviews: i64,
+}
+
+// Current and longest run of consecutive study days, the current one survives until a day is skipped
+fn streaks(days: &[NaiveDate], today: NaiveDate)-> (i64, i64){
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous = None::<NaiveDate>;
+
+    for day in days{
+        run = match previous{
+            Some(previous) if *day - previous == Duration::days(1) => run + 1,
+            _ => 1,
+        };
+        longest = longest.max(run);
+        previous = Some(*day);
+    }
+
+    let current = match previous{
+        Some(last) if today - last <= Duration::days(1) => run,
+        _ => 0,
+    };
+
+    (current, longest)
+}
+
+fn filtered(query: String, user: &User, kind: Option<&str>, group_title: Option<&str>)-> BoxedSqlQuery<'static, Pg, SqlQuery>{
+    diesel::sql_query(query)
+        .into_boxed()
+        .bind::<Integer, _>(user.id)
+        .bind::<Nullable<Text>, _>(kind.map(|kind| kind.to_owned()))
+        .bind::<Nullable<Text>, _>(group_title.map(|group_title| group_title.to_owned()))
+}
+
+// The queries are raw SQL, a failing one is reported rather than passed off as no reviews
+pub fn get_stats(user: &User, payload: String)-> Eval<String>{
+    let connection = &mut establish_connection();
+
+    if users::table.find(user.id)
+        .first::<User>(connection).is_err(){
+        return Err("INVALID_USER")
+    }
+
+    let payload = if payload.is_empty(){
+        Value::Null
+    }
+    else if let Ok(payload) = serde_json::from_str::<Value>(&payload){
+        payload
+    }
+    else{
+        return Err("INVALID_FORMAT");
+    };
+
+    let kind = payload["kind"].as_str();
+    if !matches!(kind, None | Some("kanji") | Some("vocab")){
+        return Err("INVALID_KIND");
+    }
+    let group_title = payload["group_title"].as_str();
+    if let Some(group_title) = group_title{
+        if Group::belonging_to(user)
+            .filter(groups::title.eq(group_title))
+            .first::<Group>(connection).is_err(){
+            return Err("INVALID_GROUP");
+        }
+    }
+
+    let daily = filtered(format!("
+        SELECT TO_CHAR(l.reviewed_at AT TIME ZONE 'UTC', 'YYYY-MM-DD') AS day, COUNT(*) AS reviews
+        FROM review_logs l
+        CROSS JOIN {FILTERED_REVIEWS}
+          AND r.id = l.review_id
+          AND NOT l.undone
+        GROUP BY day
+        ORDER BY day"), user, kind, group_title)
+        .load::<DailyCount>(connection)
+        .map_err(|_| "STATS_FAILED")?;
+
+    // Only answers to cards already past their first review say anything about retention
+    let retention = filtered(format!("
+        SELECT bucket, COUNT(*) AS reviews, COUNT(*) FILTER (WHERE grade <> 'again') AS passed
+        FROM (
+          SELECT l.grade, l.prior_interval_days,
+            CASE
+              WHEN l.prior_interval_days < 3 THEN '1-2'
+              WHEN l.prior_interval_days < 7 THEN '3-6'
+              WHEN l.prior_interval_days < 21 THEN '7-20'
+              WHEN l.prior_interval_days < 60 THEN '21-59'
+              ELSE '60+'
+            END AS bucket
+          FROM review_logs l
+          CROSS JOIN {FILTERED_REVIEWS}
+            AND r.id = l.review_id
+            AND NOT l.undone
+            AND l.prior_repetitions > 0
+        ) answers
+        GROUP BY bucket
+        ORDER BY MIN(prior_interval_days)"), user, kind, group_title)
+        .load::<RetentionBucket>(connection)
+        .map_err(|_| "STATS_FAILED")?;
+
+    let stages = filtered(format!("
+        SELECT
+          CASE
+            WHEN r.suspended THEN 'suspended'
+            WHEN r.last_review IS NULL THEN 'new'
+            WHEN r.repetitions = 0 OR r.interval_days < 7 THEN 'learning'
+            WHEN r.interval_days < 21 THEN 'young'
+            ELSE 'mature'
+          END AS stage,
+          COUNT(*) AS cards
+        FROM {FILTERED_REVIEWS}{ACTIVE_CARDS}
+        GROUP BY stage"), user, kind, group_title)
+        .load::<StageCount>(connection)
+        .map_err(|_| "STATS_FAILED")?;
+
+    // Overdue cards count towards today, buried ones towards the day they come back like get_due
+    let forecast_days = filtered(format!("
+        SELECT GREATEST((GREATEST(r.due, r.buried_until) AT TIME ZONE 'UTC')::DATE - (NOW() AT TIME ZONE 'UTC')::DATE, 0) AS day_offset,
+          COUNT(*) AS reviews
+        FROM {FILTERED_REVIEWS}{ACTIVE_CARDS}
+          AND NOT r.suspended
+          AND (GREATEST(r.due, r.buried_until) AT TIME ZONE 'UTC')::DATE < (NOW() AT TIME ZONE 'UTC')::DATE + {FORECAST_DAYS}
+        GROUP BY day_offset"), user, kind, group_title)
+        .load::<ForecastDay>(connection)
+        .map_err(|_| "STATS_FAILED")?;
+
+    let today = Utc::now().date_naive();
+    let study_days = daily.iter()
+        .filter_map(|daily| NaiveDate::parse_from_str(&daily.day, "%Y-%m-%d").ok())
+        .collect::<Vec<NaiveDate>>();
+    let (current_streak, longest_streak) = streaks(&study_days, today);
+
+    let mut forecast = vec![0; FORECAST_DAYS as usize];
+    for day in forecast_days{
+        if let Some(reviews) = forecast.get_mut(day.day_offset as usize){
+            *reviews = day.reviews;
+        }
+    }
+
+    let mut stage_counts = json!({ "new": 0, "learning": 0, "young": 0, "mature": 0, "suspended": 0 });
+    for stage in stages{
+        stage_counts[stage.stage] = json!(stage.cards);
+    }
+
+    Ok(json!({
+        "heatmap": daily.iter()
+            .map(|daily| json!({ "day": daily.day, "reviews": daily.reviews }))
+            .collect::<Vec<Value>>(),
+        "retention": retention.iter()
+            .map(|bucket| json!({
+                "interval_days": bucket.bucket,
+                "reviews": bucket.reviews,
+                "retention": bucket.passed as f64 / bucket.reviews as f64,
+            }))
+            .collect::<Vec<Value>>(),
+        "current_streak": current_streak,
+        "longest_streak": longest_streak,
+        "stages": stage_counts,
+        "forecast": forecast,
+    }).to_string())
+}